@@ -3,25 +3,29 @@
 //! It aims at being a simple logshipper that read logs from its inputs, transforms them using its filters
 //! and send them to its outputs.
 
-#[macro_use]
-extern crate nom;
-
 extern crate log_archive;
 
 extern crate docopt;
 
-use log_archive::configuration_items;
+use std::collections::HashMap;
+
 use log_archive::config;
+use log_archive::pipeline;
+use log_archive::hec::dead_letter;
+use log_archive::hec::quota::mask_token;
 use docopt::Docopt;
 
 // Write the Docopt usage string. dfrites ?
 static USAGE: &'static str = "
 Usage: kiosque [-c CONFIGFILE]
+       kiosque dlq list [-d DIRECTORY]
+       kiosque dlq replay [-c CONFIGFILE] [-d DIRECTORY]
        kiosque (--help | -h)
 
 Options:
     -h, --help     Show this screen.
     -c CONFIGFILE  Configuration file [default: kiosque.conf]
+    -d DIRECTORY   Dead-letter directory [default: ./dead-letters]
 ";
 
 fn main() {
@@ -32,12 +36,59 @@ fn main() {
 
     let config_file = args.get_str("-c");
 
+    if args.get_bool("list") {
+      return dlq_list(args.get_str("-d"));
+    }
+
     match config::read_config_file(config_file) {
-      Ok(configuration) => run(configuration),
+      Ok(configuration) => {
+        if args.get_bool("replay") {
+          dlq_replay(configuration, args.get_str("-d"))
+        } else {
+          run(configuration)
+        }
+      },
       Err(e) => panic!("Unable to parse config file at {}: {}", config_file, e)
     }
 }
 
+fn dlq_list(directory: &str) {
+  match dead_letter::list(directory) {
+    Ok(letters) => {
+      for (file_name, letter) in letters {
+        println!("{}: received={} remote={} token={} size={} error={}",
+                 file_name,
+                 letter.received,
+                 letter.remote_addr.unwrap_or("-".to_owned()),
+                 letter.token.map_or("-".to_owned(), |token| mask_token(&token)),
+                 letter.body.len(),
+                 letter.error);
+      }
+    },
+    Err(e) => panic!("Unable to list dead letters in {}: {}", directory, e)
+  }
+}
+
+/// Replays the dead letters through the outputs of the configuration, decoding them
+/// like its `splunk` input would.
+fn dlq_replay(mut conf: log_archive::config::Configuration, directory: &str) {
+  let mut params = HashMap::new();
+  params.insert("directory".to_owned(), directory.to_owned());
+  for &(ref name, ref input_params) in conf.inputs.iter() {
+    if name != "splunk" {
+      continue;
+    }
+    if let Some(line_facet) = input_params.as_ref().and_then(|p| p.get("line_facet")) {
+      params.insert("line_facet".to_owned(), line_facet.clone());
+    }
+  }
+  conf.inputs = vec![("dead-letter".to_owned(), Some(params))];
+  run(conf);
+}
+
 fn run(conf: log_archive::config::Configuration) {
   println!("{:?}", conf);
+  if let Err(e) = pipeline::run(conf) {
+    panic!("Unable to run pipeline: {}", e);
+  }
 }
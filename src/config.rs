@@ -1,143 +1,169 @@
-use std::str;
+//! Configuration files: sections such as `input` and `output`, each declaring plugins
+//! with optional `key = value` parameters, the `=` being optional.
 
-use nom::{IResult, multispace, space};
-use nom::IResult::*;
-use nom::{InputLength, IterIndices};
-use nom::{AsChar, ErrorKind};
-use nom::Err::Position;
-
-use std::io::prelude::*;
 use std::collections::HashMap;
-use std::ops::{Index, Range, RangeFrom};
+use std::io::prelude::*;
+use std::fs::File;
+use std::iter::Peekable;
 
-fn end_of_symbol(chr: char) -> bool {
-    !(chr == ' ' || 
-   chr == '#' || 
-   chr == '\n' || 
-   chr == '=' ||  // this is to work in key names. Will have to fixe someday
-   chr == '{' || chr == '}')
-}
+pub type PluginDeclaration = (String, Option<HashMap<String, String>>);
 
-pub fn until_eol<'a, T: ?Sized>(input: &'a T) -> IResult<&'a T, &'a T>
-    where T: Index<Range<usize>, Output = T> + Index<RangeFrom<usize>, Output = T>,
-          &'a T: IterIndices + InputLength
-{
-    let input_length = input.input_len();
-    if input_length == 0 {
-        return Error(Position(ErrorKind::MultiSpace, input));
-    }
+#[derive(Debug)]
+pub struct Configuration {
+    pub inputs: Vec<PluginDeclaration>,
+    /// Applied in order to every event, between the inputs and the outputs
+    pub filters: Vec<PluginDeclaration>,
+    pub outputs: Vec<PluginDeclaration>,
+}
 
-    let mut in_comment = false;
+#[derive(Debug, PartialEq)]
+enum Token {
+    Symbol(String),
+    Quoted(String),
+    Open,
+    Close,
+    Equal,
+}
 
-    for (idx, item) in input.iter_indices() {
-        let chr = item.as_char();
-        if chr == '#' {
-            in_comment = true;
-        }
-        if in_comment {
-            if chr == '\n' {
-                return Done(&input[idx..], &input[0..idx]);
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut chars = input.chars().peekable();
+
+    while let Some(chr) = chars.next() {
+        match chr {
+            '#' => {
+                while let Some(c) = chars.next() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
             }
-        } else {
-            if !(chr == ' ' || chr == '\t') || chr == '\n' {
-                return Done(&input[idx..], &input[0..idx]);
+            '{' => tokens.push(Token::Open),
+            '}' => tokens.push(Token::Close),
+            '=' => tokens.push(Token::Equal),
+            '"' => {
+                let mut value = String::new();
+                let mut closed = false;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => {
+                            closed = true;
+                            break;
+                        }
+                        '\\' if chars.peek() == Some(&'"') => {
+                            chars.next();
+                            value.push('"');
+                        }
+                        c => value.push(c),
+                    }
+                }
+                if !closed {
+                    return Err(format!("Unterminated string: \"{}", value));
+                }
+                tokens.push(Token::Quoted(value));
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut symbol = String::new();
+                symbol.push(c);
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '#' || c == '{' || c == '}' || c == '=' ||
+                       c == '"' {
+                        break;
+                    }
+                    symbol.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Symbol(symbol));
             }
         }
     }
-    Done(&input[input_length..], input)
+    Ok(tokens)
 }
 
-fn keys_and_values(input: &str) -> IResult<&str, HashMap<String, String>> {
-    let mut h: HashMap<String, String> = HashMap::new();
-
-    match keys_and_values_aggregator(input) {
-        IResult::Done(i, tuple_vec) => {
-            for &(k, v) in tuple_vec.iter() {
-                h.insert(k.to_owned().into(), v.to_owned().into());
+fn parse_params<'a, I>(tokens: &mut Peekable<I>) -> Result<HashMap<String, String>, String>
+    where I: Iterator<Item = &'a Token>
+{
+    let mut params = HashMap::new();
+    loop {
+        let key = match tokens.next() {
+            Some(&Token::Close) => return Ok(params),
+            Some(&Token::Symbol(ref key)) |
+            Some(&Token::Quoted(ref key)) => key.clone(),
+            Some(t) => return Err(format!("Expected a parameter name, found {:?}", t)),
+            None => return Err("Unexpected end of file, missing \"}\"".to_owned()),
+        };
+        // `key = value` and `key value` are both accepted
+        if tokens.peek() == Some(&&Token::Equal) {
+            tokens.next();
+        }
+        match tokens.next() {
+            Some(&Token::Symbol(ref value)) |
+            Some(&Token::Quoted(ref value)) => {
+                params.insert(key, value.clone());
             }
-            IResult::Done(i, h)
+            t => return Err(format!("Expected a value for \"{}\", found {:?}", key, t)),
         }
-        IResult::Incomplete(a) => IResult::Incomplete(a),
-        IResult::Error(a) => IResult::Error(a),
     }
 }
 
-named!(pub quoted_string <&str, &str>,
-       chain!(
-         tag_s!("\"")              ~
-         qs: take_until_s!("\"")   ~
-         tag_s!("\"")              ,
-         || { qs }
-         )
-      );
-
-// A symbol is anything between spaces, and followed by something.
-named!(object_symbol_name <&str, &str>,
-       chain!(
-         multispace? ~
-         symbol: take_while_s!( end_of_symbol ),
-           || { (symbol) } ));
-
-
-named!(pub multispace_and_comment <&str, Vec<&str> >, many1!(until_eol));
-
-named!(pub declaration <&str, &str>,
-       chain!(
-         symbol: object_symbol_name   ~
-         kv: chain!(
-             multispace_and_comment       ~
-             tag_s!("{")               ~
-             kv: keys_and_values       ~
-             tag_s!("}"),
-             || { kv }
-             )?
-         ,
-         || {
-           println!("Parms: {:?}", kv);
-           symbol })
-      );
-
-named!(pub key_value    <&str,(&str,&str)>,
-chain!(
-  key: object_symbol_name             ~
-  space?                              ~
-  tag_s!("=")                         ~
-  space?                              ~
-  val: alt!(
-    quoted_string                     |
-    object_symbol_name
-    )                                 ~
-  multispace_and_comment              ,
-  ||{(key, val)}
-  )
-);
-
-named!(pub keys_and_values_aggregator<&str, Vec<(&str,&str)> >,
-chain!(
-  kva: many0!(key_value),
-  || {kva} )
-);
-
-use std::fs::File;
+fn parse_plugins<'a, I>(tokens: &mut Peekable<I>) -> Result<Vec<PluginDeclaration>, String>
+    where I: Iterator<Item = &'a Token>
+{
+    let mut plugins = vec![];
+    loop {
+        match tokens.next() {
+            Some(&Token::Close) => return Ok(plugins),
+            Some(&Token::Symbol(ref name)) => {
+                let params = if tokens.peek() == Some(&&Token::Open) {
+                    tokens.next();
+                    Some(try!(parse_params(tokens)))
+                } else {
+                    None
+                };
+                plugins.push((name.clone(), params));
+            }
+            Some(t) => return Err(format!("Expected a plugin name, found {:?}", t)),
+            None => return Err("Unexpected end of file, missing \"}\"".to_owned()),
+        }
+    }
+}
 
-#[derive(Debug)]
-pub struct Configuration {
-    pub inputs: Vec<(String, Option<HashMap<String, String>>)>,
-    pub outputs: Vec<(String, Option<HashMap<String, String>>)>,
+pub fn parse_configuration(input: &str) -> Result<Configuration, String> {
+    let tokens = try!(tokenize(input));
+    let mut tokens = tokens.iter().peekable();
+    let mut configuration = Configuration {
+        inputs: vec![],
+        filters: vec![],
+        outputs: vec![],
+    };
+
+    while let Some(token) = tokens.next() {
+        let section = match token {
+            &Token::Symbol(ref section) => section.clone(),
+            t => return Err(format!("Expected a section name, found {:?}", t)),
+        };
+        if tokens.next() != Some(&Token::Open) {
+            return Err(format!("Expected \"{{\" after \"{}\"", section));
+        }
+        let plugins = try!(parse_plugins(&mut tokens));
+        match section.as_ref() {
+            "input" => configuration.inputs.extend(plugins),
+            "filter" => configuration.filters.extend(plugins),
+            "output" => configuration.outputs.extend(plugins),
+            s => return Err(format!("Unknown section \"{}\"", s)),
+        }
+    }
+    Ok(configuration)
 }
 
 pub fn read_config_file(filename: &str) -> Result<Configuration, String> {
     println!("Reading config file.");
-    let mut f = File::open(filename).unwrap();
+    let mut f = try!(File::open(filename).map_err(|e| format!("Open error: {:?}", e)));
     let mut s = String::new();
 
     match f.read_to_string(&mut s) {
-        Ok(_) => {
-            Ok(Configuration {
-                inputs: vec![],
-                outputs: vec![],
-            })
-        }
+        Ok(_) => parse_configuration(&s),
         Err(e) => Err(format!("Read error: {:?}", e)),
     }
 }
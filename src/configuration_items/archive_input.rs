@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+use std::sync::mpsc::{Receiver, SyncSender};
+
+use regex::Regex;
+
+use configuration_items::processor::{InputProcessor, ConfigurableFilter, config_opt,
+                                     config_bool};
+use event::{self, Event};
+use logmanager;
+use replay::{Needle, Replay, ReplayQuery};
+
+/// Re-emits the events of the log blocks written by the file output, oldest first, then
+/// stops. Used to route archived events through new outputs, e.g. to backfill.
+pub struct ArchiveInput {
+    name: String,
+}
+
+impl ArchiveInput {
+    pub fn new(name: String) -> ArchiveInput {
+        ArchiveInput { name: name }
+    }
+}
+
+impl ConfigurableFilter for ArchiveInput {
+    fn human_name(&self) -> &str {
+        self.name.as_ref()
+    }
+
+    fn mandatory_fields(&self) -> Vec<&str> {
+        vec!["directory"]
+    }
+}
+
+/// Times are epoch seconds or RFC 3339 dates.
+fn config_time(config: &Option<HashMap<String, String>>, key: &str) -> Option<u64> {
+    config_opt(config, key).map(|time| {
+        match event::parse_epoch_micros(&time).or_else(|| event::parse_rfc3339_micros(&time)) {
+            Some(time) => time,
+            None => panic!("Invalid value for \"{}\": {}", key, time),
+        }
+    })
+}
+
+fn query(config: &Option<HashMap<String, String>>) -> ReplayQuery {
+    ReplayQuery {
+        since: config_time(config, "since"),
+        until: config_time(config, "until"),
+        field: config_opt(config, "field"),
+        needle: config_opt(config, "query").map(|query| {
+            if config_bool(config, "regex", false) {
+                match Regex::new(&query) {
+                    Ok(re) => Needle::Regex(re),
+                    Err(e) => panic!("Invalid regex for \"query\": {}", e),
+                }
+            } else {
+                Needle::Substring(query)
+            }
+        }),
+    }
+}
+
+impl InputProcessor for ArchiveInput {
+    fn start(&self, config: &Option<HashMap<String, String>>) -> Receiver<Event> {
+        query(config);
+        self.invoke(config, ArchiveInput::handle_func)
+    }
+
+    fn handle_func(tx: SyncSender<Event>, config: Option<HashMap<String, String>>) {
+        let directory = config_opt(&config, "directory").unwrap();
+        let files = match logmanager::archive_files(&directory) {
+            Ok(files) => files,
+            Err(e) => panic!("Unable to list the archive in {}: {}", directory, e),
+        };
+
+        let replay = Replay::new(files, query(&config));
+        println!("Replaying {} blocks from {}", replay.remaining_blocks(), directory);
+        let mut count = 0;
+        for e in replay {
+            if tx.send(e).is_err() {
+                return;
+            }
+            count += 1;
+        }
+        println!("Replayed {} events from {}, stopping \"archive\" input.", count, directory);
+    }
+}
@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+use std::sync::mpsc::{Receiver, SyncSender};
+
+use configuration_items::processor::{FilterProcessor, ConfigurableFilter, config_str,
+                                     config_list};
+use date::{DateFilter as Dater, DateFormat, Timezone};
+use event::Event;
+
+/// Sets the time of events from a timestamp facet, instead of the time they were
+/// received at.
+pub struct DateFilter {
+    name: String,
+}
+
+impl DateFilter {
+    pub fn new(name: String) -> DateFilter {
+        DateFilter { name: name }
+    }
+}
+
+impl ConfigurableFilter for DateFilter {
+    fn human_name(&self) -> &str {
+        self.name.as_ref()
+    }
+}
+
+fn dater(config: &Option<HashMap<String, String>>) -> Dater {
+    let mut formats: Vec<DateFormat> = config_list(config, "format")
+        .iter()
+        .map(|f| DateFormat::from_name(f))
+        .collect();
+    if formats.is_empty() {
+        formats.push(DateFormat::Iso8601);
+    }
+    let timezone = config_str(config, "timezone", "UTC");
+
+    Dater {
+        source: config_str(config, "source", "timestamp"),
+        formats: formats,
+        timezone: match Timezone::from_name(&timezone) {
+            Some(timezone) => timezone,
+            None => panic!("Invalid value for \"timezone\": {}", timezone),
+        },
+        received_facet: config_str(config, "received_facet", "received_at"),
+        failure_facet: config_str(config, "failure_facet", "tags"),
+        failure_tag: config_str(config, "failure_tag", "_dateparsefailure"),
+    }
+}
+
+impl FilterProcessor for DateFilter {
+    fn start(&self,
+             rx: Receiver<Event>,
+             config: &Option<HashMap<String, String>>)
+             -> Receiver<Event> {
+        dater(config);
+        self.invoke(rx, config, DateFilter::handle_func)
+    }
+
+    fn handle_func(rx: Receiver<Event>,
+                   tx: SyncSender<Event>,
+                   config: Option<HashMap<String, String>>) {
+        let dater = dater(&config);
+        for mut e in rx.iter() {
+            dater.apply(&mut e);
+            if tx.send(e).is_err() {
+                return;
+            }
+        }
+    }
+}
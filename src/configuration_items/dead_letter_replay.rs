@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::mpsc::{Receiver, SyncSender};
+
+use configuration_items::processor::{InputProcessor, ConfigurableFilter, config_str};
+use event::{Delivery, Event};
+use hec::{self, dead_letter};
+
+/// Re-injects the payloads of a HEC dead-letter directory. A dead letter is removed
+/// once all its events have been written by a durable output, and kept when its
+/// payload is still rejected.
+pub struct DeadLetterReplay {
+    name: String,
+}
+
+impl DeadLetterReplay {
+    pub fn new(name: String) -> DeadLetterReplay {
+        DeadLetterReplay { name: name }
+    }
+}
+
+impl ConfigurableFilter for DeadLetterReplay {
+    fn human_name(&self) -> &str {
+        self.name.as_ref()
+    }
+
+    fn mandatory_fields(&self) -> Vec<&str> {
+        vec!["directory"]
+    }
+}
+
+fn remove_on_delivery(file_name: String) -> Box<Fn() + Send + Sync> {
+    Box::new(move || {
+        match fs::remove_file(&file_name) {
+            Ok(_) => println!("Replayed {}", file_name),
+            Err(e) => println!("Unable to remove replayed {}: {}", file_name, e),
+        }
+    })
+}
+
+impl InputProcessor for DeadLetterReplay {
+    fn start(&self, config: &Option<HashMap<String, String>>) -> Receiver<Event> {
+        self.invoke(config, DeadLetterReplay::handle_func)
+    }
+
+    fn handle_func(tx: SyncSender<Event>, config: Option<HashMap<String, String>>) {
+        let directory = config_str(&config, "directory", "./dead-letters");
+        let line_facet = config_str(&config, "line_facet", "stdout");
+
+        let letters = match dead_letter::list(&directory) {
+            Ok(letters) => letters,
+            Err(e) => {
+                println!("Unable to list dead letters in {}: {}", directory, e);
+                return;
+            }
+        };
+
+        for (file_name, letter) in letters {
+            match hec::decode_events(&letter.body, letter.received, &line_facet) {
+                Ok(mut events) => {
+                    let on_delivery = remove_on_delivery(file_name);
+                    if events.is_empty() {
+                        on_delivery();
+                        continue;
+                    }
+                    let delivery = Delivery::new(events.len(), on_delivery);
+                    for e in events.iter_mut() {
+                        e.acks.push(delivery.clone());
+                    }
+                    for e in events {
+                        if tx.send(e).is_err() {
+                            return;
+                        }
+                    }
+                }
+                Err((_, ix, error)) => {
+                    println!("{}: event {} is still rejected: {}", file_name, ix, error)
+                }
+            }
+        }
+    }
+}
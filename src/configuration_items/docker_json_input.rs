@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+use std::sync::mpsc::{Receiver, SyncSender};
+use std::thread;
+use std::time::Duration;
+
+use configuration_items::processor::{InputProcessor, ConfigurableFilter, config_opt,
+                                     config_str, config_parse, config_bool};
+use docker::{self, PartialLines};
+use event::{self, Event};
+use tail::Tailer;
+
+/// Longest `json-file` entry: docker splits logs in 16KB parts, which JSON escaping can
+/// grow up to six times.
+const MAX_ENTRY_LENGTH: usize = 6 * 16384 + 1024;
+
+/// Reads the `json-file` logs of the containers of a docker host. Events are mapped like
+/// the `splunk` input maps the docker `splunk` log driver: the short container id is the
+/// facility, the stream is the `source` facet and the line is stored under `line_facet`.
+pub struct DockerJsonInput {
+    name: String,
+}
+
+impl DockerJsonInput {
+    pub fn new(name: String) -> DockerJsonInput {
+        DockerJsonInput { name: name }
+    }
+}
+
+impl ConfigurableFilter for DockerJsonInput {
+    fn human_name(&self) -> &str {
+        self.name.as_ref()
+    }
+}
+
+impl InputProcessor for DockerJsonInput {
+    fn start(&self, config: &Option<HashMap<String, String>>) -> Receiver<Event> {
+        self.invoke(config, DockerJsonInput::handle_func)
+    }
+
+    fn handle_func(tx: SyncSender<Event>, config: Option<HashMap<String, String>>) {
+        let root = config_str(&config, "root", "/var/lib/docker/containers");
+        let poll_interval = config_parse(&config, "poll_interval", 1000u64);
+        let tag = config_opt(&config, "tag");
+        let line_facet = config_str(&config, "line_facet", "stdout");
+
+        let mut partials = PartialLines::new(config_parse(&config, "max_line", 1048576usize));
+        let mut tailer = Tailer::new(vec![format!("{}/*/*-json.log", root)],
+                                     config_opt(&config, "state_file"),
+                                     config_bool(&config, "from_beginning", false),
+                                     MAX_ENTRY_LENGTH);
+        let mut connected = true;
+        while connected {
+            let result = tailer.poll(|path, line, delivery| {
+                let entry = match docker::parse_json_file_line(&String::from_utf8_lossy(&line)) {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        println!("Skipping invalid line of {}: {}", path, e);
+                        delivery.confirm();
+                        return true;
+                    }
+                };
+                let complete = entry.log.ends_with('\n');
+                let key = format!("{}:{}", path, entry.stream);
+                partials.hold(&key, delivery);
+                let time = entry.time.unwrap_or(event::now_micros());
+                if let Some((time, log)) = partials.push(&key, time, &entry.log, complete) {
+                    let container_id = docker::container_id(path).unwrap_or(String::new());
+                    let mut e = Event::new(time);
+                    e.facility = tag.clone()
+                        .unwrap_or(container_id.chars().take(12).collect());
+                    e.facets.insert("container_id".to_owned(), container_id);
+                    e.facets.insert("source".to_owned(), entry.stream);
+                    e.facets.insert(line_facet.clone(),
+                                    log.trim_right_matches('\n').to_owned());
+                    e.acks = partials.release(&key);
+                    connected = tx.send(e).is_ok();
+                }
+                connected
+            });
+            if let Err(e) = result {
+                println!("Error while following docker logs: {}", e);
+            }
+            thread::sleep(Duration::from_millis(poll_interval));
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::thread;
+
+use rustc_serialize::json::Json;
+
+use configuration_items::processor::{InputProcessor, ConfigurableFilter, config_opt,
+                                     config_str, config_parse};
+use configuration_items::unix_input::remove_stale_socket;
+use docker_plugin::{self, ContainerInfo, EntryReader, LogEntry, ReadLogsQuery};
+use event::Event;
+use logmanager;
+use replay::{Replay, ReplayQuery};
+
+const PLUGIN_CONTENT_TYPE: &'static str = "application/vnd.docker.plugins.v1+json";
+/// Largest accepted request body
+const MAX_REQUEST_LENGTH: usize = 1 << 20;
+/// Lines queued for a `docker logs --follow` reader. Readers falling further behind are
+/// dropped, rather than holding back the container logs.
+const FOLLOW_BUFFER: usize = 10000;
+
+/// Docker logging plugin, listening on a unix socket. When `archive_directory` is set,
+/// `docker logs` reads the logs back from the log blocks found there.
+pub struct DockerPluginInput {
+    name: String,
+}
+
+impl DockerPluginInput {
+    pub fn new(name: String) -> DockerPluginInput {
+        DockerPluginInput { name: name }
+    }
+}
+
+impl ConfigurableFilter for DockerPluginInput {
+    fn human_name(&self) -> &str {
+        self.name.as_ref()
+    }
+}
+
+#[derive(Clone)]
+struct PluginOptions {
+    line_facet: String,
+    max_line: usize,
+    archive_directory: Option<String>,
+    /// FIFOs being read
+    files: Arc<Mutex<HashSet<String>>>,
+    /// `docker logs --follow` readers, by id of the containers being read
+    followers: Arc<Mutex<HashMap<String, Vec<SyncSender<Event>>>>>,
+}
+
+fn read_fifo(file: String, info: ContainerInfo, tx: SyncSender<Event>, options: PluginOptions) {
+    let fifo = match File::open(&file) {
+        Ok(fifo) => fifo,
+        Err(e) => {
+            println!("Unable to open docker log FIFO {}: {}", file, e);
+            options.files.lock().unwrap().remove(&file);
+            return;
+        }
+    };
+    println!("Reading logs of container {} from {}", info.id, file);
+
+    let container_id = info.id.clone();
+    options.followers.lock().unwrap().insert(container_id.clone(), vec![]);
+    let mut reader = BufReader::new(fifo);
+    let mut entries = EntryReader::new(info, options.line_facet.clone(), options.max_line);
+    loop {
+        let frame = match docker_plugin::read_frame(&mut reader, options.max_line + 1024) {
+            Ok(Some(frame)) => frame,
+            Ok(None) => break,
+            Err(e) => {
+                println!("Stopping to read {}: {}", file, e);
+                break;
+            }
+        };
+        match LogEntry::decode(&frame) {
+            Ok(entry) => {
+                if let Some(e) = entries.read_entry(entry) {
+                    if let Some(followers) = options.followers
+                        .lock()
+                        .unwrap()
+                        .get_mut(&container_id) {
+                        followers.retain(|follower| {
+                            match follower.try_send(e.clone()) {
+                                Ok(()) => true,
+                                Err(TrySendError::Full(_)) => {
+                                    println!("Dropping a slow log reader of {}", container_id);
+                                    false
+                                }
+                                Err(TrySendError::Disconnected(_)) => false,
+                            }
+                        });
+                    }
+                    if tx.send(e).is_err() {
+                        break;
+                    }
+                }
+            }
+            Err(e) => println!("Skipping log entry of {}: {}", file, e),
+        }
+    }
+    // Ends the followers
+    options.followers.lock().unwrap().remove(&container_id);
+    options.files.lock().unwrap().remove(&file);
+}
+
+fn error_reply(error: &str) -> Vec<u8> {
+    format!("{{\"Err\":{}}}", Json::String(error.to_owned())).into_bytes()
+}
+
+/// Streams the archived events of the container, oldest first, then its new events
+/// when following. Fails before anything is sent only.
+fn read_logs(request: &Json, options: &PluginOptions, stream: &UnixStream) -> Result<(), String> {
+    let directory = match options.archive_directory {
+        Some(ref directory) => directory,
+        None => return Err("Reading logs is not enabled".to_owned()),
+    };
+    let query = ReadLogsQuery::from_json(request);
+    let files = try!(logmanager::archive_files(directory).map_err(|e| format!("{}", e)));
+    // Subscribed first, so that no line logged while the archive is read is missed.
+    // Containers which are not running have nothing to follow.
+    let live = if query.follow {
+        options.followers.lock().unwrap().get_mut(&query.container_id).map(|followers| {
+            let (tx, rx) = sync_channel(FOLLOW_BUFFER);
+            followers.push(tx);
+            rx
+        })
+    } else {
+        None
+    };
+    // Blocks are only decoded when their time range matches, one after the other
+    let archived = Replay::new(files,
+                               ReplayQuery {
+                                   since: query.since,
+                                   until: query.until,
+                                   field: None,
+                                   needle: None,
+                               });
+
+    if let Err(e) = send_logs(&query, archived, live, &options.line_facet, stream) {
+        println!("Stopped sending logs of {}: {}", query.container_id, e);
+    }
+    Ok(())
+}
+
+fn send_logs(query: &ReadLogsQuery,
+             archived: Replay,
+             live: Option<Receiver<Event>>,
+             line_facet: &str,
+             mut out: &UnixStream)
+             -> io::Result<()> {
+    try!(out.write_all(&docker_plugin::stream_response("application/x-json-stream")));
+    let mut latest = try!(query.write_events(archived, line_facet, &mut out));
+    if let Some(live) = live {
+        // Lines archived meanwhile were sent already: older ones are skipped, and so is
+        // each line sent with the time of the last one
+        let last = latest.first().map(|e| e.time);
+        for e in live.iter() {
+            if last.map_or(false, |last| e.time < last) || !query.matches(&e) {
+                continue;
+            }
+            let sent = latest.iter().position(|sent| {
+                sent.time == e.time && sent.facets.get(line_facet) == e.facets.get(line_facet) &&
+                sent.facets.get("source") == e.facets.get("source")
+            });
+            match sent {
+                Some(ix) => {
+                    latest.remove(ix);
+                }
+                None => try!(docker_plugin::write_event(&e, line_facet, &mut out)),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn handle_request(path: &str,
+                  body: &Json,
+                  tx: &SyncSender<Event>,
+                  options: &PluginOptions)
+                  -> Vec<u8> {
+    let reply = match path {
+        "/Plugin.Activate" => "{\"Implements\":[\"LogDriver\"]}".to_owned().into_bytes(),
+        "/LogDriver.Capabilities" => {
+            format!("{{\"Cap\":{{\"ReadLogs\":{}}}}}",
+                    options.archive_directory.is_some())
+                .into_bytes()
+        }
+        "/LogDriver.StartLogging" => {
+            match body.find("File").and_then(|f| f.as_string()) {
+                Some(file) => {
+                    if options.files.lock().unwrap().insert(file.to_owned()) {
+                        let file = file.to_owned();
+                        let info = ContainerInfo::from_json(body.find("Info"));
+                        let tx = tx.clone();
+                        let options = options.clone();
+                        let _ = thread::Builder::new()
+                            .name("docker-fifo".to_string())
+                            .spawn(move || read_fifo(file, info, tx, options));
+                        error_reply("")
+                    } else {
+                        error_reply(&format!("Already reading {}", file))
+                    }
+                }
+                None => error_reply("Missing File"),
+            }
+        }
+        // Docker closes the FIFO once the container stops, which ends its reader
+        "/LogDriver.StopLogging" => error_reply(""),
+        path => error_reply(&format!("Unknown endpoint {}", path)),
+    };
+    docker_plugin::response(PLUGIN_CONTENT_TYPE, &reply)
+}
+
+fn handle_connection(stream: UnixStream, tx: SyncSender<Event>, options: PluginOptions) {
+    let mut reader = BufReader::new(&stream);
+    let request = match docker_plugin::read_request(&mut reader, MAX_REQUEST_LENGTH) {
+        Ok(request) => request,
+        Err(e) => {
+            println!("Invalid docker plugin request: {}", e);
+            return;
+        }
+    };
+    let body = Json::from_str(&String::from_utf8_lossy(&request.body)).unwrap_or(Json::Null);
+    let reply = match request.path.as_ref() {
+        // Logs are streamed, from this thread
+        "/LogDriver.ReadLogs" => {
+            match read_logs(&body, &options, &stream) {
+                Ok(()) => return,
+                Err(e) => docker_plugin::response(PLUGIN_CONTENT_TYPE, &error_reply(&e)),
+            }
+        }
+        path => handle_request(path, &body, &tx, &options),
+    };
+    if let Err(e) = (&stream).write_all(&reply) {
+        println!("Unable to reply to docker: {}", e);
+    }
+}
+
+impl InputProcessor for DockerPluginInput {
+    fn start(&self, config: &Option<HashMap<String, String>>) -> Receiver<Event> {
+        self.invoke(config, DockerPluginInput::handle_func)
+    }
+
+    fn handle_func(tx: SyncSender<Event>, config: Option<HashMap<String, String>>) {
+        let socket = config_str(&config, "socket", "/run/docker/plugins/kiosque.sock");
+        let options = PluginOptions {
+            line_facet: config_str(&config, "line_facet", "stdout"),
+            max_line: config_parse(&config, "max_line", 1048576usize),
+            archive_directory: config_opt(&config, "archive_directory"),
+            files: Arc::new(Mutex::new(HashSet::new())),
+            followers: Arc::new(Mutex::new(HashMap::new())),
+        };
+
+        remove_stale_socket(&socket);
+        let listener = match UnixListener::bind(&socket) {
+            Ok(listener) => listener,
+            Err(e) => panic!("Unable to listen for docker on {}: {}", socket, e),
+        };
+        println!("Listening for docker on {}", socket);
+
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let tx = tx.clone();
+                    let options = options.clone();
+                    let _ = thread::Builder::new()
+                        .name("docker-plugin-connection".to_string())
+                        .spawn(move || handle_connection(stream, tx, options));
+                }
+                Err(e) => println!("Error while accepting docker connection: {}", e),
+            }
+        }
+    }
+}
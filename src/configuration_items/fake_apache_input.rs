@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+use std::sync::mpsc::{Receiver, SyncSender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::{TimeZone, UTC};
+use rand::{Rng, SeedableRng, StdRng};
+
+use configuration_items::processor::{InputProcessor, ConfigurableFilter, config_opt,
+                                     config_str, config_parse};
+use event::{self, Event};
+use fake_apache;
+
+/// Start time of the events generated with a `seed` but no `start_time`
+const SEEDED_START_TIME: u64 = 1462290089;
+
+/// Generates Apache access log lines at a given rate, to load test a pipeline. With a
+/// `seed`, the same lines, with the same times, are generated on every run.
+pub struct FakeApache {
+    name: String,
+}
+
+impl FakeApache {
+    pub fn new(name: String) -> FakeApache {
+        FakeApache { name: name }
+    }
+}
+
+impl ConfigurableFilter for FakeApache {
+    fn human_name(&self) -> &str {
+        self.name.as_ref()
+    }
+}
+
+fn generate<R: Rng>(rng: &mut R,
+                    tx: SyncSender<Event>,
+                    config: Option<HashMap<String, String>>) {
+    let rate = config_parse(&config, "rate", 10.0f64);
+    let burst = config_parse(&config, "burst", 1u64).max(1);
+    let count = config_opt(&config, "count").map(|_| config_parse(&config, "count", 0u64));
+    let time_spread = config_parse(&config, "time_spread", 0i64);
+    let tag = config_str(&config, "tag", "fake-apache");
+    let line_facet = config_str(&config, "line_facet", "stdout");
+    // Times follow the schedule from `start_time`, or the clock when neither it nor a
+    // seed is set
+    let start_time = match config_opt(&config, "start_time") {
+        Some(_) => Some(config_parse(&config, "start_time", 0u64)),
+        None => config_opt(&config, "seed").map(|_| SEEDED_START_TIME),
+    };
+
+    let start = Instant::now();
+    let mut sent = 0u64;
+    while count.map(|c| sent < c).unwrap_or(true) {
+        // Bursts are sent on schedule, a rate of 0 sends as fast as possible
+        if rate > 0.0 {
+            let due = sent as f64 / rate;
+            let elapsed = start.elapsed();
+            let elapsed = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1e9;
+            if due > elapsed {
+                thread::sleep(Duration::from_millis(((due - elapsed) * 1000.0) as u64));
+            }
+        }
+
+        let in_burst = count.map(|c| burst.min(c - sent)).unwrap_or(burst);
+        let now = match start_time {
+            Some(start_time) if rate > 0.0 => {
+                start_time * 1000000 + (sent as f64 / rate * 1e6) as u64
+            }
+            Some(start_time) => start_time * 1000000,
+            None => event::now_micros(),
+        };
+        for _ in 0..in_burst {
+            let offset = if time_spread > 0 {
+                rng.gen_range(0, time_spread as u64 * 1000000)
+            } else {
+                0
+            };
+            let micros = now.saturating_sub(offset);
+            let time = UTC.timestamp((micros / 1000000) as i64,
+                                     (micros % 1000000) as u32 * 1000);
+
+            let mut e = Event::new(micros);
+            e.facility = tag.clone();
+            e.facets.insert(line_facet.clone(), fake_apache::apache_line(rng, time));
+            if tx.send(e).is_err() {
+                return;
+            }
+        }
+        sent += in_burst;
+    }
+    println!("Generated {} fake apache lines.", sent);
+}
+
+impl InputProcessor for FakeApache {
+    fn start(&self, config: &Option<HashMap<String, String>>) -> Receiver<Event> {
+        // Invalid numbers are reported before the generator starts
+        config_parse(config, "seed", 0usize);
+        config_parse(config, "count", 0u64);
+        config_parse(config, "rate", 0.0f64);
+        config_parse(config, "burst", 0u64);
+        config_parse(config, "time_spread", 0i64);
+        config_parse(config, "start_time", 0u64);
+        self.invoke(config, FakeApache::handle_func)
+    }
+
+    fn handle_func(tx: SyncSender<Event>, config: Option<HashMap<String, String>>) {
+        match config_opt(&config, "seed").map(|_| config_parse(&config, "seed", 0usize)) {
+            Some(seed) => {
+                let seed: &[usize] = &[seed];
+                generate(&mut StdRng::from_seed(seed), tx, config)
+            }
+            None => {
+                match StdRng::new() {
+                    Ok(mut rng) => generate(&mut rng, tx, config),
+                    Err(e) => println!("Unable to seed the fake apache generator: {}", e),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+use std::sync::mpsc::{Receiver, SyncSender};
+use std::thread;
+use std::time::Duration;
+
+use configuration_items::processor::{InputProcessor, ConfigurableFilter, config_opt,
+                                     config_str, config_parse, config_bool};
+use event::{self, Event};
+use tail::Tailer;
+
+pub struct FileInput {
+    name: String,
+}
+
+impl FileInput {
+    pub fn new(name: String) -> FileInput {
+        FileInput { name: name }
+    }
+}
+
+impl ConfigurableFilter for FileInput {
+    fn human_name(&self) -> &str {
+        self.name.as_ref()
+    }
+
+    fn mandatory_fields(&self) -> Vec<&str> {
+        vec!["path"]
+    }
+}
+
+impl InputProcessor for FileInput {
+    fn start(&self, config: &Option<HashMap<String, String>>) -> Receiver<Event> {
+        self.invoke(config, FileInput::handle_func)
+    }
+
+    fn handle_func(tx: SyncSender<Event>, config: Option<HashMap<String, String>>) {
+        let patterns = config_str(&config, "path", "")
+            .split(',')
+            .map(|p| p.trim().to_owned())
+            .filter(|p| !p.is_empty())
+            .collect();
+        let poll_interval = config_parse(&config, "poll_interval", 1000u64);
+        let tag = config_str(&config, "tag", "file");
+        let line_facet = config_str(&config, "line_facet", "message");
+
+        let mut tailer = Tailer::new(patterns,
+                                     config_opt(&config, "state_file"),
+                                     config_bool(&config, "from_beginning", false),
+                                     config_parse(&config, "max_line", 65536usize));
+        let mut connected = true;
+        while connected {
+            // Offsets are saved once the outputs confirmed the events
+            let result = tailer.poll(|path, line, delivery| {
+                let line = String::from_utf8_lossy(&line);
+                let mut e = Event::new(event::now_micros());
+                e.facility = tag.clone();
+                e.facets.insert(line_facet.clone(), line.trim_right_matches('\r').to_owned());
+                e.facets.insert("path".to_owned(), path.to_owned());
+                e.acks.push(delivery);
+                connected = tx.send(e).is_ok();
+                connected
+            });
+            if let Err(e) = result {
+                println!("Error while following files: {}", e);
+            }
+            thread::sleep(Duration::from_millis(poll_interval));
+        }
+    }
+}
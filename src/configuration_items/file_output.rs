@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use capnp::serialize;
+use chrono::offset::utc::UTC;
+
+use configuration_items::processor::{OutputProcessor, ConfigurableFilter, config_str,
+                                     config_parse};
+use event::{self, Event};
+
+pub struct FileOutput {
+    name: String,
+}
+
+impl FileOutput {
+    pub fn new(name: String) -> FileOutput {
+        FileOutput { name: name }
+    }
+}
+
+impl ConfigurableFilter for FileOutput {
+    fn human_name(&self) -> &str {
+        self.name.as_ref()
+    }
+}
+
+/// Keeps interpolated values from escaping the output directory.
+fn path_component(value: &str) -> String {
+    let cleaned = value.replace("/", "_").replace("\0", "_");
+    if cleaned.is_empty() || cleaned.starts_with(".") {
+        format!("_{}", cleaned)
+    } else {
+        cleaned
+    }
+}
+
+/// Replaces `#{host}`, `#{facility}` and `#{attrs.NAME}` in `directory` with the values
+/// of `event`.
+pub fn interpolate(directory: &str, event: &Event) -> String {
+    let mut result = String::new();
+    let mut rest = directory;
+
+    while let Some(start) = rest.find("#{") {
+        result.push_str(&rest[..start]);
+        match rest[start..].find('}') {
+            Some(end) => {
+                let name = &rest[start + 2..start + end];
+                let value = if name == "host" {
+                    Some(&event.hostname)
+                } else if name == "facility" {
+                    Some(&event.facility)
+                } else if name.starts_with("attrs.") {
+                    event.facets.get(&name["attrs.".len()..])
+                } else {
+                    None
+                };
+                result.push_str(&path_component(value.map(|v| &v[..]).unwrap_or("")));
+                rest = &rest[start + end + 1..];
+            }
+            None => {
+                result.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Writes `events` as a `Logblock` in `directory` and returns the file name. The block
+/// is synced to disk and renamed into place before returning.
+pub fn write_log_block(directory: &str, events: &[Event]) -> io::Result<String> {
+    try!(fs::create_dir_all(directory));
+
+    let message = event::log_block(events);
+
+    let now = UTC::now();
+    let file_name = format!("{}/{}-{}.capnp",
+                            directory,
+                            now.format("%Y%m%d-%H%M%S"),
+                            event::timestamp_micros(now));
+    let tmp_name = format!("{}.tmp", file_name);
+    {
+        let mut f = try!(File::create(&tmp_name));
+        try!(serialize::write_message(&mut f, &message));
+        try!(f.sync_all());
+    }
+    try!(fs::rename(&tmp_name, &file_name));
+    try!(try!(File::open(directory)).sync_all());
+    Ok(file_name)
+}
+
+fn flush(directory: &str, mut events: Vec<Event>) {
+    events.sort_by(|a, b| a.time.cmp(&b.time));
+    match write_log_block(directory, &events) {
+        Ok(file_name) => {
+            println!("Wrote {} lines to {}.", events.len(), file_name);
+            for e in events.iter() {
+                e.confirm();
+            }
+        }
+        Err(e) => println!("Unable to write {} lines to {}: {}", events.len(), directory, e),
+    }
+}
+
+impl OutputProcessor for FileOutput {
+    fn start(&self,
+             rx: Receiver<Event>,
+             config: &Option<HashMap<String, String>>)
+             -> Result<JoinHandle<()>, String> {
+        self.invoke(rx, config, FileOutput::handle_func)
+    }
+
+    fn confirms_delivery(&self) -> bool {
+        true
+    }
+
+    fn handle_func(rx: Receiver<Event>, config: Option<HashMap<String, String>>) {
+        let directory = config_str(&config, "directory", "./logs");
+        let flush_every = config_parse(&config, "flush_every", 5000usize);
+        let flush_interval = Duration::from_secs(config_parse(&config, "flush_interval", 5u64));
+
+        let mut buffers: HashMap<String, Vec<Event>> = HashMap::new();
+        let mut last_flush = Instant::now();
+
+        loop {
+            match rx.recv_timeout(flush_interval) {
+                Ok(event) => {
+                    let target = interpolate(&directory, &event);
+                    let full = {
+                        let buffer = buffers.entry(target.clone()).or_insert_with(Vec::new);
+                        buffer.push(event);
+                        buffer.len() >= flush_every
+                    };
+                    if full {
+                        if let Some(events) = buffers.remove(&target) {
+                            flush(&target, events);
+                        }
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+
+            if last_flush.elapsed() >= flush_interval {
+                for (target, events) in buffers.drain() {
+                    flush(&target, events);
+                }
+                last_flush = Instant::now();
+            }
+        }
+
+        for (target, events) in buffers.drain() {
+            flush(&target, events);
+        }
+    }
+}
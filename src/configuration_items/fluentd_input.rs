@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use std::io::{self, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{Receiver, SyncSender};
+use std::thread;
+
+use configuration_items::processor::{InputProcessor, ConfigurableFilter, config_str,
+                                     config_parse};
+use event::{self, Delivery, Event};
+use fluent;
+use msgpack;
+
+/// Fluentd forward protocol server. Chunks are acknowledged once all their events
+/// have been written by a durable output.
+pub struct FluentdInput {
+    name: String,
+}
+
+impl FluentdInput {
+    pub fn new(name: String) -> FluentdInput {
+        FluentdInput { name: name }
+    }
+}
+
+impl ConfigurableFilter for FluentdInput {
+    fn human_name(&self) -> &str {
+        self.name.as_ref()
+    }
+}
+
+fn acknowledge(writer: &Arc<Mutex<TcpStream>>, chunk: &str) {
+    let mut writer = writer.lock().unwrap();
+    if let Err(e) = writer.write_all(&fluent::ack_response(chunk)) {
+        println!("Unable to acknowledge fluentd chunk {}: {}", chunk, e);
+    }
+}
+
+fn handle_connection(stream: TcpStream, tx: SyncSender<Event>, max_length: usize) {
+    let peer = stream.peer_addr().map(|a| a.to_string()).unwrap_or("unknown".to_owned());
+    let writer = match stream.try_clone() {
+        Ok(writer) => Arc::new(Mutex::new(writer)),
+        Err(e) => {
+            println!("Closing fluentd connection from {}: {}", peer, e);
+            return;
+        }
+    };
+    let mut reader = BufReader::new(stream);
+    loop {
+        let message = match msgpack::read_value(&mut reader, max_length) {
+            Ok(message) => message,
+            Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return,
+            Err(e) => {
+                println!("Closing fluentd connection from {}: {}", peer, e);
+                return;
+            }
+        };
+        let request = match fluent::decode_request(&message, event::now_micros(), max_length) {
+            Ok(request) => request,
+            Err(e) => {
+                println!("Closing fluentd connection from {}: {}", peer, e);
+                return;
+            }
+        };
+
+        let mut events = request.events;
+        if let Some(chunk) = request.chunk {
+            if events.is_empty() {
+                acknowledge(&writer, &chunk);
+            } else {
+                let writer = writer.clone();
+                let delivery = Delivery::new(events.len(),
+                                             Box::new(move || acknowledge(&writer, &chunk)));
+                for e in events.iter_mut() {
+                    e.acks.push(delivery.clone());
+                }
+            }
+        }
+        for e in events {
+            if tx.send(e).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+impl InputProcessor for FluentdInput {
+    fn start(&self, config: &Option<HashMap<String, String>>) -> Receiver<Event> {
+        self.invoke(config, FluentdInput::handle_func)
+    }
+
+    fn handle_func(tx: SyncSender<Event>, config: Option<HashMap<String, String>>) {
+        let address = config_str(&config, "listen", "0.0.0.0:24224");
+        let max_length = config_parse(&config, "max_length", 16777216usize);
+
+        let listener = match TcpListener::bind(&address[..]) {
+            Ok(listener) => listener,
+            Err(e) => panic!("Unable to listen for fluentd on {}: {}", address, e),
+        };
+        println!("Listening for fluentd on {}", address);
+
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let tx = tx.clone();
+                    let _ = thread::Builder::new()
+                        .name("fluentd-connection".to_string())
+                        .spawn(move || handle_connection(stream, tx, max_length));
+                }
+                Err(e) => println!("Error while accepting fluentd connection: {}", e),
+            }
+        }
+    }
+}
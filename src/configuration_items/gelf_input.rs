@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+use std::io::BufReader;
+use std::net::{TcpListener, TcpStream, UdpSocket};
+use std::sync::mpsc::{Receiver, SyncSender};
+use std::thread;
+use std::time::Duration;
+
+use configuration_items::processor::{InputProcessor, ConfigurableFilter, config_opt,
+                                     config_str, config_parse};
+use event::{self, Event};
+use gelf::{self, ChunkAssembler};
+use lines;
+
+pub struct GelfInput {
+    name: String,
+}
+
+impl GelfInput {
+    pub fn new(name: String) -> GelfInput {
+        GelfInput { name: name }
+    }
+}
+
+impl ConfigurableFilter for GelfInput {
+    fn human_name(&self) -> &str {
+        self.name.as_ref()
+    }
+}
+
+#[derive(Clone)]
+struct GelfOptions {
+    line_facet: String,
+    tag: String,
+    max_length: usize,
+    chunk_timeout: Duration,
+    max_pending: usize,
+}
+
+impl GelfOptions {
+    fn to_event(&self, message: &[u8]) -> Option<Event> {
+        match gelf::parse_message(message, event::now_micros(), &self.line_facet, &self.tag) {
+            Ok(e) => Some(e),
+            Err(e) => {
+                println!("Skipping GELF message: {}", e);
+                None
+            }
+        }
+    }
+}
+
+fn listen_udp(address: String, tx: SyncSender<Event>, options: GelfOptions) {
+    let socket = match UdpSocket::bind(&address[..]) {
+        Ok(socket) => socket,
+        Err(e) => panic!("Unable to listen for GELF on udp {}: {}", address, e),
+    };
+    println!("Listening for GELF on udp {}", address);
+
+    let mut assembler = ChunkAssembler::new(options.chunk_timeout,
+                                            options.max_pending,
+                                            options.max_length);
+    let mut buffer = vec![0; 65536];
+    loop {
+        let size = match socket.recv_from(&mut buffer) {
+            Ok((size, _)) => size,
+            Err(e) => {
+                println!("Error while receiving GELF datagram: {}", e);
+                continue;
+            }
+        };
+        let message = match assembler.add(&buffer[..size]) {
+            Ok(Some(message)) => message,
+            Ok(None) => continue,
+            Err(e) => {
+                println!("Skipping GELF datagram: {}", e);
+                continue;
+            }
+        };
+        let message = match gelf::decompress(&message, options.max_length) {
+            Ok(message) => message,
+            Err(e) => {
+                println!("Skipping GELF message: {}", e);
+                continue;
+            }
+        };
+        if let Some(e) = options.to_event(&message) {
+            if tx.send(e).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+fn handle_connection(stream: TcpStream, tx: SyncSender<Event>, options: GelfOptions) {
+    let peer = stream.peer_addr().map(|a| a.to_string()).unwrap_or("unknown".to_owned());
+    let mut reader = BufReader::new(stream);
+    loop {
+        // One more byte than allowed tells longer messages apart
+        match lines::read_record(&mut reader, b'\0', options.max_length + 1) {
+            Ok(None) => return,
+            Ok(Some(frame)) => {
+                if frame.len() > options.max_length {
+                    println!("Closing GELF connection from {}: message larger than {} bytes",
+                             peer,
+                             options.max_length);
+                    return;
+                }
+                if frame.iter().all(|b| (*b as char).is_whitespace()) {
+                    continue;
+                }
+                if let Some(e) = options.to_event(&frame) {
+                    if tx.send(e).is_err() {
+                        return;
+                    }
+                }
+            }
+            Err(e) => {
+                println!("Closing GELF connection from {}: {}", peer, e);
+                return;
+            }
+        }
+    }
+}
+
+fn listen_tcp(address: String, tx: SyncSender<Event>, options: GelfOptions) {
+    let listener = match TcpListener::bind(&address[..]) {
+        Ok(listener) => listener,
+        Err(e) => panic!("Unable to listen for GELF on tcp {}: {}", address, e),
+    };
+    println!("Listening for GELF on tcp {}", address);
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let tx = tx.clone();
+                let options = options.clone();
+                let _ = thread::Builder::new()
+                    .name("gelf-connection".to_string())
+                    .spawn(move || handle_connection(stream, tx, options));
+            }
+            Err(e) => println!("Error while accepting GELF connection: {}", e),
+        }
+    }
+}
+
+impl InputProcessor for GelfInput {
+    fn start(&self, config: &Option<HashMap<String, String>>) -> Receiver<Event> {
+        if config_opt(config, "udp").is_none() && config_opt(config, "tcp").is_none() {
+            panic!("\"{}\" needs an \"udp\" or a \"tcp\" address to listen on",
+                   self.human_name());
+        }
+        self.invoke(config, GelfInput::handle_func)
+    }
+
+    fn handle_func(tx: SyncSender<Event>, config: Option<HashMap<String, String>>) {
+        let chunk_timeout = config_parse(&config, "chunk_timeout", 5000u64);
+        let options = GelfOptions {
+            line_facet: config_str(&config, "line_facet", "message"),
+            tag: config_str(&config, "tag", "gelf"),
+            max_length: config_parse(&config, "max_length", 1048576usize),
+            chunk_timeout: Duration::from_millis(chunk_timeout),
+            max_pending: config_parse(&config, "max_pending_messages", 1000usize),
+        };
+
+        let udp = config_opt(&config, "udp").map(|address| {
+            let tx = tx.clone();
+            let options = options.clone();
+            thread::Builder::new()
+                .name("gelf-udp".to_string())
+                .spawn(move || listen_udp(address, tx, options))
+                .unwrap()
+        });
+
+        if let Some(address) = config_opt(&config, "tcp") {
+            listen_tcp(address, tx, options);
+        } else if let Some(udp) = udp {
+            drop(tx);
+            let _ = udp.join();
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use std::sync::mpsc::{Receiver, SyncSender};
+
+use configuration_items::processor::{FilterProcessor, ConfigurableFilter, config_str,
+                                     config_bool, config_entries};
+use event::Event;
+use grok::{Grok, GrokFilter as Matcher};
+
+/// Extracts facets from a text facet, e.g. an apache access log line, with grok patterns.
+pub struct GrokFilter {
+    name: String,
+}
+
+impl GrokFilter {
+    pub fn new(name: String) -> GrokFilter {
+        GrokFilter { name: name }
+    }
+}
+
+impl ConfigurableFilter for GrokFilter {
+    fn human_name(&self) -> &str {
+        self.name.as_ref()
+    }
+
+    fn mandatory_fields(&self) -> Vec<&str> {
+        vec!["match"]
+    }
+}
+
+fn matcher(config: &Option<HashMap<String, String>>) -> Matcher {
+    let mut grok = Grok::new();
+    if let Some(ref config) = *config {
+        for (key, value) in config.iter() {
+            if key.starts_with("pattern.") {
+                grok.add_pattern(&key["pattern.".len()..], value);
+            }
+        }
+    }
+
+    // `match` first, then `match.1`, `match.2`...
+    let patterns = config_entries(config, "match")
+        .iter()
+        .map(|&(ref key, ref pattern)| {
+            match grok.compile(pattern) {
+                Ok(p) => p,
+                Err(e) => panic!("Invalid grok pattern for \"{}\": {}", key, e),
+            }
+        })
+        .collect();
+
+    Matcher {
+        source: config_str(config, "source", "message"),
+        patterns: patterns,
+        overwrite: config_bool(config, "overwrite", false),
+        failure_facet: config_str(config, "failure_facet", "tags"),
+        failure_tag: config_str(config, "failure_tag", "_grokparsefailure"),
+    }
+}
+
+impl FilterProcessor for GrokFilter {
+    fn start(&self,
+             rx: Receiver<Event>,
+             config: &Option<HashMap<String, String>>)
+             -> Receiver<Event> {
+        matcher(config);
+        self.invoke(rx, config, GrokFilter::handle_func)
+    }
+
+    fn handle_func(rx: Receiver<Event>,
+                   tx: SyncSender<Event>,
+                   config: Option<HashMap<String, String>>) {
+        let matcher = matcher(&config);
+        for mut e in rx.iter() {
+            matcher.apply(&mut e);
+            if tx.send(e).is_err() {
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,26 @@
+use std::io::Read;
+use std::str;
+
+use nickel::Request;
+
+/// Returns the first value of the `name` header of `request`, if it is valid UTF-8.
+pub fn header<D>(request: &Request<D>, name: &str) -> Option<String> {
+    request.origin
+        .headers
+        .get_raw(name)
+        .and_then(|values| values.first())
+        .and_then(|value| str::from_utf8(value).ok())
+        .map(|value| value.to_owned())
+}
+
+/// Reads the body of `request`, up to `max_length` bytes. Returns `None` when it is
+/// longer, without reading the rest.
+pub fn read_body<D>(request: &mut Request<D>, max_length: usize) -> Option<Vec<u8>> {
+    let mut body = vec![];
+    let _ = request.origin.by_ref().take(max_length as u64 + 1).read_to_end(&mut body);
+    if body.len() > max_length {
+        None
+    } else {
+        Some(body)
+    }
+}
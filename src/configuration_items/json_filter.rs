@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+use std::sync::mpsc::{Receiver, SyncSender};
+
+use configuration_items::processor::{FilterProcessor, ConfigurableFilter, config_str,
+                                     config_parse, config_bool};
+use event::Event;
+use fields::{Arrays, FieldFilter, FieldOptions, Format};
+
+/// Expands a facet holding a JSON object into facets.
+pub struct JsonFilter {
+    name: String,
+}
+
+impl JsonFilter {
+    pub fn new(name: String) -> JsonFilter {
+        JsonFilter { name: name }
+    }
+}
+
+impl ConfigurableFilter for JsonFilter {
+    fn human_name(&self) -> &str {
+        self.name.as_ref()
+    }
+}
+
+/// Reads the options shared by the `json` and `kv` filters.
+pub fn field_filter(config: &Option<HashMap<String, String>>, format: Format) -> FieldFilter {
+    let arrays = config_str(config, "arrays", "json");
+    let default_tag = match format {
+        Format::Json => "_jsonparsefailure",
+        Format::Kv => "_kvparsefailure",
+    };
+    FieldFilter {
+        source: config_str(config, "source", "message"),
+        format: format,
+        options: FieldOptions {
+            prefix: config_str(config, "prefix", ""),
+            flatten: config_bool(config, "flatten", true),
+            arrays: match Arrays::from_name(&arrays) {
+                Some(arrays) => arrays,
+                None => panic!("Invalid value for \"arrays\": {}", arrays),
+            },
+            max_depth: config_parse(config, "max_depth", 10usize),
+        },
+        overwrite: config_bool(config, "overwrite", false),
+        failure_facet: config_str(config, "failure_facet", "tags"),
+        failure_tag: config_str(config, "failure_tag", default_tag),
+    }
+}
+
+impl FilterProcessor for JsonFilter {
+    fn start(&self,
+             rx: Receiver<Event>,
+             config: &Option<HashMap<String, String>>)
+             -> Receiver<Event> {
+        field_filter(config, Format::Json);
+        self.invoke(rx, config, JsonFilter::handle_func)
+    }
+
+    fn handle_func(rx: Receiver<Event>,
+                   tx: SyncSender<Event>,
+                   config: Option<HashMap<String, String>>) {
+        let filter = field_filter(&config, Format::Json);
+        for mut e in rx.iter() {
+            filter.apply(&mut e);
+            if tx.send(e).is_err() {
+                return;
+            }
+        }
+    }
+}
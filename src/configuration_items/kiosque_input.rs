@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{channel, Receiver, SyncSender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use hyper::net::{HttpStream, NetworkStream, Openssl, Ssl};
+
+use configuration_items::processor::{InputProcessor, ConfigurableFilter, config_opt,
+                                     config_str, config_parse};
+use event::{Delivery, Event};
+use transport::{self, BatchReader};
+
+/// Receives the batches sent by the `kiosque` output of another node. Each batch is
+/// acknowledged once all its events are durably stored. With a `token`, connections
+/// which do not start with it are closed.
+pub struct KiosqueInput {
+    name: String,
+}
+
+impl KiosqueInput {
+    pub fn new(name: String) -> KiosqueInput {
+        KiosqueInput { name: name }
+    }
+}
+
+impl ConfigurableFilter for KiosqueInput {
+    fn human_name(&self) -> &str {
+        self.name.as_ref()
+    }
+}
+
+/// Connections have this many seconds to send the token.
+const TOKEN_TIMEOUT_SECONDS: u64 = 10;
+
+fn handle_connection(stream: TcpStream,
+                     ssl: Option<Arc<Openssl>>,
+                     token: Option<Arc<String>>,
+                     tx: SyncSender<Event>,
+                     max_length: usize) {
+    let peer = stream.peer_addr().map(|a| a.to_string()).unwrap_or("unknown".to_owned());
+    // Reads time out so that acknowledgements are sent while the sender is idle
+    let socket = match stream.try_clone() {
+        Ok(socket) => socket,
+        Err(e) => {
+            println!("Closing kiosque connection from {}: {}", peer, e);
+            return;
+        }
+    };
+    let mut stream: Box<NetworkStream + Send> = match ssl {
+        Some(ref ssl) => {
+            match ssl.wrap_server(HttpStream(stream)) {
+                Ok(stream) => Box::new(stream),
+                Err(e) => {
+                    println!("TLS handshake with {} failed: {}", peer, e);
+                    return;
+                }
+            }
+        }
+        None => Box::new(HttpStream(stream)),
+    };
+    let _ = socket.set_read_timeout(Some(Duration::from_millis(100)));
+
+    let (ack_tx, ack_rx) = channel();
+    let ack_tx = Arc::new(Mutex::new(ack_tx));
+    let mut batches = BatchReader::new(max_length);
+    let mut buffer = vec![0; 65536];
+    let connected = Instant::now();
+    let mut authenticated = token.is_none();
+    loop {
+        match stream.read(&mut buffer) {
+            Ok(0) => return,
+            Ok(size) => batches.push(&buffer[..size]),
+            Err(ref e) if transport::is_timeout(e) => {}
+            Err(e) => {
+                println!("Closing kiosque connection from {}: {}", peer, e);
+                return;
+            }
+        }
+
+        if let (false, Some(token)) = (authenticated, token.as_ref()) {
+            match batches.next_token() {
+                Ok(Some(ref sent)) if transport::same_token(sent, token.as_bytes()) => {
+                    authenticated = true;
+                }
+                Ok(Some(_)) => {
+                    println!("Closing kiosque connection from {}: invalid token", peer);
+                    return;
+                }
+                Ok(None) if connected.elapsed() <
+                            Duration::from_secs(TOKEN_TIMEOUT_SECONDS) => continue,
+                Ok(None) => {
+                    println!("Closing kiosque connection from {}: no token sent", peer);
+                    return;
+                }
+                Err(e) => {
+                    println!("Closing kiosque connection from {}: {}", peer, e);
+                    return;
+                }
+            }
+        }
+
+        loop {
+            match batches.next_batch() {
+                Ok(Some((id, events))) => {
+                    if events.is_empty() {
+                        let _ = ack_tx.lock().unwrap().send(id);
+                        continue;
+                    }
+                    let ack_tx = ack_tx.clone();
+                    let delivery = Delivery::new(events.len(),
+                                                 Box::new(move || {
+                                                     let _ = ack_tx.lock().unwrap().send(id);
+                                                 }));
+                    for mut e in events {
+                        e.acks.push(delivery.clone());
+                        if tx.send(e).is_err() {
+                            return;
+                        }
+                    }
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    println!("Closing kiosque connection from {}: {}", peer, e);
+                    return;
+                }
+            }
+        }
+
+        while let Ok(id) = ack_rx.try_recv() {
+            if let Err(e) = stream.write_all(&transport::encode_ack(id))
+                .and_then(|_| stream.flush()) {
+                println!("Closing kiosque connection from {}: {}", peer, e);
+                return;
+            }
+        }
+    }
+}
+
+impl InputProcessor for KiosqueInput {
+    fn start(&self, config: &Option<HashMap<String, String>>) -> Receiver<Event> {
+        self.invoke(config, KiosqueInput::handle_func)
+    }
+
+    fn handle_func(tx: SyncSender<Event>, config: Option<HashMap<String, String>>) {
+        let listen = config_str(&config, "listen", "127.0.0.1:7474");
+        let max_length = config_parse(&config, "max_length", 67108864usize);
+        let token = config_opt(&config, "token").map(Arc::new);
+        let ssl = match (config_opt(&config, "cert-file"), config_opt(&config, "cert-key")) {
+            (Some(cert_file), Some(cert_key)) => {
+                Some(Arc::new(Openssl::with_cert_and_key(cert_file, cert_key).unwrap()))
+            }
+            _ => None,
+        };
+
+        let listener = match TcpListener::bind(&listen[..]) {
+            Ok(listener) => listener,
+            Err(e) => panic!("Unable to listen for kiosque nodes on {}: {}", listen, e),
+        };
+        println!("Listening for kiosque nodes on {}", listen);
+
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let tx = tx.clone();
+                    let ssl = ssl.clone();
+                    let token = token.clone();
+                    let _ = thread::Builder::new()
+                        .name("kiosque-connection".to_string())
+                        .spawn(move || handle_connection(stream, ssl, token, tx, max_length));
+                }
+                Err(e) => println!("Error while accepting kiosque connection: {}", e),
+            }
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::mem;
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, TryRecvError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use hyper::net::{HttpStream, NetworkStream, Openssl, Ssl};
+use openssl::ssl::{SslContext, SslMethod, SSL_VERIFY_PEER};
+
+use configuration_items::processor::{OutputProcessor, ConfigurableFilter, config_opt,
+                                     config_str, config_parse, config_bool};
+use event::{self, Event};
+use transport::{self, AckReader, Outbox};
+
+/// Ships events to the `kiosque` input of another node, in batches. Events are
+/// confirmed once the other node acknowledges their batch, so acknowledgements reach
+/// the original clients once the events are stored at the far end.
+pub struct KiosqueOutput {
+    name: String,
+}
+
+impl KiosqueOutput {
+    pub fn new(name: String) -> KiosqueOutput {
+        KiosqueOutput { name: name }
+    }
+}
+
+impl ConfigurableFilter for KiosqueOutput {
+    fn human_name(&self) -> &str {
+        self.name.as_ref()
+    }
+
+    fn mandatory_fields(&self) -> Vec<&str> {
+        vec!["address"]
+    }
+}
+
+/// The server certificate is checked against `ca_file`, or the system certificates.
+fn tls_client(ca_file: Option<String>) -> Openssl {
+    let mut context = SslContext::new(SslMethod::Sslv23).unwrap();
+    context.set_verify(SSL_VERIFY_PEER, None);
+    let loaded = match ca_file {
+        Some(ref ca_file) => context.set_CA_file(ca_file),
+        None => context.set_default_verify_paths(),
+    };
+    if let Err(e) = loaded {
+        panic!("Unable to load the CA certificates: {}", e);
+    }
+    Openssl { context: Arc::new(context) }
+}
+
+struct Connection {
+    stream: Box<NetworkStream + Send>,
+    acks: AckReader,
+}
+
+fn connect(address: &str,
+           ssl: &Option<Openssl>,
+           token: &Option<String>)
+           -> io::Result<Connection> {
+    let stream = try!(TcpStream::connect(address));
+    let socket = try!(stream.try_clone());
+    let stream: Box<NetworkStream + Send> = match *ssl {
+        Some(ref ssl) => {
+            let host = address.rsplitn(2, ':').last().unwrap_or(address);
+            match ssl.wrap_client(HttpStream(stream), host) {
+                Ok(stream) => Box::new(stream),
+                Err(e) => return Err(io::Error::new(io::ErrorKind::Other, format!("{}", e))),
+            }
+        }
+        None => Box::new(HttpStream(stream)),
+    };
+    // Reads time out so that the output keeps batching while waiting for acks
+    try!(socket.set_read_timeout(Some(Duration::from_millis(100))));
+    let mut connection = Connection {
+        stream: stream,
+        acks: AckReader::new(),
+    };
+    if let Some(ref token) = *token {
+        try!(connection.send(&transport::encode_token(token)));
+    }
+    Ok(connection)
+}
+
+impl Connection {
+    fn send(&mut self, frame: &[u8]) -> io::Result<()> {
+        try!(self.stream.write_all(frame));
+        self.stream.flush()
+    }
+
+    /// Waits for acknowledgements until the read times out.
+    fn receive_acks(&mut self, outbox: &mut Outbox) -> io::Result<()> {
+        let mut buffer = [0; 4096];
+        match self.stream.read(&mut buffer) {
+            Ok(0) => {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed"))
+            }
+            Ok(size) => self.acks.push(&buffer[..size]),
+            Err(ref e) if transport::is_timeout(e) => {}
+            Err(e) => return Err(e),
+        }
+        while let Some(id) = self.acks.next_ack() {
+            outbox.acknowledge(id);
+        }
+        Ok(())
+    }
+}
+
+impl OutputProcessor for KiosqueOutput {
+    fn start(&self,
+             rx: Receiver<Event>,
+             config: &Option<HashMap<String, String>>)
+             -> Result<JoinHandle<()>, String> {
+        self.invoke(rx, config, KiosqueOutput::handle_func)
+    }
+
+    fn confirms_delivery(&self) -> bool {
+        true
+    }
+
+    fn handle_func(rx: Receiver<Event>, config: Option<HashMap<String, String>>) {
+        let address = config_str(&config, "address", "");
+        let batch_size = config_parse(&config, "batch_size", 5000usize);
+        let flush_interval = Duration::from_millis(config_parse(&config, "flush_interval",
+                                                                1000u64));
+        let retry_interval = Duration::from_millis(config_parse(&config, "retry_interval",
+                                                                5000u64));
+        let token = config_opt(&config, "token");
+        let ssl = if config_bool(&config, "tls", false) {
+            Some(tls_client(config_opt(&config, "ca-file")))
+        } else {
+            None
+        };
+
+        let mut outbox = Outbox::new(event::now_micros(),
+                                     config_parse(&config, "max_unacked_batches", 16usize));
+        let mut batch = vec![];
+        let mut last_flush = Instant::now();
+        let mut connection: Option<Connection> = None;
+        let mut last_attempt: Option<Instant> = None;
+        let mut closing = false;
+
+        while !closing || !batch.is_empty() || !outbox.is_empty() {
+            if !closing && !outbox.is_full() {
+                // Waiting for acks below already blocks, events are only waited for when
+                // there are none to wait for. Whatever is queued is taken at once.
+                let mut wait = outbox.is_empty() || connection.is_none();
+                while batch.len() < batch_size {
+                    let received = if wait {
+                        rx.recv_timeout(Duration::from_millis(100))
+                    } else {
+                        rx.try_recv().map_err(|e| match e {
+                            TryRecvError::Empty => RecvTimeoutError::Timeout,
+                            TryRecvError::Disconnected => RecvTimeoutError::Disconnected,
+                        })
+                    };
+                    wait = false;
+                    match received {
+                        Ok(e) => batch.push(e),
+                        Err(RecvTimeoutError::Timeout) => break,
+                        Err(RecvTimeoutError::Disconnected) => {
+                            closing = true;
+                            break;
+                        }
+                    }
+                }
+            }
+
+            if !batch.is_empty() && !outbox.is_full() &&
+               (batch.len() >= batch_size || last_flush.elapsed() >= flush_interval || closing) {
+                let frame = outbox.add(mem::replace(&mut batch, vec![]));
+                last_flush = Instant::now();
+                let sent = match connection {
+                    Some(ref mut connection) => connection.send(frame),
+                    None => Ok(()),
+                };
+                if let Err(e) = sent {
+                    println!("Lost connection to {}: {}", address, e);
+                    connection = None;
+                }
+            }
+
+            if connection.is_none() {
+                if last_attempt.map(|at| at.elapsed() < retry_interval).unwrap_or(false) {
+                    if outbox.is_full() || closing {
+                        thread::sleep(Duration::from_millis(100));
+                    }
+                    continue;
+                }
+                last_attempt = Some(Instant::now());
+                match connect(&address, &ssl, &token) {
+                    Ok(mut new_connection) => {
+                        println!("Connected to {}, sending {} pending batches.",
+                                 address,
+                                 outbox.len());
+                        let resent = outbox.frames()
+                            .into_iter()
+                            .fold(Ok(()), |result, frame| {
+                                result.and_then(|_| new_connection.send(frame))
+                            });
+                        match resent {
+                            Ok(()) => connection = Some(new_connection),
+                            Err(e) => println!("Lost connection to {}: {}", address, e),
+                        }
+                    }
+                    Err(e) => println!("Unable to connect to {}: {}", address, e),
+                }
+            }
+
+            let received = match connection {
+                Some(ref mut connection) if !outbox.is_empty() => {
+                    connection.receive_acks(&mut outbox)
+                }
+                _ => Ok(()),
+            };
+            if let Err(e) = received {
+                println!("Lost connection to {}: {}", address, e);
+                connection = None;
+            }
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use std::sync::mpsc::{Receiver, SyncSender};
+use std::thread;
+use std::time::Duration;
+
+use configuration_items::processor::{InputProcessor, ConfigurableFilter, config_opt,
+                                     config_str, config_parse, config_bool};
+use cri::{self, CriReader};
+use event::Event;
+use tail::Tailer;
+
+/// Reads the container log files of a kubernetes node, in the CRI format.
+pub struct KubernetesInput {
+    name: String,
+}
+
+impl KubernetesInput {
+    pub fn new(name: String) -> KubernetesInput {
+        KubernetesInput { name: name }
+    }
+}
+
+impl ConfigurableFilter for KubernetesInput {
+    fn human_name(&self) -> &str {
+        self.name.as_ref()
+    }
+}
+
+impl InputProcessor for KubernetesInput {
+    fn start(&self, config: &Option<HashMap<String, String>>) -> Receiver<Event> {
+        self.invoke(config, KubernetesInput::handle_func)
+    }
+
+    fn handle_func(tx: SyncSender<Event>, config: Option<HashMap<String, String>>) {
+        let patterns = config_str(&config, "path", "/var/log/containers/*.log")
+            .split(',')
+            .map(|p| p.trim().to_owned())
+            .filter(|p| !p.is_empty())
+            .collect();
+        let poll_interval = config_parse(&config, "poll_interval", 1000u64);
+        let max_line = config_parse(&config, "max_line", 1048576usize);
+
+        let mut reader = CriReader::new(config_str(&config, "tag", "kubernetes"),
+                                        config_str(&config, "line_facet", "stdout"),
+                                        max_line);
+        // Runtimes split lines in 16KB parts, the time, stream and tag come on top
+        let mut tailer = Tailer::new(patterns,
+                                     config_opt(&config, "state_file"),
+                                     config_bool(&config, "from_beginning", false),
+                                     max_line + 1024);
+        let mut connected = true;
+        while connected {
+            let result = tailer.poll(|path, line, delivery| {
+                match cri::parse_line(&String::from_utf8_lossy(&line)) {
+                    Ok(entry) => {
+                        if let Some(e) = reader.read_entry(path, entry, Some(delivery)) {
+                            connected = tx.send(e).is_ok();
+                        }
+                    }
+                    Err(e) => {
+                        println!("Skipping line of {}: {}", path, e);
+                        delivery.confirm();
+                    }
+                }
+                connected
+            });
+            if let Err(e) = result {
+                println!("Error while following container logs: {}", e);
+            }
+            thread::sleep(Duration::from_millis(poll_interval));
+        }
+    }
+}
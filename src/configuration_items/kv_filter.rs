@@ -0,0 +1,46 @@
+use std::collections::HashMap;
+use std::sync::mpsc::{Receiver, SyncSender};
+
+use configuration_items::processor::{FilterProcessor, ConfigurableFilter};
+use configuration_items::json_filter::field_filter;
+use event::Event;
+use fields::Format;
+
+/// Expands a facet holding logfmt `key=value` pairs into facets.
+pub struct KvFilter {
+    name: String,
+}
+
+impl KvFilter {
+    pub fn new(name: String) -> KvFilter {
+        KvFilter { name: name }
+    }
+}
+
+impl ConfigurableFilter for KvFilter {
+    fn human_name(&self) -> &str {
+        self.name.as_ref()
+    }
+}
+
+impl FilterProcessor for KvFilter {
+    fn start(&self,
+             rx: Receiver<Event>,
+             config: &Option<HashMap<String, String>>)
+             -> Receiver<Event> {
+        field_filter(config, Format::Kv);
+        self.invoke(rx, config, KvFilter::handle_func)
+    }
+
+    fn handle_func(rx: Receiver<Event>,
+                   tx: SyncSender<Event>,
+                   config: Option<HashMap<String, String>>) {
+        let filter = field_filter(&config, Format::Kv);
+        for mut e in rx.iter() {
+            filter.apply(&mut e);
+            if tx.send(e).is_err() {
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::mpsc::{Receiver, SyncSender};
+
+use hyper::net::Openssl;
+use hyper::status::StatusCode;
+use nickel::{Nickel, HttpRouter};
+
+use configuration_items::http::{header, read_body};
+use configuration_items::processor::{InputProcessor, ConfigurableFilter, config_str,
+                                     config_opt, config_parse};
+use event::Event;
+use loki::{LokiOptions, LokiReceiver};
+use queue;
+
+/// Loki push API receiver, on `/loki/api/v1/push`. Served over HTTPS when a certificate
+/// is configured.
+pub struct LokiInput {
+    name: String,
+}
+
+impl LokiInput {
+    pub fn new(name: String) -> LokiInput {
+        LokiInput { name: name }
+    }
+}
+
+impl ConfigurableFilter for LokiInput {
+    fn human_name(&self) -> &str {
+        self.name.as_ref()
+    }
+}
+
+impl InputProcessor for LokiInput {
+    fn start(&self, config: &Option<HashMap<String, String>>) -> Receiver<Event> {
+        self.invoke(config, LokiInput::handle_func)
+    }
+
+    fn handle_func(tx: SyncSender<Event>, config: Option<HashMap<String, String>>) {
+        let listen = config_str(&config, "listen", "127.0.0.1:3100");
+        let options = LokiOptions {
+            line_facet: config_str(&config, "line_facet", "message"),
+            tag: config_str(&config, "tag", "loki"),
+            max_length: config_parse(&config, "max_length", 16777216usize),
+            queue_size: config_parse(&config, "queue_size", queue::DEFAULT_CAPACITY),
+        };
+        let max_length = options.max_length;
+        let receiver = Arc::new(LokiReceiver::new(tx, options));
+
+        let mut server = Nickel::new();
+        server.post("/loki/api/v1/push",
+                    middleware! { |request|
+            let content_type = header(request, "Content-Type");
+            let content_encoding = header(request, "Content-Encoding");
+            match read_body(request, max_length) {
+                Some(body) => {
+                    receiver.push(content_type.as_ref().map(|c| &c[..]),
+                                  content_encoding.as_ref().map(|e| &e[..]),
+                                  &body)
+                }
+                None => (StatusCode::PayloadTooLarge, "Request body is too large".to_owned()),
+            }
+        });
+
+        match (config_opt(&config, "cert-file"), config_opt(&config, "cert-key")) {
+            (Some(cert_file), Some(cert_key)) => {
+                let ssl = Openssl::with_cert_and_key(cert_file, cert_key).unwrap();
+                server.listen_https(&listen[..], ssl);
+            }
+            _ => {
+                server.listen(&listen[..]);
+            }
+        }
+    }
+}
@@ -1,2 +1,82 @@
 pub mod processor;
+pub mod http;
 pub mod splunk_server;
+pub mod file_output;
+pub mod dead_letter_replay;
+pub mod syslog_input;
+pub mod file_input;
+pub mod stdin_input;
+pub mod fake_apache_input;
+pub mod gelf_input;
+pub mod fluentd_input;
+pub mod docker_json_input;
+pub mod kubernetes_input;
+pub mod docker_plugin_input;
+pub mod otlp_input;
+pub mod loki_input;
+pub mod tcp_input;
+pub mod unix_input;
+pub mod archive_input;
+pub mod kiosque_input;
+pub mod kiosque_output;
+pub mod multiline_filter;
+pub mod grok_filter;
+pub mod json_filter;
+pub mod kv_filter;
+pub mod date_filter;
+pub mod sanitize_filter;
+pub mod severity_filter;
+
+use configuration_items::processor::{InputProcessor, FilterProcessor, OutputProcessor};
+
+pub fn input_processor(name: &str) -> Option<Box<InputProcessor>> {
+    match name {
+        "splunk" => Some(Box::new(splunk_server::SplunkServer::new(name.to_owned()))),
+        "syslog" => Some(Box::new(syslog_input::SyslogInput::new(name.to_owned()))),
+        "otlp" => Some(Box::new(otlp_input::OtlpInput::new(name.to_owned()))),
+        "loki" => Some(Box::new(loki_input::LokiInput::new(name.to_owned()))),
+        "tcp" => Some(Box::new(tcp_input::TcpInput::new(name.to_owned()))),
+        "unix" => Some(Box::new(unix_input::UnixInput::new(name.to_owned()))),
+        "fluentd" => Some(Box::new(fluentd_input::FluentdInput::new(name.to_owned()))),
+        "gelf" => Some(Box::new(gelf_input::GelfInput::new(name.to_owned()))),
+        "docker-json" => {
+            Some(Box::new(docker_json_input::DockerJsonInput::new(name.to_owned())))
+        }
+        "docker-plugin" => {
+            Some(Box::new(docker_plugin_input::DockerPluginInput::new(name.to_owned())))
+        }
+        "kubernetes" => {
+            Some(Box::new(kubernetes_input::KubernetesInput::new(name.to_owned())))
+        }
+        "file" => Some(Box::new(file_input::FileInput::new(name.to_owned()))),
+        "archive" => Some(Box::new(archive_input::ArchiveInput::new(name.to_owned()))),
+        "kiosque" => Some(Box::new(kiosque_input::KiosqueInput::new(name.to_owned()))),
+        "stdin" => Some(Box::new(stdin_input::StdinInput::new(name.to_owned()))),
+        "fake-apache" => Some(Box::new(fake_apache_input::FakeApache::new(name.to_owned()))),
+        "dead-letter" => {
+            Some(Box::new(dead_letter_replay::DeadLetterReplay::new(name.to_owned())))
+        }
+        _ => None,
+    }
+}
+
+pub fn filter_processor(name: &str) -> Option<Box<FilterProcessor>> {
+    match name {
+        "multiline" => Some(Box::new(multiline_filter::MultilineFilter::new(name.to_owned()))),
+        "grok" => Some(Box::new(grok_filter::GrokFilter::new(name.to_owned()))),
+        "json" => Some(Box::new(json_filter::JsonFilter::new(name.to_owned()))),
+        "kv" => Some(Box::new(kv_filter::KvFilter::new(name.to_owned()))),
+        "date" => Some(Box::new(date_filter::DateFilter::new(name.to_owned()))),
+        "sanitize" => Some(Box::new(sanitize_filter::SanitizeFilter::new(name.to_owned()))),
+        "severity" => Some(Box::new(severity_filter::SeverityFilter::new(name.to_owned()))),
+        _ => None,
+    }
+}
+
+pub fn output_processor(name: &str) -> Option<Box<OutputProcessor>> {
+    match name {
+        "file" => Some(Box::new(file_output::FileOutput::new(name.to_owned()))),
+        "kiosque" => Some(Box::new(kiosque_output::KiosqueOutput::new(name.to_owned()))),
+        _ => None,
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, SyncSender};
+use std::time::{Duration, Instant};
+
+use regex::Regex;
+
+use configuration_items::processor::{FilterProcessor, ConfigurableFilter, config_opt,
+                                     config_str, config_parse};
+use event::Event;
+use multiline::{Multiline, MultilineOptions};
+
+/// Merges continuation lines, e.g. of stack traces, into the preceding event of the
+/// same source stream.
+pub struct MultilineFilter {
+    name: String,
+}
+
+impl MultilineFilter {
+    pub fn new(name: String) -> MultilineFilter {
+        MultilineFilter { name: name }
+    }
+}
+
+impl ConfigurableFilter for MultilineFilter {
+    fn human_name(&self) -> &str {
+        self.name.as_ref()
+    }
+}
+
+fn pattern(config: &Option<HashMap<String, String>>, key: &str) -> Option<Regex> {
+    config_opt(config, key).map(|pattern| {
+        match Regex::new(&pattern) {
+            Ok(re) => re,
+            Err(e) => panic!("Invalid regex for \"{}\": {}", key, e),
+        }
+    })
+}
+
+fn options(config: &Option<HashMap<String, String>>) -> MultilineOptions {
+    MultilineOptions {
+        start: pattern(config, "start_pattern"),
+        continuation: pattern(config, "continuation_pattern"),
+        line_facet: config_str(config, "line_facet", "message"),
+        key_facets: config_str(config, "key_facets", "source,path")
+            .split(',')
+            .map(|f| f.trim().to_owned())
+            .filter(|f| !f.is_empty())
+            .collect(),
+        max_lines: config_parse(config, "max_lines", 500usize),
+        max_streams: config_parse(config, "max_streams", 10000usize),
+        timeout: Duration::from_millis(config_parse(config, "timeout", 1000u64)),
+    }
+}
+
+impl FilterProcessor for MultilineFilter {
+    fn start(&self,
+             rx: Receiver<Event>,
+             config: &Option<HashMap<String, String>>)
+             -> Receiver<Event> {
+        if config_opt(config, "start_pattern").is_none() &&
+           config_opt(config, "continuation_pattern").is_none() {
+            panic!("\"{}\" needs a \"start_pattern\" or a \"continuation_pattern\"",
+                   self.human_name());
+        }
+        options(config);
+        self.invoke(rx, config, MultilineFilter::handle_func)
+    }
+
+    fn handle_func(rx: Receiver<Event>,
+                   tx: SyncSender<Event>,
+                   config: Option<HashMap<String, String>>) {
+        let mut multiline = Multiline::new(options(&config));
+        let tick = Duration::from_millis(100);
+
+        loop {
+            let mut complete = match rx.recv_timeout(tick) {
+                Ok(e) => multiline.push(e),
+                Err(RecvTimeoutError::Timeout) => vec![],
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            complete.extend(multiline.expire(Instant::now()));
+            for e in complete {
+                if tx.send(e).is_err() {
+                    return;
+                }
+            }
+        }
+
+        for e in multiline.flush() {
+            if tx.send(e).is_err() {
+                return;
+            }
+        }
+    }
+}
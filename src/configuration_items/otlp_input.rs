@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::mpsc::{Receiver, SyncSender};
+
+use hyper::net::Openssl;
+use nickel::{Nickel, HttpRouter, Response, MiddlewareResult, Halt};
+
+use configuration_items::http::{header, read_body};
+use configuration_items::processor::{InputProcessor, ConfigurableFilter, config_str,
+                                     config_opt, config_parse};
+use event::Event;
+use otlp::{OtlpOptions, OtlpReceiver, OtlpReply};
+use queue;
+
+/// OTLP/HTTP logs receiver, on `/v1/logs`. Served over HTTPS when a certificate is
+/// configured.
+pub struct OtlpInput {
+    name: String,
+}
+
+impl OtlpInput {
+    pub fn new(name: String) -> OtlpInput {
+        OtlpInput { name: name }
+    }
+}
+
+impl ConfigurableFilter for OtlpInput {
+    fn human_name(&self) -> &str {
+        self.name.as_ref()
+    }
+}
+
+/// Sends the reply body as is, since protobuf replies are binary.
+fn send_reply<'mw, D>(mut response: Response<'mw, D>,
+                      reply: OtlpReply)
+                      -> MiddlewareResult<'mw, D> {
+    response.set(reply.status);
+    response.headers_mut()
+        .set_raw("Content-Type", vec![reply.content_type.as_bytes().to_vec()]);
+    let mut stream = try!(response.start());
+    match stream.write_all(&reply.body) {
+        Ok(()) => Ok(Halt(stream)),
+        Err(e) => stream.bail(format!("Unable to send the OTLP reply: {}", e)),
+    }
+}
+
+impl InputProcessor for OtlpInput {
+    fn start(&self, config: &Option<HashMap<String, String>>) -> Receiver<Event> {
+        self.invoke(config, OtlpInput::handle_func)
+    }
+
+    fn handle_func(tx: SyncSender<Event>, config: Option<HashMap<String, String>>) {
+        let listen = config_str(&config, "listen", "127.0.0.1:4318");
+        let options = OtlpOptions {
+            line_facet: config_str(&config, "line_facet", "message"),
+            tag: config_str(&config, "tag", "otlp"),
+            max_line: config_parse(&config, "max_line", 1048576usize),
+            max_length: config_parse(&config, "max_length", 16777216usize),
+            queue_size: config_parse(&config, "queue_size", queue::DEFAULT_CAPACITY),
+        };
+        let max_length = options.max_length;
+        let receiver = Arc::new(OtlpReceiver::new(tx, options));
+
+        let mut server = Nickel::new();
+        server.post("/v1/logs",
+                    middleware! { |request, response|
+            let content_type = header(request, "Content-Type");
+            let content_encoding = header(request, "Content-Encoding");
+            let content_type = content_type.as_ref().map(|c| &c[..]);
+            let reply = match read_body(request, max_length) {
+                Some(body) => {
+                    receiver.export(content_type,
+                                    content_encoding.as_ref().map(|e| &e[..]),
+                                    &body)
+                }
+                None => receiver.too_large(content_type),
+            };
+            return send_reply(response, reply)
+        });
+
+        match (config_opt(&config, "cert-file"), config_opt(&config, "cert-key")) {
+            (Some(cert_file), Some(cert_key)) => {
+                let ssl = Openssl::with_cert_and_key(cert_file, cert_key).unwrap();
+                server.listen_https(&listen[..], ssl);
+            }
+            _ => {
+                server.listen(&listen[..]);
+            }
+        }
+    }
+}
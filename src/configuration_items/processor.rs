@@ -1,9 +1,12 @@
 use std::collections::HashMap;
+use std::str::FromStr;
 use std::sync::mpsc::{Receiver, SyncSender};
 use std::thread;
 use std::sync::mpsc::sync_channel;
 use std::thread::JoinHandle;
 
+use event::Event;
+
 pub trait ConfigurableFilter {
     fn human_name(&self) -> &str;
     fn mandatory_fields(&self) -> Vec<&str> {
@@ -33,22 +36,91 @@ pub trait ConfigurableFilter {
     }
 }
 
+/// Returns the configured value for `key`, if any.
+pub fn config_opt(config: &Option<HashMap<String, String>>, key: &str) -> Option<String> {
+    config.as_ref().and_then(|c| c.get(key)).map(|v| v.clone())
+}
+
+/// Returns the configured value for `key`, or `default` when it is not set.
+pub fn config_str(config: &Option<HashMap<String, String>>, key: &str, default: &str) -> String {
+    config_opt(config, key).unwrap_or(default.to_owned())
+}
+
+/// Parses the configured value for `key`, or returns `default` when it is not set.
+pub fn config_parse<T: FromStr>(config: &Option<HashMap<String, String>>,
+                                key: &str,
+                                default: T)
+                                -> T {
+    match config_opt(config, key) {
+        Some(value) => {
+            match value.parse::<T>() {
+                Ok(v) => v,
+                Err(_) => panic!("Invalid value for \"{}\": {}", key, value),
+            }
+        }
+        None => default,
+    }
+}
+
+/// Returns the keys and values configured for `key`, then `key.1`, `key.2`..., in
+/// that order.
+pub fn config_entries(config: &Option<HashMap<String, String>>,
+                      key: &str)
+                      -> Vec<(String, String)> {
+    let prefix = format!("{}.", key);
+    let mut entries = vec![];
+    if let Some(ref config) = *config {
+        for (k, value) in config.iter() {
+            if k == key {
+                entries.push((0, k.clone(), value.clone()));
+            } else if k.starts_with(&prefix) {
+                match k[prefix.len()..].parse::<u32>() {
+                    Ok(ix) => entries.push((ix, k.clone(), value.clone())),
+                    Err(_) => panic!("Invalid key \"{}\", expected {}.<number>", k, key),
+                }
+            }
+        }
+    }
+    entries.sort_by_key(|&(ix, _, _)| ix);
+    entries.into_iter().map(|(_, k, value)| (k, value)).collect()
+}
+
+/// Returns the values configured for `key`, then `key.1`, `key.2`..., in that order.
+pub fn config_list(config: &Option<HashMap<String, String>>, key: &str) -> Vec<String> {
+    config_entries(config, key).into_iter().map(|(_, value)| value).collect()
+}
+
+pub fn config_bool(config: &Option<HashMap<String, String>>, key: &str, default: bool) -> bool {
+    match config_opt(config, key) {
+        Some(value) => {
+            match value.as_ref() {
+                "true" | "yes" | "on" => true,
+                "false" | "no" | "off" => false,
+                _ => panic!("Invalid value for \"{}\": {}", key, value),
+            }
+        }
+        None => default,
+    }
+}
+
 pub trait InputProcessor: ConfigurableFilter {
     #[allow(unused_variables)]
-    fn start(&self, config: &Option<HashMap<String, String>>) -> Receiver<String> {
+    fn start(&self, config: &Option<HashMap<String, String>>) -> Receiver<Event> {
         panic!("Not implemented");
     }
 
     #[allow(unused_variables)]
-    fn handle_func(tx: SyncSender<String>, config: Option<HashMap<String, String>>) {
+    fn handle_func(tx: SyncSender<Event>, config: Option<HashMap<String, String>>)
+        where Self: Sized
+    {
         panic!("Not implemented");
     }
 
     fn invoke(&self,
               config: &Option<HashMap<String, String>>,
-              handle_func: fn(tx: SyncSender<String>,
+              handle_func: fn(tx: SyncSender<Event>,
                               config: Option<HashMap<String, String>>))
-              -> Receiver<String> {
+              -> Receiver<Event> {
         let (tx, rx) = sync_channel(10000);
         let conf = config.clone();
 
@@ -68,21 +140,29 @@ pub trait InputProcessor: ConfigurableFilter {
 
 pub trait OutputProcessor: ConfigurableFilter {
     fn start(&self,
-             _rx: Receiver<String>,
+             _rx: Receiver<Event>,
              _config: &Option<HashMap<String, String>>)
              -> Result<JoinHandle<()>, String> {
         panic!("Not implemented");
     }
 
+    /// Outputs returning `true` confirm the deliveries of the events they receive once
+    /// these events are durably stored.
+    fn confirms_delivery(&self) -> bool {
+        false
+    }
+
     #[allow(unused_variables)]
-    fn handle_func(rx: Receiver<String>, config: Option<HashMap<String, String>>) {
+    fn handle_func(rx: Receiver<Event>, config: Option<HashMap<String, String>>)
+        where Self: Sized
+    {
         panic!("Not implemented");
     }
 
     fn invoke(&self,
-              rx: Receiver<String>,
+              rx: Receiver<Event>,
               config: &Option<HashMap<String, String>>,
-              handle_func: fn(rx: Receiver<String>,
+              handle_func: fn(rx: Receiver<Event>,
                               config: Option<HashMap<String, String>>))
               -> Result<JoinHandle<()>, String> {
         let conf = config.clone();
@@ -97,3 +177,44 @@ pub trait OutputProcessor: ConfigurableFilter {
         }
     }
 }
+
+/// Filters sit between the inputs and the outputs: they receive every event and send
+/// on whatever should reach the outputs, in order. A filter dropping or merging events
+/// must keep their acknowledgements, or confirm them.
+pub trait FilterProcessor: ConfigurableFilter {
+    fn start(&self,
+             _rx: Receiver<Event>,
+             _config: &Option<HashMap<String, String>>)
+             -> Receiver<Event> {
+        panic!("Not implemented");
+    }
+
+    #[allow(unused_variables)]
+    fn handle_func(rx: Receiver<Event>,
+                   tx: SyncSender<Event>,
+                   config: Option<HashMap<String, String>>)
+        where Self: Sized
+    {
+        panic!("Not implemented");
+    }
+
+    fn invoke(&self,
+              rx: Receiver<Event>,
+              config: &Option<HashMap<String, String>>,
+              handle_func: fn(rx: Receiver<Event>,
+                              tx: SyncSender<Event>,
+                              config: Option<HashMap<String, String>>))
+              -> Receiver<Event> {
+        let (tx, filtered) = sync_channel(10000);
+        let conf = config.clone();
+
+        let run_loop = thread::Builder::new().name("run_loop".to_string()).spawn(move || {
+            handle_func(rx, tx, conf);
+        });
+
+        match run_loop {
+            Ok(_) => filtered,
+            Err(e) => panic!("Unable to spawn {} filter thread: {}", self.human_name(), e),
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+use std::sync::mpsc::{Receiver, SyncSender};
+
+use configuration_items::processor::{FilterProcessor, ConfigurableFilter, config_str,
+                                     config_bool};
+use event::Event;
+use sanitize::{sanitize_event, SanitizeOptions};
+
+/// Strips colors and other terminal escape sequences, and control characters, so lines
+/// are readable and searchable.
+pub struct SanitizeFilter {
+    name: String,
+}
+
+impl SanitizeFilter {
+    pub fn new(name: String) -> SanitizeFilter {
+        SanitizeFilter { name: name }
+    }
+}
+
+impl ConfigurableFilter for SanitizeFilter {
+    fn human_name(&self) -> &str {
+        self.name.as_ref()
+    }
+}
+
+fn options(config: &Option<HashMap<String, String>>) -> SanitizeOptions {
+    let facets = config_str(config, "facets", "message,stdout,stderr");
+    SanitizeOptions {
+        facets: if facets.trim() == "*" {
+            vec![]
+        } else {
+            facets.split(',')
+                .map(|f| f.trim().to_owned())
+                .filter(|f| !f.is_empty())
+                .collect()
+        },
+        keep_whitespace: config_bool(config, "keep_whitespace", true),
+        collapse_replacements: config_bool(config, "collapse_replacements", false),
+        replacement: config_str(config, "replacement", "?"),
+    }
+}
+
+impl FilterProcessor for SanitizeFilter {
+    fn start(&self,
+             rx: Receiver<Event>,
+             config: &Option<HashMap<String, String>>)
+             -> Receiver<Event> {
+        options(config);
+        self.invoke(rx, config, SanitizeFilter::handle_func)
+    }
+
+    fn handle_func(rx: Receiver<Event>,
+                   tx: SyncSender<Event>,
+                   config: Option<HashMap<String, String>>) {
+        let options = options(&config);
+        for mut e in rx.iter() {
+            sanitize_event(&mut e, &options);
+            if tx.send(e).is_err() {
+                return;
+            }
+        }
+    }
+}
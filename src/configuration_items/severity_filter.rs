@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use std::sync::mpsc::{Receiver, SyncSender};
+
+use configuration_items::processor::{FilterProcessor, ConfigurableFilter, config_opt,
+                                     config_str, config_list};
+use event::Event;
+use severity::{Level, Rule, SeverityDetector, SeverityOptions};
+
+/// Infers a normalized `level` facet from the markers found in lines.
+pub struct SeverityFilter {
+    name: String,
+}
+
+impl SeverityFilter {
+    pub fn new(name: String) -> SeverityFilter {
+        SeverityFilter { name: name }
+    }
+}
+
+impl ConfigurableFilter for SeverityFilter {
+    fn human_name(&self) -> &str {
+        self.name.as_ref()
+    }
+}
+
+fn names(config: &Option<HashMap<String, String>>, key: &str, default: &str) -> Vec<String> {
+    config_str(config, key, default)
+        .split(',')
+        .map(|f| f.trim().to_owned())
+        .filter(|f| !f.is_empty())
+        .collect()
+}
+
+fn detector(config: &Option<HashMap<String, String>>) -> SeverityDetector {
+    let rules = config_list(config, "rule")
+        .iter()
+        .map(|rule| {
+            match Rule::parse(rule) {
+                Ok(rule) => rule,
+                Err(e) => panic!("Invalid severity rule {}: {}", rule, e),
+            }
+        })
+        .collect();
+    let default = config_opt(config, "default").map(|name| {
+        match Level::from_name(&name) {
+            Some(level) => level,
+            None => panic!("Invalid value for \"default\": {}", name),
+        }
+    });
+
+    SeverityDetector::new(SeverityOptions {
+        text_facets: names(config, "facets", "message,stdout,stderr"),
+        level_fields: names(config, "level_fields", "level,severity,loglevel,log.level"),
+        rules: rules,
+        level_facet: config_str(config, "level_facet", "level"),
+        default: default,
+    })
+}
+
+impl FilterProcessor for SeverityFilter {
+    fn start(&self,
+             rx: Receiver<Event>,
+             config: &Option<HashMap<String, String>>)
+             -> Receiver<Event> {
+        detector(config);
+        self.invoke(rx, config, SeverityFilter::handle_func)
+    }
+
+    fn handle_func(rx: Receiver<Event>,
+                   tx: SyncSender<Event>,
+                   config: Option<HashMap<String, String>>) {
+        let detector = detector(&config);
+        for mut e in rx.iter() {
+            detector.apply(&mut e);
+            if tx.send(e).is_err() {
+                return;
+            }
+        }
+    }
+}
@@ -1,5 +1,30 @@
-use configuration_items::processor::{InputProcessor, ConfigurableFilter};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::mpsc::{Receiver, SyncSender};
+use std::time::Duration;
 
+use hyper::net::Openssl;
+use hyper::status::StatusCode;
+use nickel::{Nickel, HttpRouter, QueryString, Request, Response};
+use nickel::mimes::MediaType;
+
+use configuration_items::http::{header, read_body};
+use configuration_items::processor::{InputProcessor, ConfigurableFilter, config_str,
+                                     config_bool, config_opt, config_parse};
+use event::Event;
+use queue;
+use hec::{Collector, CollectorOptions, HecRequest};
+use hec::ack::AckLimits;
+use hec::dead_letter::DeadLetterStore;
+use hec::quota::Limits;
+use hec::response::{HecStatus, Reply};
+
+static EVENT_PATHS: &'static [&'static str] = &["/services/collector/event/1.0",
+                                                "/services/collector/event",
+                                                "/services/collector"];
+
+static HEALTH_PATHS: &'static [&'static str] = &["/services/collector/health/1.0",
+                                                 "/services/collector/health"];
 
 pub struct SplunkServer {
     name: String,
@@ -11,6 +36,23 @@ impl SplunkServer {
     }
 }
 
+/// Sends `reply` as JSON, with its `Retry-After` header if any.
+fn respond<D>(response: &mut Response<D>, reply: Reply) -> (StatusCode, String) {
+    response.set(MediaType::Json);
+    if let Some(retry_after) = reply.retry_after {
+        response.headers_mut()
+            .set_raw("Retry-After", vec![retry_after.to_string().into_bytes()]);
+    }
+    (reply.status, reply.body)
+}
+
+/// The HEC channel comes from the `X-Splunk-Request-Channel` header or the
+/// `channel` query parameter.
+fn request_channel<D>(request: &mut Request<D>) -> Option<String> {
+    header(request, "X-Splunk-Request-Channel")
+        .or_else(|| request.query().get("channel").map(|c| c.to_owned()))
+}
+
 impl ConfigurableFilter for SplunkServer {
     fn human_name(&self) -> &str {
         self.name.as_ref()
@@ -20,3 +62,120 @@ impl ConfigurableFilter for SplunkServer {
         vec![]
     }
 }
+
+impl InputProcessor for SplunkServer {
+    fn start(&self, config: &Option<HashMap<String, String>>) -> Receiver<Event> {
+        self.invoke(config, SplunkServer::handle_func)
+    }
+
+    fn handle_func(tx: SyncSender<Event>, config: Option<HashMap<String, String>>) {
+        let listen = config_str(&config, "listen", "127.0.0.1:6767");
+        let cert_file = config_str(&config, "cert-file", "assets/server.crt");
+        let cert_key = config_str(&config, "cert-key", "assets/server.key");
+        let max_length = config_parse(&config, "max_length", 16777216usize);
+        let options = CollectorOptions {
+            ack: config_bool(&config, "ack", false),
+            ack_limits: AckLimits {
+                timeout: Duration::from_secs(config_parse(&config, "ack_timeout", 600)),
+                max_ids_per_channel: config_parse(&config, "ack_max_ids_per_channel", 100000),
+                max_channels: config_parse(&config, "ack_max_channels", 10000),
+            },
+            tokens: config_str(&config, "tokens", "")
+                .split(',')
+                .map(|t| t.trim().to_owned())
+                .filter(|t| !t.is_empty())
+                .collect(),
+            line_facet: config_str(&config, "line_facet", "stdout"),
+            dead_letters: config_opt(&config, "dead_letter_directory").map(|directory| {
+                DeadLetterStore::new(directory,
+                                     config_parse(&config, "dead_letter_max_files", 1000),
+                                     config_parse(&config, "dead_letter_max_bytes", 100 << 20))
+            }),
+            limits: Limits {
+                events_per_second: config_opt(&config, "max_events_per_second")
+                    .map(|_| config_parse(&config, "max_events_per_second", 0.0)),
+                bytes_per_second: config_opt(&config, "max_bytes_per_second")
+                    .map(|_| config_parse(&config, "max_bytes_per_second", 0.0)),
+                burst_seconds: config_parse(&config, "burst_seconds", 1.0),
+                daily_bytes: config_opt(&config, "daily_quota_bytes")
+                    .map(|_| config_parse(&config, "daily_quota_bytes", 0)),
+            },
+            queue_size: config_parse(&config, "queue_size", queue::DEFAULT_CAPACITY),
+        };
+        let collector = Arc::new(Collector::new(tx, options));
+
+        let ssl = Openssl::with_cert_and_key(cert_file, cert_key).unwrap();
+        let mut server = Nickel::new();
+
+        for path in EVENT_PATHS.iter() {
+            server.options(*path,
+                           middleware! { |_, mut response|
+                respond(&mut response, Reply::new(HecStatus::Success))
+            });
+
+            let collector = collector.clone();
+            server.post(*path,
+                        middleware! { |request, mut response|
+                let channel = request_channel(request);
+                let authorization = header(request, "Authorization");
+                let remote_addr = request.origin.remote_addr.to_string();
+                let reply = match read_body(request, max_length) {
+                    Some(body) => {
+                        collector.post_events(&HecRequest {
+                            channel: channel.as_ref().map(|c| &c[..]),
+                            authorization: authorization.as_ref().map(|a| &a[..]),
+                            remote_addr: Some(&remote_addr),
+                            body: &String::from_utf8(body).unwrap_or(String::new()),
+                        })
+                    }
+                    None => Reply::new(HecStatus::RequestTooLarge),
+                };
+                respond(&mut response, reply)
+            });
+        }
+
+        let ack_collector = collector.clone();
+        server.post("/services/collector/ack",
+                    middleware! { |request, mut response|
+            let channel = request_channel(request);
+            let authorization = header(request, "Authorization");
+            let remote_addr = request.origin.remote_addr.to_string();
+            let reply = match read_body(request, max_length) {
+                Some(body) => {
+                    ack_collector.query_acks(&HecRequest {
+                        channel: channel.as_ref().map(|c| &c[..]),
+                        authorization: authorization.as_ref().map(|a| &a[..]),
+                        remote_addr: Some(&remote_addr),
+                        body: &String::from_utf8(body).unwrap_or(String::new()),
+                    })
+                }
+                None => Reply::new(HecStatus::RequestTooLarge),
+            };
+            respond(&mut response, reply)
+        });
+
+        for path in HEALTH_PATHS.iter() {
+            let collector = collector.clone();
+            server.get(*path,
+                       middleware! { |_, mut response|
+                respond(&mut response, collector.health())
+            });
+        }
+
+        let throttled_collector = collector.clone();
+        server.get("/kiosque/throttled",
+                   middleware! { |request, mut response|
+            let authorization = header(request, "Authorization");
+            let remote_addr = request.origin.remote_addr.to_string();
+            let reply = throttled_collector.throttled(&HecRequest {
+                channel: None,
+                authorization: authorization.as_ref().map(|a| &a[..]),
+                remote_addr: Some(&remote_addr),
+                body: "",
+            });
+            respond(&mut response, reply)
+        });
+
+        server.listen_https(&listen[..], ssl);
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead};
+use std::sync::mpsc::{Receiver, SyncSender};
+
+use configuration_items::processor::{InputProcessor, ConfigurableFilter, config_str,
+                                     config_parse};
+use event::{self, Event};
+use lines;
+
+/// Reads records from the standard input until its end. Once the input is exhausted,
+/// the input stops and the pipeline shuts down after the outputs are flushed.
+pub struct StdinInput {
+    name: String,
+}
+
+impl StdinInput {
+    pub fn new(name: String) -> StdinInput {
+        StdinInput { name: name }
+    }
+}
+
+impl ConfigurableFilter for StdinInput {
+    fn human_name(&self) -> &str {
+        self.name.as_ref()
+    }
+}
+
+pub fn delimiter(name: &str) -> u8 {
+    match name {
+        "newline" => b'\n',
+        "nul" => b'\0',
+        _ => panic!("Unknown stdin delimiter \"{}\", expected \"newline\" or \"nul\"", name),
+    }
+}
+
+impl InputProcessor for StdinInput {
+    fn start(&self, config: &Option<HashMap<String, String>>) -> Receiver<Event> {
+        delimiter(&config_str(config, "delimiter", "newline"));
+        self.invoke(config, StdinInput::handle_func)
+    }
+
+    fn handle_func(tx: SyncSender<Event>, config: Option<HashMap<String, String>>) {
+        let delimiter = delimiter(&config_str(&config, "delimiter", "newline"));
+        let tag = config_str(&config, "tag", "stdin");
+        let line_facet = config_str(&config, "line_facet", "message");
+        let max_line = config_parse(&config, "max_line", 65536usize);
+
+        let stdin = io::stdin();
+        if let Err(e) = forward(&mut stdin.lock(), delimiter, &tag, &line_facet, max_line, &tx) {
+            println!("Error while reading stdin: {}", e);
+        }
+        println!("End of stdin, stopping \"stdin\" input.");
+    }
+}
+
+/// Sends the non empty records of a stream until its end, or until the pipeline stops.
+/// With the `\n` delimiter, `\r\n` ends records too.
+pub fn forward<R: BufRead>(reader: &mut R,
+                           delimiter: u8,
+                           tag: &str,
+                           line_facet: &str,
+                           max_line: usize,
+                           tx: &SyncSender<Event>)
+                           -> io::Result<()> {
+    loop {
+        let record = if delimiter == b'\n' {
+            try!(lines::read_line(reader, max_line))
+        } else {
+            try!(lines::read_record(reader, delimiter, max_line))
+        };
+        let record = match record {
+            Some(record) => record,
+            None => break,
+        };
+        if record.is_empty() {
+            continue;
+        }
+        let mut e = Event::new(event::now_micros());
+        e.facility = tag.to_owned();
+        e.facets.insert(line_facet.to_owned(), String::from_utf8_lossy(&record).into_owned());
+        if tx.send(e).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
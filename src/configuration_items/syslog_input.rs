@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+use std::io::BufReader;
+use std::net::{TcpListener, TcpStream, UdpSocket};
+use std::sync::mpsc::{Receiver, SyncSender};
+use std::thread;
+
+use configuration_items::processor::{InputProcessor, ConfigurableFilter, config_opt,
+                                     config_str, config_parse};
+use event::{self, Event};
+use syslog;
+
+pub struct SyslogInput {
+    name: String,
+}
+
+impl SyslogInput {
+    pub fn new(name: String) -> SyslogInput {
+        SyslogInput { name: name }
+    }
+}
+
+impl ConfigurableFilter for SyslogInput {
+    fn human_name(&self) -> &str {
+        self.name.as_ref()
+    }
+}
+
+fn listen_udp(address: String, tx: SyncSender<Event>, line_facet: String, max_length: usize) {
+    let socket = match UdpSocket::bind(&address[..]) {
+        Ok(socket) => socket,
+        Err(e) => panic!("Unable to listen for syslog on udp {}: {}", address, e),
+    };
+    println!("Listening for syslog on udp {}", address);
+
+    let mut buffer = vec![0; max_length];
+    loop {
+        match socket.recv_from(&mut buffer) {
+            Ok((size, _)) => {
+                let line = String::from_utf8_lossy(&buffer[..size]);
+                let e = syslog::parse(&line).into_event(event::now_micros(), &line_facet);
+                if tx.send(e).is_err() {
+                    return;
+                }
+            }
+            Err(e) => println!("Error while receiving syslog datagram: {}", e),
+        }
+    }
+}
+
+fn handle_connection(stream: TcpStream,
+                     tx: SyncSender<Event>,
+                     line_facet: String,
+                     max_length: usize) {
+    let peer = stream.peer_addr().map(|a| a.to_string()).unwrap_or("unknown".to_owned());
+    let mut reader = BufReader::new(stream);
+    loop {
+        match syslog::read_frame(&mut reader, max_length) {
+            Ok(Some(line)) => {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let e = syslog::parse(&line).into_event(event::now_micros(), &line_facet);
+                if tx.send(e).is_err() {
+                    return;
+                }
+            }
+            Ok(None) => return,
+            Err(e) => {
+                println!("Closing syslog connection from {}: {}", peer, e);
+                return;
+            }
+        }
+    }
+}
+
+fn listen_tcp(address: String, tx: SyncSender<Event>, line_facet: String, max_length: usize) {
+    let listener = match TcpListener::bind(&address[..]) {
+        Ok(listener) => listener,
+        Err(e) => panic!("Unable to listen for syslog on tcp {}: {}", address, e),
+    };
+    println!("Listening for syslog on tcp {}", address);
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let tx = tx.clone();
+                let line_facet = line_facet.clone();
+                let _ = thread::Builder::new()
+                    .name("syslog-connection".to_string())
+                    .spawn(move || handle_connection(stream, tx, line_facet, max_length));
+            }
+            Err(e) => println!("Error while accepting syslog connection: {}", e),
+        }
+    }
+}
+
+impl InputProcessor for SyslogInput {
+    fn start(&self, config: &Option<HashMap<String, String>>) -> Receiver<Event> {
+        if config_opt(config, "udp").is_none() && config_opt(config, "tcp").is_none() {
+            panic!("\"{}\" needs an \"udp\" or a \"tcp\" address to listen on",
+                   self.human_name());
+        }
+        self.invoke(config, SyslogInput::handle_func)
+    }
+
+    fn handle_func(tx: SyncSender<Event>, config: Option<HashMap<String, String>>) {
+        let line_facet = config_str(&config, "line_facet", "message");
+        let max_length = config_parse(&config, "max_length", 65536usize);
+
+        let udp = config_opt(&config, "udp").map(|address| {
+            let tx = tx.clone();
+            let line_facet = line_facet.clone();
+            thread::Builder::new()
+                .name("syslog-udp".to_string())
+                .spawn(move || listen_udp(address, tx, line_facet, max_length))
+                .unwrap()
+        });
+
+        if let Some(address) = config_opt(&config, "tcp") {
+            listen_tcp(address, tx, line_facet, max_length);
+        } else if let Some(udp) = udp {
+            drop(tx);
+            let _ = udp.join();
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+use std::io::BufReader;
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{Receiver, SyncSender};
+use std::thread;
+
+use configuration_items::processor::{InputProcessor, ConfigurableFilter, config_str,
+                                     config_parse};
+use event::Event;
+use lines::{self, Codec, LineOptions};
+
+/// Newline delimited plain text or JSON over TCP, for tools that cannot speak HEC.
+pub struct TcpInput {
+    name: String,
+}
+
+impl TcpInput {
+    pub fn new(name: String) -> TcpInput {
+        TcpInput { name: name }
+    }
+}
+
+impl ConfigurableFilter for TcpInput {
+    fn human_name(&self) -> &str {
+        self.name.as_ref()
+    }
+
+    fn mandatory_fields(&self) -> Vec<&str> {
+        vec!["listen"]
+    }
+}
+
+/// Reads the `codec`, `line_facet`, `tag` and `max_line` settings shared by the socket
+/// inputs.
+pub fn line_options(config: &Option<HashMap<String, String>>, default_tag: &str) -> LineOptions {
+    let codec = config_str(config, "codec", "plain");
+    LineOptions {
+        codec: match Codec::from_name(&codec) {
+            Some(codec) => codec,
+            None => panic!("Unknown codec \"{}\", expected \"plain\" or \"json\"", codec),
+        },
+        line_facet: config_str(config, "line_facet", "message"),
+        tag: config_str(config, "tag", default_tag),
+        max_line: config_parse(config, "max_line", 65536usize),
+    }
+}
+
+fn handle_connection(stream: TcpStream, tx: SyncSender<Event>, options: LineOptions) {
+    let peer = stream.peer_addr().map(|a| a.to_string()).unwrap_or("unknown".to_owned());
+    let source = vec![("peer_address".to_owned(), peer.clone())];
+    let mut reader = BufReader::new(stream);
+    if let Err(e) = lines::forward(&mut reader, &source, &tx, &options) {
+        println!("Closing tcp connection from {}: {}", peer, e);
+    }
+}
+
+impl InputProcessor for TcpInput {
+    fn start(&self, config: &Option<HashMap<String, String>>) -> Receiver<Event> {
+        line_options(config, "tcp");
+        self.invoke(config, TcpInput::handle_func)
+    }
+
+    fn handle_func(tx: SyncSender<Event>, config: Option<HashMap<String, String>>) {
+        let address = config_str(&config, "listen", "");
+        let options = line_options(&config, "tcp");
+
+        let listener = match TcpListener::bind(&address[..]) {
+            Ok(listener) => listener,
+            Err(e) => panic!("Unable to listen on tcp {}: {}", address, e),
+        };
+        println!("Listening for lines on tcp {}", address);
+
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let tx = tx.clone();
+                    let options = options.clone();
+                    let _ = thread::Builder::new()
+                        .name("tcp-connection".to_string())
+                        .spawn(move || handle_connection(stream, tx, options));
+                }
+                Err(e) => println!("Error while accepting tcp connection: {}", e),
+            }
+        }
+    }
+}
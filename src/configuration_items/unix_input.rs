@@ -0,0 +1,192 @@
+use std::cmp;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufReader};
+use std::mem;
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::{UnixDatagram, UnixListener, UnixStream};
+use std::sync::mpsc::{Receiver, SyncSender};
+use std::thread;
+
+use libc;
+
+use configuration_items::processor::{InputProcessor, ConfigurableFilter, config_str,
+                                     config_parse};
+use configuration_items::tcp_input::line_options;
+use event::{self, Event};
+use lines::{self, LineOptions};
+
+/// Newline delimited plain text or JSON over a unix domain socket, either `stream` or
+/// `datagram`. Stream connections are tagged with the credentials of the peer process.
+pub struct UnixInput {
+    name: String,
+}
+
+impl UnixInput {
+    pub fn new(name: String) -> UnixInput {
+        UnixInput { name: name }
+    }
+}
+
+impl ConfigurableFilter for UnixInput {
+    fn human_name(&self) -> &str {
+        self.name.as_ref()
+    }
+
+    fn mandatory_fields(&self) -> Vec<&str> {
+        vec!["path"]
+    }
+}
+
+/// `struct ucred`, as filled by `SO_PEERCRED`
+#[repr(C)]
+struct PeerCredentials {
+    pid: libc::pid_t,
+    uid: libc::uid_t,
+    gid: libc::gid_t,
+}
+
+/// Returns the pid, uid and gid of the process at the other end of `stream`.
+fn peer_credentials(stream: &UnixStream) -> io::Result<PeerCredentials> {
+    let mut credentials = PeerCredentials {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut length = mem::size_of::<PeerCredentials>() as libc::socklen_t;
+    let result = unsafe {
+        libc::getsockopt(stream.as_raw_fd(),
+                         libc::SOL_SOCKET,
+                         libc::SO_PEERCRED,
+                         &mut credentials as *mut PeerCredentials as *mut libc::c_void,
+                         &mut length)
+    };
+    if result == 0 {
+        Ok(credentials)
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+fn handle_connection(stream: UnixStream, tx: SyncSender<Event>, options: LineOptions) {
+    let source = match peer_credentials(&stream) {
+        Ok(credentials) => {
+            vec![("peer_pid".to_owned(), credentials.pid.to_string()),
+                 ("peer_uid".to_owned(), credentials.uid.to_string()),
+                 ("peer_gid".to_owned(), credentials.gid.to_string())]
+        }
+        Err(e) => {
+            println!("Unable to get the credentials of a unix peer: {}", e);
+            vec![]
+        }
+    };
+    let mut reader = BufReader::new(stream);
+    if let Err(e) = lines::forward(&mut reader, &source, &tx, &options) {
+        println!("Closing unix connection: {}", e);
+    }
+}
+
+fn listen_stream(path: String, tx: SyncSender<Event>, options: LineOptions) {
+    let listener = match UnixListener::bind(&path) {
+        Ok(listener) => listener,
+        Err(e) => panic!("Unable to listen on unix socket {}: {}", path, e),
+    };
+    println!("Listening for lines on unix socket {}", path);
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let tx = tx.clone();
+                let options = options.clone();
+                let _ = thread::Builder::new()
+                    .name("unix-connection".to_string())
+                    .spawn(move || handle_connection(stream, tx, options));
+            }
+            Err(e) => println!("Error while accepting unix connection: {}", e),
+        }
+    }
+}
+
+/// Each datagram holds one or more lines. Datagrams sent from a bound socket are tagged
+/// with its path.
+fn listen_datagram(path: String,
+                   tx: SyncSender<Event>,
+                   options: LineOptions,
+                   max_length: usize) {
+    let socket = match UnixDatagram::bind(&path) {
+        Ok(socket) => socket,
+        Err(e) => panic!("Unable to listen on unix socket {}: {}", path, e),
+    };
+    println!("Listening for datagrams on unix socket {}", path);
+
+    // One more byte than allowed tells truncated datagrams apart
+    let mut buffer = vec![0; max_length + 1];
+    loop {
+        match socket.recv_from(&mut buffer) {
+            Ok((size, _)) if size > max_length => {
+                println!("Dropping unix datagram larger than {} bytes", max_length);
+            }
+            Ok((size, address)) => {
+                let source = match address.as_pathname() {
+                    Some(peer) => vec![("peer_path".to_owned(), peer.display().to_string())],
+                    None => vec![],
+                };
+                let received = event::now_micros();
+                for line in buffer[..size].split(|b| *b == b'\n') {
+                    let line = if line.last() == Some(&b'\r') {
+                        &line[..line.len() - 1]
+                    } else {
+                        line
+                    };
+                    let line = &line[..cmp::min(line.len(), options.max_line)];
+                    if let Some(e) = lines::decode(line, received, &source, &options) {
+                        if tx.send(e).is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+            Err(e) => println!("Error while receiving unix datagram: {}", e),
+        }
+    }
+}
+
+/// Removes the socket a previous run left at `path`, which would prevent binding. Any
+/// other kind of file is kept, and is an error.
+pub fn remove_stale_socket(path: &str) {
+    match fs::symlink_metadata(path) {
+        Ok(ref metadata) if metadata.file_type().is_socket() => {
+            if let Err(e) = fs::remove_file(path) {
+                panic!("Unable to remove the socket left at {}: {}", path, e);
+            }
+        }
+        Ok(_) => panic!("Unable to listen on {}: the file exists and is not a socket", path),
+        Err(_) => {}
+    }
+}
+
+impl InputProcessor for UnixInput {
+    fn start(&self, config: &Option<HashMap<String, String>>) -> Receiver<Event> {
+        match config_str(config, "mode", "stream").as_ref() {
+            "stream" | "datagram" => {}
+            mode => panic!("Unknown unix socket mode \"{}\", expected \"stream\" or \"datagram\"",
+                           mode),
+        }
+        line_options(config, "unix");
+        self.invoke(config, UnixInput::handle_func)
+    }
+
+    fn handle_func(tx: SyncSender<Event>, config: Option<HashMap<String, String>>) {
+        let path = config_str(&config, "path", "");
+        let options = line_options(&config, "unix");
+
+        remove_stale_socket(&path);
+        if config_str(&config, "mode", "stream") == "datagram" {
+            let max_length = config_parse(&config, "max_length", 65536usize);
+            listen_datagram(path, tx, options, max_length);
+        } else {
+            listen_stream(path, tx, options);
+        }
+    }
+}
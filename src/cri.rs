@@ -0,0 +1,127 @@
+//! Container log files written by CRI runtimes (containerd, CRI-O), as linked by the
+//! kubelet in `/var/log/containers/<pod>_<namespace>_<container>-<id>.log`.
+//!
+//! Each line is `<RFC 3339 time> <stream> <tag> <message>`, where the tag is `P` for
+//! the partial parts of a long line and `F` for its last part.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use docker::PartialLines;
+use event::{self, Delivery, Event};
+
+#[derive(Debug, PartialEq)]
+pub struct CriEntry {
+    /// Time since EPOCH in µs, if the entry has a valid one
+    pub time: Option<u64>,
+    pub stream: String,
+    pub partial: bool,
+    pub message: String,
+}
+
+pub fn parse_line(line: &str) -> Result<CriEntry, String> {
+    let mut fields = line.splitn(4, ' ');
+    let (time, stream, tag) = match (fields.next(), fields.next(), fields.next()) {
+        (Some(time), Some(stream), Some(tag)) => (time, stream, tag),
+        _ => return Err(format!("Invalid CRI log line: {}", line)),
+    };
+    let partial = match tag.split(':').next() {
+        Some("P") => true,
+        Some("F") => false,
+        _ => return Err(format!("Invalid CRI log tag: {}", tag)),
+    };
+    Ok(CriEntry {
+        time: event::parse_rfc3339_micros(time),
+        stream: stream.to_owned(),
+        partial: partial,
+        message: fields.next().unwrap_or("").to_owned(),
+    })
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ContainerMeta {
+    pub pod: String,
+    pub namespace: String,
+    pub container: String,
+    pub container_id: String,
+}
+
+/// Extracts the container metadata of a `<pod>_<namespace>_<container>-<id>.log` path.
+pub fn parse_path(path: &str) -> Option<ContainerMeta> {
+    let stem = match Path::new(path).file_name().and_then(|name| name.to_str()) {
+        Some(name) if name.ends_with(".log") => &name[..name.len() - ".log".len()],
+        _ => return None,
+    };
+    let parts: Vec<&str> = stem.splitn(3, '_').collect();
+    if parts.len() != 3 {
+        return None;
+    }
+    match parts[2].rfind('-') {
+        Some(ix) if ix > 0 && ix + 1 < parts[2].len() => {
+            Some(ContainerMeta {
+                pod: parts[0].to_owned(),
+                namespace: parts[1].to_owned(),
+                container: parts[2][..ix].to_owned(),
+                container_id: parts[2][ix + 1..].to_owned(),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Turns the lines of container log files into events, joining partial lines. The
+/// stream is the `source` facet, next to `pod`, `namespace`, `container` and
+/// `container_id` when the path holds them.
+pub struct CriReader {
+    partials: PartialLines,
+    facility: String,
+    line_facet: String,
+}
+
+impl CriReader {
+    pub fn new(facility: String, line_facet: String, max_line: usize) -> CriReader {
+        CriReader {
+            partials: PartialLines::new(max_line),
+            facility: facility,
+            line_facet: line_facet,
+        }
+    }
+
+    /// Returns the event completed by `line`, read from the file at `path`.
+    pub fn read_line(&mut self, path: &str, line: &str) -> Result<Option<Event>, String> {
+        let entry = try!(parse_line(line));
+        Ok(self.read_entry(path, entry, None))
+    }
+
+    /// Returns the event completed by `entry`. Its delivery, if any, is held until then
+    /// and attached to that event, along with the ones of the previous parts.
+    pub fn read_entry(&mut self,
+                      path: &str,
+                      entry: CriEntry,
+                      delivery: Option<Arc<Delivery>>)
+                      -> Option<Event> {
+        let key = format!("{}:{}", path, entry.stream);
+        if let Some(delivery) = delivery {
+            self.partials.hold(&key, delivery);
+        }
+        let time = entry.time.unwrap_or(event::now_micros());
+        let complete = self.partials.push(&key, time, &entry.message, !entry.partial);
+        let (time, message) = match complete {
+            Some(complete) => complete,
+            None => return None,
+        };
+
+        let mut e = Event::new(time);
+        e.facility = self.facility.clone();
+        if let Some(meta) = parse_path(path) {
+            e.facets.insert("pod".to_owned(), meta.pod);
+            e.facets.insert("namespace".to_owned(), meta.namespace);
+            e.facets.insert("container".to_owned(), meta.container);
+            e.facets.insert("container_id".to_owned(), meta.container_id);
+        }
+        e.facets.insert("source".to_owned(), entry.stream);
+        e.facets.insert(self.line_facet.clone(), message);
+        e.acks = self.partials.release(&key);
+        Some(e)
+    }
+}
@@ -0,0 +1,158 @@
+//! Timestamps found in log lines, in strftime formats, ISO 8601 or epoch based.
+
+use chrono::{DateTime, FixedOffset, Local, NaiveDateTime, TimeZone, UTC};
+
+use event::{self, Event};
+
+/// ISO 8601 variants accepted besides RFC 3339.
+static ISO8601_FORMATS: &'static [&'static str] = &["%Y-%m-%dT%H:%M:%S%.f%z",
+                                                     "%Y-%m-%d %H:%M:%S%.f%z",
+                                                     "%Y-%m-%dT%H:%M:%S%.f",
+                                                     "%Y-%m-%d %H:%M:%S%.f"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DateFormat {
+    /// RFC 3339 and its variants: space separated, without a zone, `+hhmm` zones
+    Iso8601,
+    /// Epoch seconds, with an optional fraction
+    Unix,
+    /// Epoch milliseconds, with an optional fraction
+    UnixMs,
+    Strftime(String),
+}
+
+impl DateFormat {
+    /// `ISO8601`, `UNIX`, `UNIX_MS`, or a strftime format.
+    pub fn from_name(name: &str) -> DateFormat {
+        match name {
+            "ISO8601" => DateFormat::Iso8601,
+            "UNIX" => DateFormat::Unix,
+            "UNIX_MS" => DateFormat::UnixMs,
+            format => DateFormat::Strftime(format.to_owned()),
+        }
+    }
+}
+
+/// Zone of the timestamps which do not hold one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Timezone {
+    Fixed(FixedOffset),
+    /// The local zone of the server, daylight saving time included
+    Local,
+}
+
+impl Timezone {
+    /// Parses `UTC`, `Z`, `local`, or an offset: `+02:00`, `-0530`.
+    pub fn from_name(name: &str) -> Option<Timezone> {
+        match name {
+            "UTC" | "Z" => return Some(Timezone::Fixed(FixedOffset::east(0))),
+            "local" => return Some(Timezone::Local),
+            _ => {}
+        }
+        let sign = match name.chars().next() {
+            Some('+') => 1,
+            Some('-') => -1,
+            _ => return None,
+        };
+        let digits = name[1..].replace(":", "");
+        if digits.len() != 4 || !digits.chars().all(|c| c.is_digit(10)) {
+            return None;
+        }
+        let hours = digits[..2].parse::<i32>().unwrap();
+        let minutes = digits[2..].parse::<i32>().unwrap();
+        if hours > 23 || minutes > 59 {
+            return None;
+        }
+        Some(Timezone::Fixed(FixedOffset::east(sign * (hours * 3600 + minutes * 60))))
+    }
+
+    fn utc(&self, naive: &NaiveDateTime) -> Option<DateTime<UTC>> {
+        match *self {
+            Timezone::Fixed(offset) => {
+                offset.from_local_datetime(naive).single().map(|dt| dt.with_timezone(&UTC))
+            }
+            Timezone::Local => {
+                Local.from_local_datetime(naive).single().map(|dt| dt.with_timezone(&UTC))
+            }
+        }
+    }
+}
+
+fn micros(dt: DateTime<UTC>) -> Option<u64> {
+    if dt.timestamp() >= 0 {
+        Some(event::timestamp_micros(dt))
+    } else {
+        None
+    }
+}
+
+/// Parses with a strftime format, in `timezone` unless the format has a zone.
+fn parse_strftime(text: &str, format: &str, timezone: &Timezone) -> Option<u64> {
+    match DateTime::parse_from_str(text, format) {
+        Ok(dt) => micros(dt.with_timezone(&UTC)),
+        Err(_) => {
+            NaiveDateTime::parse_from_str(text, format)
+                .ok()
+                .and_then(|naive| timezone.utc(&naive))
+                .and_then(micros)
+        }
+    }
+}
+
+/// Parses a timestamp with the first format that fits, to µs since EPOCH.
+pub fn parse(text: &str, formats: &[DateFormat], timezone: &Timezone) -> Option<u64> {
+    let text = text.trim();
+    formats.iter()
+        .filter_map(|format| {
+            match *format {
+                DateFormat::Iso8601 => {
+                    event::parse_rfc3339_micros(text).or_else(|| {
+                        ISO8601_FORMATS.iter()
+                            .filter_map(|f| parse_strftime(text, f, timezone))
+                            .next()
+                    })
+                }
+                DateFormat::Unix => event::parse_epoch_micros(text),
+                DateFormat::UnixMs => event::parse_epoch_micros(text).map(|us| us / 1000),
+                DateFormat::Strftime(ref f) => parse_strftime(text, f, timezone),
+            }
+        })
+        .next()
+}
+
+/// Sets the time of events from one of their facets.
+pub struct DateFilter {
+    pub source: String,
+    pub formats: Vec<DateFormat>,
+    pub timezone: Timezone,
+    /// Facet keeping the time the event had before, e.g. when it was received, in
+    /// RFC 3339. Nothing is kept when empty.
+    pub received_facet: String,
+    /// Facet marking events whose timestamp did not parse, and its value.
+    pub failure_facet: String,
+    pub failure_tag: String,
+}
+
+impl DateFilter {
+    /// Returns whether the time was set.
+    pub fn apply(&self, e: &mut Event) -> bool {
+        let time = e.facets.get(&self.source).and_then(|text| {
+            parse(text, &self.formats, &self.timezone)
+        });
+        match time {
+            Some(time) => {
+                if !self.received_facet.is_empty() {
+                    let received = UTC.timestamp((e.time / 1000000) as i64,
+                                                 (e.time % 1000000) as u32 * 1000);
+                    e.facets.insert(self.received_facet.clone(), received.to_rfc3339());
+                }
+                e.time = time;
+                true
+            }
+            None => {
+                event::add_tag(e, &self.failure_facet, &self.failure_tag);
+                false
+            }
+        }
+    }
+}
@@ -0,0 +1,105 @@
+//! Docker `json-file` log files, as found in `/var/lib/docker/containers/<id>/`.
+//!
+//! Each line is a JSON object `{"log":"...","stream":"stdout","time":"..."}`. Docker
+//! splits lines longer than 16KB into several entries: only the last one ends with a
+//! newline.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use rustc_serialize::json;
+
+use event::{self, Delivery};
+
+#[derive(RustcDecodable, Debug)]
+struct JsonFileLine {
+    log: String,
+    stream: Option<String>,
+    time: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct JsonFileEntry {
+    pub log: String,
+    pub stream: String,
+    /// Time since EPOCH in µs, if the entry has a valid one
+    pub time: Option<u64>,
+}
+
+pub fn parse_json_file_line(line: &str) -> Result<JsonFileEntry, String> {
+    let line = try!(json::decode::<JsonFileLine>(line).map_err(|e| format!("{}", e)));
+    Ok(JsonFileEntry {
+        log: line.log,
+        stream: line.stream.unwrap_or(String::new()),
+        time: line.time.as_ref().and_then(|t| event::parse_rfc3339_micros(t)),
+    })
+}
+
+/// Returns the container id of a `<root>/<id>/<id>-json.log` path.
+pub fn container_id(path: &str) -> Option<String> {
+    Path::new(path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| if name.ends_with("-json.log") {
+            Some(name[..name.len() - "-json.log".len()].to_owned())
+        } else {
+            None
+        })
+        .and_then(|id| if id.is_empty() {
+            None
+        } else {
+            Some(id)
+        })
+}
+
+/// Reassembles lines split into several entries, by stream. A line is returned once
+/// complete, with the time of its first part, or as soon as it grows past
+/// `max_length`.
+pub struct PartialLines {
+    max_length: usize,
+    pending: HashMap<String, (u64, String)>,
+    /// Deliveries of the entries of each stream, until its line is complete
+    held: HashMap<String, Vec<Arc<Delivery>>>,
+}
+
+impl PartialLines {
+    pub fn new(max_length: usize) -> PartialLines {
+        PartialLines {
+            max_length: max_length,
+            pending: HashMap::new(),
+            held: HashMap::new(),
+        }
+    }
+
+    /// Keeps the delivery of an entry of `stream` until its line is complete, so that
+    /// it is not confirmed before the first parts of the line are stored.
+    pub fn hold(&mut self, stream: &str, delivery: Arc<Delivery>) {
+        self.held.entry(stream.to_owned()).or_insert_with(Vec::new).push(delivery);
+    }
+
+    /// Returns the deliveries held for `stream`, once `push` returned its line.
+    pub fn release(&mut self, stream: &str) -> Vec<Arc<Delivery>> {
+        self.held.remove(stream).unwrap_or(vec![])
+    }
+
+    pub fn push(&mut self,
+                stream: &str,
+                time: u64,
+                part: &str,
+                complete: bool)
+                -> Option<(u64, String)> {
+        let full = {
+            let pending = self.pending
+                .entry(stream.to_owned())
+                .or_insert_with(|| (time, String::new()));
+            pending.1.push_str(part);
+            complete || pending.1.len() >= self.max_length
+        };
+        if full {
+            self.pending.remove(stream)
+        } else {
+            None
+        }
+    }
+}
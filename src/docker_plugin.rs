@@ -0,0 +1,342 @@
+//! Docker logging plugin protocol.
+//!
+//! Docker talks to the plugin with JSON requests over HTTP on a unix socket. On
+//! `/LogDriver.StartLogging` it hands over a FIFO carrying the logs of a container as
+//! protobuf `LogEntry` messages, each prefixed with its length as a big-endian u32.
+//! `/LogDriver.ReadLogs` answers `docker logs` with a stream of such frames, which lasts
+//! until the container stops when following.
+
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, BufRead, Read, Write};
+
+use rustc_serialize::json::Json;
+
+use docker::PartialLines;
+use event::{self, Event};
+use protobuf;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogEntry {
+    /// `stdout` or `stderr`
+    pub source: String,
+    pub time_nano: i64,
+    pub line: Vec<u8>,
+    pub partial: bool,
+    /// Set on the parts of a line split by docker, with whether this is the last one
+    pub partial_last: Option<bool>,
+}
+
+impl LogEntry {
+    pub fn decode(data: &[u8]) -> Result<LogEntry, String> {
+        let mut entry = LogEntry {
+            source: String::new(),
+            time_nano: 0,
+            line: vec![],
+            partial: false,
+            partial_last: None,
+        };
+        for field in protobuf::Reader::new(data) {
+            match try!(field) {
+                (1, value) => entry.source = try!(value.as_string()),
+                (2, value) => entry.time_nano = try!(value.as_u64()) as i64,
+                (3, value) => entry.line = try!(value.as_bytes()).to_vec(),
+                (4, value) => entry.partial = try!(value.as_u64()) != 0,
+                (5, value) => {
+                    let mut last = false;
+                    for field in protobuf::Reader::new(try!(value.as_bytes())) {
+                        if let (1, value) = try!(field) {
+                            last = try!(value.as_u64()) != 0;
+                        }
+                    }
+                    entry.partial_last = Some(last);
+                }
+                _ => {}
+            }
+        }
+        Ok(entry)
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut writer = protobuf::Writer::new();
+        writer.bytes(1, self.source.as_bytes());
+        writer.varint(2, self.time_nano as u64);
+        writer.bytes(3, &self.line);
+        if self.partial {
+            writer.varint(4, 1);
+        }
+        writer.buffer
+    }
+
+    /// Whether this entry ends a line
+    pub fn is_complete(&self) -> bool {
+        match self.partial_last {
+            Some(last) => last,
+            None => !self.partial,
+        }
+    }
+}
+
+/// Reads the next length-prefixed frame. Returns `None` at the end of the stream.
+pub fn read_frame<R: Read>(reader: &mut R, max_length: usize) -> io::Result<Option<Vec<u8>>> {
+    let mut length = [0u8; 4];
+    match reader.read(&mut length[..1]) {
+        Ok(0) => return Ok(None),
+        Ok(_) => try!(reader.read_exact(&mut length[1..])),
+        Err(e) => return Err(e),
+    }
+    let length = length.iter().fold(0usize, |acc, b| (acc << 8) | *b as usize);
+    if length > max_length {
+        return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                  format!("Log entry of {} bytes is too large", length)));
+    }
+    let mut frame = vec![0; length];
+    try!(reader.read_exact(&mut frame));
+    Ok(Some(frame))
+}
+
+pub fn write_frame(entry: &LogEntry, out: &mut Vec<u8>) {
+    let encoded = entry.encode();
+    let length = encoded.len();
+    out.extend_from_slice(&[(length >> 24) as u8, (length >> 16) as u8, (length >> 8) as u8,
+                            length as u8]);
+    out.extend_from_slice(&encoded);
+}
+
+/// The container a FIFO carries the logs of, from the `Info` of the requests
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContainerInfo {
+    pub id: String,
+    pub name: String,
+    pub image: String,
+}
+
+impl ContainerInfo {
+    pub fn from_json(info: Option<&Json>) -> ContainerInfo {
+        let field = |name: &str| {
+            info.and_then(|i| i.find(name))
+                .and_then(|v| v.as_string())
+                .unwrap_or("")
+                .to_owned()
+        };
+        ContainerInfo {
+            id: field("ContainerID"),
+            name: field("ContainerName").trim_left_matches('/').to_owned(),
+            image: field("ContainerImageName"),
+        }
+    }
+}
+
+/// Turns the entries of a container FIFO into events, joining the lines docker split.
+/// Events are mapped like the ones of the docker `splunk` log driver: the short
+/// container id is the facility and `source` holds the stream.
+pub struct EntryReader {
+    container: ContainerInfo,
+    line_facet: String,
+    partials: PartialLines,
+}
+
+impl EntryReader {
+    pub fn new(container: ContainerInfo, line_facet: String, max_line: usize) -> EntryReader {
+        EntryReader {
+            container: container,
+            line_facet: line_facet,
+            partials: PartialLines::new(max_line),
+        }
+    }
+
+    pub fn read_entry(&mut self, entry: LogEntry) -> Option<Event> {
+        let time = if entry.time_nano > 0 {
+            entry.time_nano as u64 / 1000
+        } else {
+            event::now_micros()
+        };
+        let line = String::from_utf8_lossy(&entry.line).into_owned();
+        let complete = self.partials.push(&entry.source, time, &line, entry.is_complete());
+        let (time, line) = match complete {
+            Some(complete) => complete,
+            None => return None,
+        };
+
+        let mut e = Event::new(time);
+        e.facility = self.container.id.chars().take(12).collect();
+        e.facets.insert("container_id".to_owned(), self.container.id.clone());
+        e.facets.insert("container_name".to_owned(), self.container.name.clone());
+        e.facets.insert("image".to_owned(), self.container.image.clone());
+        e.facets.insert("source".to_owned(), entry.source);
+        e.facets.insert(self.line_facet.clone(), line);
+        Some(e)
+    }
+}
+
+/// What `docker logs` asks for
+#[derive(Debug, PartialEq)]
+pub struct ReadLogsQuery {
+    pub container_id: String,
+    /// Time bounds in µs since EPOCH
+    pub since: Option<u64>,
+    pub until: Option<u64>,
+    /// Number of lines from the end, all of them when `None`
+    pub tail: Option<usize>,
+    /// Keep sending the lines logged after the archived ones
+    pub follow: bool,
+}
+
+impl ReadLogsQuery {
+    pub fn from_json(request: &Json) -> ReadLogsQuery {
+        let config = request.find("Config");
+        // Unset times are Go zero times (0001-01-01T00:00:00Z), which fail to parse
+        let time = |name: &str| {
+            config.and_then(|c| c.find(name))
+                .and_then(|t| t.as_string())
+                .and_then(event::parse_rfc3339_micros)
+        };
+        ReadLogsQuery {
+            container_id: ContainerInfo::from_json(request.find("Info")).id,
+            since: time("Since"),
+            until: time("Until"),
+            tail: config.and_then(|c| c.find("Tail"))
+                .and_then(|t| t.as_i64())
+                .and_then(|t| if t >= 0 { Some(t as usize) } else { None }),
+            follow: config.and_then(|c| c.find("Follow"))
+                .and_then(|f| f.as_boolean())
+                .unwrap_or(false),
+        }
+    }
+
+    pub fn matches(&self, e: &Event) -> bool {
+        e.facets.get("container_id") == Some(&self.container_id) &&
+        self.since.map(|since| e.time >= since).unwrap_or(true) &&
+        self.until.map(|until| e.time <= until).unwrap_or(true)
+    }
+
+    /// Writes the events of the query as log entry frames, keeping the last `tail` of
+    /// them. `events` come oldest first. Returns the events written with the time of
+    /// the last one.
+    pub fn write_events<I, W>(&self,
+                              events: I,
+                              line_facet: &str,
+                              out: &mut W)
+                              -> io::Result<Vec<Event>>
+        where I: Iterator<Item = Event>,
+              W: Write
+    {
+        let matching = events.filter(|e| self.matches(e));
+        let mut latest = vec![];
+        match self.tail {
+            Some(tail) => {
+                // Only the last lines are held
+                let mut selected = VecDeque::new();
+                for e in matching {
+                    if selected.len() == tail {
+                        selected.pop_front();
+                    }
+                    if tail > 0 {
+                        selected.push_back(e);
+                    }
+                }
+                for e in selected {
+                    try!(write_event(&e, line_facet, out));
+                    push_latest(&mut latest, e);
+                }
+            }
+            None => {
+                for e in matching {
+                    try!(write_event(&e, line_facet, out));
+                    push_latest(&mut latest, e);
+                }
+            }
+        }
+        Ok(latest)
+    }
+}
+
+/// Keeps `e` with the events of the same time written before it, dropping older ones.
+fn push_latest(latest: &mut Vec<Event>, e: Event) {
+    if latest.last().map_or(false, |last: &Event| last.time != e.time) {
+        latest.clear();
+    }
+    latest.push(e);
+}
+
+/// Writes an event as a log entry frame.
+pub fn write_event<W: Write>(e: &Event, line_facet: &str, out: &mut W) -> io::Result<()> {
+    let entry = LogEntry {
+        source: e.facets.get("source").cloned().unwrap_or(String::new()),
+        time_nano: (e.time * 1000) as i64,
+        line: e.facets.get(line_facet).cloned().unwrap_or(String::new()).into_bytes(),
+        partial: false,
+        partial_last: None,
+    };
+    let mut frame = vec![];
+    write_frame(&entry, &mut frame);
+    out.write_all(&frame)
+}
+
+/// An HTTP request on the plugin socket
+#[derive(Debug)]
+pub struct PluginRequest {
+    pub method: String,
+    pub path: String,
+    pub body: Vec<u8>,
+}
+
+/// Reads an HTTP request with a `Content-Length` body, as docker sends them.
+pub fn read_request<R: BufRead>(reader: &mut R,
+                                max_length: usize)
+                                -> io::Result<PluginRequest> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_owned());
+    let mut request_line = String::new();
+    try!(reader.read_line(&mut request_line));
+    let parts: Vec<&str> = request_line.split_whitespace().collect();
+    if parts.len() != 3 {
+        return Err(invalid("Invalid request line"));
+    }
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut header = String::new();
+        if try!(reader.read_line(&mut header)) == 0 {
+            return Err(invalid("Unexpected end of headers"));
+        }
+        let header = header.trim_right();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(ix) = header.find(':') {
+            headers.insert(header[..ix].trim().to_lowercase(),
+                           header[ix + 1..].trim().to_owned());
+        }
+    }
+
+    let length = match headers.get("content-length") {
+        Some(length) => try!(length.parse::<usize>().map_err(|_| invalid("Invalid length"))),
+        None => 0,
+    };
+    if length > max_length {
+        return Err(invalid("Request body is too large"));
+    }
+    let mut body = vec![0; length];
+    try!(reader.read_exact(&mut body));
+    Ok(PluginRequest {
+        method: parts[0].to_owned(),
+        path: parts[1].to_owned(),
+        body: body,
+    })
+}
+
+/// Headers of a response whose body lasts until the connection is closed.
+pub fn stream_response(content_type: &str) -> Vec<u8> {
+    format!("HTTP/1.1 200 OK\r\nContent-Type: {}\r\nConnection: close\r\n\r\n",
+            content_type)
+        .into_bytes()
+}
+
+pub fn response(content_type: &str, body: &[u8]) -> Vec<u8> {
+    let mut out = format!("HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: \
+                           {}\r\nConnection: close\r\n\r\n",
+                          content_type,
+                          body.len())
+        .into_bytes();
+    out.extend_from_slice(body);
+    out
+}
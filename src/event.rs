@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::iter;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use capnp;
+use capnp::message::{Builder, HeapAllocator};
+use chrono::datetime::DateTime;
+use chrono::offset::utc::UTC;
+use chrono::Timelike;
+use logformat::schema_capnp::{logblock, logline};
+use rustc_serialize::json::Json;
+
+/// Tracks a group of events (e.g. all the events of one HEC request) until every
+/// one of them has been durably stored, then runs its completion callback once.
+pub struct Delivery {
+    remaining: AtomicUsize,
+    on_complete: Box<Fn() + Send + Sync>,
+}
+
+impl Delivery {
+    pub fn new(event_count: usize, on_complete: Box<Fn() + Send + Sync>) -> Arc<Delivery> {
+        Arc::new(Delivery {
+            remaining: AtomicUsize::new(event_count),
+            on_complete: on_complete,
+        })
+    }
+
+    pub fn confirm(&self) {
+        if self.remaining.fetch_sub(1, Ordering::SeqCst) == 1 {
+            (self.on_complete)();
+        }
+    }
+}
+
+impl fmt::Debug for Delivery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "Delivery {{ remaining: {} }}",
+               self.remaining.load(Ordering::SeqCst))
+    }
+}
+
+/// A `Logline` travelling through the pipeline, from inputs to outputs.
+#[derive(Debug, Clone)]
+pub struct Event {
+    /// This is time since EPOCH in µs, as in `Logline.time`
+    pub time: u64,
+    pub facility: String,
+    pub hostname: String,
+    pub facets: HashMap<String, String>,
+    /// Deliveries to confirm once this event has been durably written
+    pub acks: Vec<Arc<Delivery>>,
+}
+
+impl Event {
+    pub fn new(time: u64) -> Event {
+        Event {
+            time: time,
+            facility: String::new(),
+            hostname: String::new(),
+            facets: HashMap::new(),
+            acks: vec![],
+        }
+    }
+
+    pub fn confirm(&self) {
+        for ack in self.acks.iter() {
+            ack.confirm();
+        }
+    }
+
+    pub fn write_to(&self, lline: &mut logline::Builder) {
+        lline.set_time(self.time);
+        lline.set_facility(&self.facility);
+        lline.set_hostname(&self.hostname);
+        {
+            let mut facets_builder = lline.borrow().init_facets();
+            let mut kv = facets_builder.borrow().init_entries(self.facets.len() as u32);
+
+            for (ix, (key, value)) in self.facets.iter().enumerate() {
+                let _ = kv.borrow().get(ix as u32).set_key(key);
+                let _ = kv.borrow().get(ix as u32).set_value(value);
+            }
+        }
+    }
+
+    pub fn from_reader(reader: &logline::Reader) -> Result<Event, capnp::Error> {
+        let mut event = Event::new(reader.get_time());
+        event.facility = try!(reader.get_facility()).to_owned();
+        event.hostname = try!(reader.get_hostname()).to_owned();
+        let facets = try!(reader.get_facets());
+        for kv in try!(facets.get_entries()).iter() {
+            event.facets.insert(try!(kv.get_key()).to_owned(), try!(kv.get_value()).to_owned());
+        }
+        Ok(event)
+    }
+}
+
+/// Builds a `Logblock` holding `events`, with their time range.
+pub fn log_block(events: &[Event]) -> Builder<HeapAllocator> {
+    let mut message = Builder::new_default();
+    {
+        let mut block = message.init_root::<logblock::Builder>();
+        block.set_start_time(events.iter().map(|e| e.time).min().unwrap_or(0));
+        block.set_end_time(events.iter().map(|e| e.time).max().unwrap_or(0));
+        let mut lines = block.borrow().init_entries(events.len() as u32);
+        for (ix, event) in events.iter().enumerate() {
+            let mut cline = lines.borrow().get(ix as u32);
+            event.write_to(&mut cline);
+        }
+    }
+    message
+}
+
+pub fn timestamp_micros(ts: DateTime<UTC>) -> u64 {
+    let second_in_micro = ts.timestamp() as u64 * 1000000;
+    let us: u64 = (ts.nanosecond() / 1000) as u64;
+    second_in_micro + us
+}
+
+pub fn now_micros() -> u64 {
+    timestamp_micros(UTC::now())
+}
+
+/// Parses an RFC 3339 timestamp, with up to nanosecond precision, to µs since EPOCH.
+/// Times before EPOCH are rejected.
+pub fn parse_rfc3339_micros(timestamp: &str) -> Option<u64> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .and_then(|dt| if dt.timestamp() >= 0 {
+            Some(timestamp_micros(dt.with_timezone(&UTC)))
+        } else {
+            None
+        })
+}
+
+/// Converts fractional epoch seconds such as `"1462290089.642521"` to µs without going
+/// through a float. Digits beyond the µs are truncated.
+pub fn parse_epoch_micros(time: &str) -> Option<u64> {
+    let time = time.trim();
+    let (seconds, fraction) = match time.find('.') {
+        Some(ix) => (&time[..ix], &time[ix + 1..]),
+        None => (time, ""),
+    };
+    if seconds.is_empty() || !seconds.chars().all(|c| c.is_digit(10)) ||
+       !fraction.chars().all(|c| c.is_digit(10)) {
+        return None;
+    }
+
+    let mut micros = 0u64;
+    for digit in fraction.chars().chain(iter::repeat('0')).take(6) {
+        micros = micros * 10 + digit.to_digit(10).unwrap() as u64;
+    }
+    seconds.parse::<u64>()
+        .ok()
+        .and_then(|s| s.checked_mul(1000000))
+        .and_then(|s| s.checked_add(micros))
+}
+
+/// Returns the time in µs, if `time` holds valid epoch seconds, either as a number or
+/// as a string.
+pub fn json_epoch_micros(time: &Json) -> Option<u64> {
+    match *time {
+        Json::String(ref s) => parse_epoch_micros(s),
+        Json::U64(s) => s.checked_mul(1000000),
+        Json::I64(s) if s >= 0 => (s as u64).checked_mul(1000000),
+        Json::F64(s) if s >= 0.0 && s.is_finite() => Some((s * 1000000.0).round() as u64),
+        _ => None,
+    }
+}
+
+/// Appends `tag` to the `facet` of `e`, comma separated when the facet is not empty.
+pub fn add_tag(e: &mut Event, facet: &str, tag: &str) {
+    let tags = match e.facets.get(facet) {
+        Some(tags) if !tags.is_empty() => format!("{},{}", tags, tag),
+        _ => tag.to_owned(),
+    };
+    e.facets.insert(facet.to_owned(), tags);
+}
@@ -0,0 +1,97 @@
+//! Synthetic Apache access log lines, for load testing and benchmarks.
+
+use rand::Rng;
+use chrono::duration::Duration;
+use chrono::datetime::DateTime;
+use chrono::offset::utc::UTC;
+
+static APACHE_FORMAT: &'static str = "%d/%b/%Y:%H:%M:%S %z";
+pub static REFERERS: &'static [&'static str] = &["-",
+                                                 "http://www.casualcyclist.com",
+                                                 "http://bestcyclingreviews.com/top_online_shops",
+                                                 "http://bleater.com",
+                                                 "http://searchengine.com"];
+
+pub static VERBS: &'static [&'static str] = &["GET", "POST", "PUT", "DELETE", "HEAD"];
+
+pub static USERAGENTS: &'static [&'static str] =
+    &["Mozilla/4.0 (compatible; MSIE 7.0; Windows NT 6.0)",
+      "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_9_2) AppleWebKit/537.36 (KHTML, like Gecko) \
+       Chrome/36.0.1944.0 Safari/537.36",
+      "Mozilla/5.0 (Linux; U; Android 2.3.5; en-us; HTC Vision Build/GRI40) AppleWebKit/533.1 \
+       (KHTML, like Gecko) Version/4.0 Mobile Safari/533.1",
+      "Mozilla/5.0 (iPad; CPU OS 6_0 like Mac OS X) AppleWebKit/536.26 (KHTML, like Gecko) \
+       Version/6.0 Mobile/10A5355d Safari/8536.25",
+      "Mozilla/5.0 (Windows; U; Windows NT 6.1; rv:2.2) Gecko/20110201",
+      "Mozilla/5.0 (Windows NT 5.1; rv:31.0) Gecko/20100101 Firefox/31.0",
+      "Mozilla/5.0 (Windows; U; MSIE 9.0; WIndows NT 9.0; en-US))"];
+pub static RESOURCES: &'static [&'static str] = &["/handle-bars",
+                                                  "/stems",
+                                                  "/wheelsets",
+                                                  "/forks",
+                                                  "/seatposts",
+                                                  "/saddles",
+                                                  "/shifters",
+                                                  "/Store/cart.jsp?productID="];
+
+pub fn random_ip<R: Rng>(rng: &mut R) -> String {
+    let a: u32 = rng.gen_range(1, 255);
+    let b: u32 = rng.gen_range(1, 255);
+    let c: u32 = rng.gen_range(0, 255);
+    let d: u32 = rng.gen_range(2, 255);
+    format!("{}.{}.{}.{}", a, b, c, d)
+}
+
+pub fn apache_time(start: DateTime<UTC>, us: i64) -> (DateTime<UTC>, String) {
+    let d = Duration::microseconds(us);
+    if let Some(r) = start.checked_add(d) {
+        (r, r.format(APACHE_FORMAT).to_string())
+    } else {
+        (start, start.format(APACHE_FORMAT).to_string())
+    }
+}
+
+/// Builds a random access log line for a request served at `time`.
+pub fn apache_line<R: Rng>(rng: &mut R, time: DateTime<UTC>) -> String {
+    let r = REFERERS[rng.gen_range(0, REFERERS.len())];
+    let ua = USERAGENTS[rng.gen_range(0, USERAGENTS.len())];
+    let uri = RESOURCES[rng.gen_range(0, RESOURCES.len())];
+    let verb = VERBS[rng.gen_range(0, VERBS.len())];
+
+    let prefix = match uri.find("Store") {
+        Some(_) => format!("{}{}", uri, rng.gen_range(1000, 1500)),
+        None => "".into(),
+    };
+
+    format!("{} - - [{}] \"{} {}{} HTTP/1.0\" 200 {} \"{}\" \"{}\"",
+            random_ip(rng),
+            time.format(APACHE_FORMAT),
+            verb,
+            uri,
+            prefix,
+            rng.gen_range(2000, 5000),
+            r,
+            ua)
+}
+
+/// Generates `event_count` lines, sorted by time, spread over the `spread_seconds`
+/// seconds following `start`.
+pub fn line_generator<R: Rng>(rng: &mut R,
+                              event_count: u32,
+                              start: DateTime<UTC>,
+                              spread_seconds: i64)
+                              -> Vec<(DateTime<UTC>, String)> {
+    let mut times = Vec::<i64>::with_capacity(event_count as usize);
+
+    for _ in 1..event_count + 1 {
+        let _ = times.push(rng.gen_range(0, spread_seconds.max(1)) * 1000000);
+    }
+    times.sort();
+    times.into_iter()
+        .map(|time| {
+            let (datetime, _) = apache_time(start, time);
+            let line = apache_line(rng, datetime);
+            (datetime, line)
+        })
+        .collect()
+}
@@ -0,0 +1,207 @@
+//! Expands structured text, JSON objects or logfmt `key=value` pairs, into facets.
+
+use rustc_serialize::json::Json;
+
+use event::{self, Event};
+use lines::json_string;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// A JSON object
+    Json,
+    /// Space separated `key=value` pairs, values being optionally double quoted
+    Kv,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Arrays {
+    /// Arrays are kept as JSON
+    Json,
+    /// Each element is a facet, suffixed with its index: `ports.0`, `ports.1`...
+    Index,
+    /// Elements are joined with commas, when none is an object or an array
+    Join,
+}
+
+impl Arrays {
+    pub fn from_name(name: &str) -> Option<Arrays> {
+        match name {
+            "json" => Some(Arrays::Json),
+            "index" => Some(Arrays::Index),
+            "join" => Some(Arrays::Join),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldOptions {
+    /// Prepended to every facet name
+    pub prefix: String,
+    /// Nested objects give dotted facet names, `http.status`, instead of JSON facets
+    pub flatten: bool,
+    pub arrays: Arrays,
+    /// Values nested deeper than this are kept as JSON
+    pub max_depth: usize,
+}
+
+fn expand_value(name: String,
+                value: &Json,
+                depth: usize,
+                options: &FieldOptions,
+                fields: &mut Vec<(String, String)>) {
+    let nested = options.flatten && depth < options.max_depth;
+    match *value {
+        Json::Object(ref object) if nested => {
+            for (key, value) in object.iter() {
+                expand_value(format!("{}.{}", name, key), value, depth + 1, options, fields);
+            }
+        }
+        Json::Array(ref array) if nested && options.arrays == Arrays::Index => {
+            for (ix, value) in array.iter().enumerate() {
+                expand_value(format!("{}.{}", name, ix), value, depth + 1, options, fields);
+            }
+        }
+        Json::Array(ref array) if options.arrays == Arrays::Join &&
+                                  !array.iter().any(|v| v.is_object() || v.is_array()) => {
+            let joined = array.iter().map(json_string).collect::<Vec<String>>().join(",");
+            fields.push((name, joined));
+        }
+        ref other => fields.push((name, json_string(other))),
+    }
+}
+
+/// Expands a JSON object into facets. Anything else is an error.
+pub fn parse_json(text: &str, options: &FieldOptions) -> Result<Vec<(String, String)>, String> {
+    let object = match Json::from_str(text) {
+        Ok(Json::Object(object)) => object,
+        Ok(_) => return Err("Not a JSON object".to_owned()),
+        Err(e) => return Err(format!("{}", e)),
+    };
+    let mut fields = vec![];
+    for (key, value) in object.iter() {
+        expand_value(format!("{}{}", options.prefix, key), value, 1, options, &mut fields);
+    }
+    Ok(fields)
+}
+
+/// Parses logfmt pairs: `level=info msg="user logged in" admin`. A key without a value
+/// is `true`. Text without any `key=value` pair is an error, so plain messages are not
+/// mistaken for a list of keys.
+pub fn parse_kv(text: &str, options: &FieldOptions) -> Result<Vec<(String, String)>, String> {
+    let mut fields = vec![];
+    let mut pairs = 0;
+    let mut chars = text.chars().peekable();
+
+    loop {
+        while chars.peek().map_or(false, |c| c.is_whitespace()) {
+            chars.next();
+        }
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let mut key = String::new();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() || c == '=' || c == '"' {
+                break;
+            }
+            key.push(c);
+            chars.next();
+        }
+        if key.is_empty() {
+            return Err(format!("Expected a key in {}", text));
+        }
+
+        let value = if chars.peek() == Some(&'=') {
+            chars.next();
+            pairs += 1;
+            let mut value = String::new();
+            if chars.peek() == Some(&'"') {
+                chars.next();
+                let mut closed = false;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => {
+                            closed = true;
+                            break;
+                        }
+                        '\\' => {
+                            match chars.next() {
+                                Some('n') => value.push('\n'),
+                                Some('t') => value.push('\t'),
+                                Some(c) => value.push(c),
+                                None => break,
+                            }
+                        }
+                        c => value.push(c),
+                    }
+                }
+                if !closed {
+                    return Err(format!("Unterminated value for {}", key));
+                }
+            } else {
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() {
+                        break;
+                    }
+                    value.push(c);
+                    chars.next();
+                }
+            }
+            value
+        } else if chars.peek().map_or(true, |c| c.is_whitespace()) {
+            "true".to_owned()
+        } else {
+            return Err(format!("Unexpected quote after {}", key));
+        };
+        fields.push((format!("{}{}", options.prefix, key), value));
+    }
+
+    if pairs == 0 {
+        return Err("No key=value pair".to_owned());
+    }
+    Ok(fields)
+}
+
+/// Parses a facet and stores its fields as facets. Events whose facet does not parse
+/// are left untouched, besides the failure tag.
+pub struct FieldFilter {
+    pub source: String,
+    pub format: Format,
+    pub options: FieldOptions,
+    /// Fields replace existing facets when set
+    pub overwrite: bool,
+    /// Facet marking events that did not parse, and its value.
+    pub failure_facet: String,
+    pub failure_tag: String,
+}
+
+impl FieldFilter {
+    /// Returns whether the source facet parsed. Events without it are left untouched.
+    pub fn apply(&self, e: &mut Event) -> bool {
+        let parsed = match e.facets.get(&self.source) {
+            Some(text) => {
+                match self.format {
+                    Format::Json => parse_json(text, &self.options),
+                    Format::Kv => parse_kv(text, &self.options),
+                }
+            }
+            None => return false,
+        };
+        match parsed {
+            Ok(fields) => {
+                for (facet, value) in fields {
+                    if self.overwrite || !e.facets.contains_key(&facet) {
+                        e.facets.insert(facet, value);
+                    }
+                }
+                true
+            }
+            Err(_) => {
+                event::add_tag(e, &self.failure_facet, &self.failure_tag);
+                false
+            }
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! Fluentd forward protocol, as spoken by the docker `fluentd` log driver.
+//!
+//! A message is a MessagePack array starting with the tag, in one of three modes:
+//! `[tag, time, record, option?]` (Message), `[tag, [[time, record], ...], option?]`
+//! (Forward) or `[tag, entries, option?]` (PackedForward) where entries is the
+//! concatenation of encoded `[time, record]` arrays, gzip compressed when the option
+//! says `"compressed": "gzip"`. A `chunk` option asks for an `{"ack": chunk}` reply.
+
+use std::io::{Cursor, Read};
+
+use flate2::read::MultiGzDecoder;
+
+use event::Event;
+use msgpack::{self, Value};
+
+/// Events decoded from a forward protocol message
+#[derive(Debug)]
+pub struct ForwardRequest {
+    pub events: Vec<Event>,
+    /// Chunk id to acknowledge, if the client asked for it
+    pub chunk: Option<String>,
+}
+
+/// Returns the time in µs of an integer, float or EventTime (ext type 0) time.
+fn entry_time(time: &Value) -> Option<u64> {
+    match *time {
+        Value::UInt(s) => s.checked_mul(1000000),
+        Value::Int(s) if s >= 0 => (s as u64).checked_mul(1000000),
+        Value::Float(s) if s >= 0.0 && s.is_finite() => Some((s * 1000000.0).round() as u64),
+        Value::Ext(0, ref data) if data.len() == 8 => {
+            let seconds = data[..4].iter().fold(0u64, |acc, b| (acc << 8) | *b as u64);
+            let nanos = data[4..].iter().fold(0u64, |acc, b| (acc << 8) | *b as u64);
+            Some(seconds * 1000000 + nanos / 1000)
+        }
+        _ => None,
+    }
+}
+
+/// Maps an entry into a `Logline`: the tag is the facility and each record key a facet.
+fn entry_event(tag: &str, time: &Value, record: &Value, received: u64) -> Result<Event, String> {
+    let entries = match *record {
+        Value::Map(ref entries) => entries,
+        _ => return Err("Forward record is not a map".to_owned()),
+    };
+    let mut e = Event::new(entry_time(time).unwrap_or(received));
+    e.facility = tag.to_owned();
+    for &(ref key, ref value) in entries.iter() {
+        e.facets.insert(key.to_text(), value.to_text());
+    }
+    Ok(e)
+}
+
+fn entry_pair(entry: &Value) -> Result<(&Value, &Value), String> {
+    match *entry {
+        Value::Array(ref pair) if pair.len() >= 2 => Ok((&pair[0], &pair[1])),
+        _ => Err("Forward entry is not a [time, record] array".to_owned()),
+    }
+}
+
+/// Decodes the concatenated entries of a PackedForward message.
+fn packed_entries(entries: &[u8],
+                  compressed: bool,
+                  max_length: usize)
+                  -> Result<Vec<Value>, String> {
+    let mut data = vec![];
+    let entries = if compressed {
+        try!(MultiGzDecoder::new(entries)
+            .and_then(|decoder| decoder.take(max_length as u64 + 1).read_to_end(&mut data))
+            .map_err(|e| format!("Invalid compressed entries: {}", e)));
+        if data.len() > max_length {
+            return Err(format!("Entries larger than {} bytes", max_length));
+        }
+        &data[..]
+    } else {
+        entries
+    };
+
+    let mut cursor = Cursor::new(entries);
+    let mut values = vec![];
+    while (cursor.position() as usize) < entries.len() {
+        values.push(try!(msgpack::read_value(&mut cursor, max_length)
+            .map_err(|e| format!("Invalid packed entry: {}", e))));
+    }
+    Ok(values)
+}
+
+pub fn decode_request(message: &Value,
+                      received: u64,
+                      max_length: usize)
+                      -> Result<ForwardRequest, String> {
+    let parts = match *message {
+        Value::Array(ref parts) if parts.len() >= 2 => parts,
+        _ => return Err("Forward message is not an array".to_owned()),
+    };
+    let tag = match parts[0].as_str() {
+        Some(tag) => tag,
+        None => return Err("Forward message without a tag".to_owned()),
+    };
+
+    let mut events = vec![];
+    let option = match parts[1] {
+        Value::Array(ref entries) => {
+            for entry in entries.iter() {
+                let (time, record) = try!(entry_pair(entry));
+                events.push(try!(entry_event(tag, time, record, received)));
+            }
+            parts.get(2)
+        }
+        Value::Str(_) | Value::Bin(_) => {
+            let option = parts.get(2);
+            let compressed = option.and_then(|o| o.get("compressed"))
+                .and_then(|c| c.as_str()) == Some("gzip");
+            let packed = parts[1].as_bytes().unwrap();
+            for entry in try!(packed_entries(packed, compressed, max_length)).iter() {
+                let (time, record) = try!(entry_pair(entry));
+                events.push(try!(entry_event(tag, time, record, received)));
+            }
+            option
+        }
+        ref time => {
+            match parts.get(2) {
+                Some(record) => events.push(try!(entry_event(tag, time, record, received))),
+                None => return Err("Forward message without a record".to_owned()),
+            }
+            parts.get(3)
+        }
+    };
+
+    Ok(ForwardRequest {
+        events: events,
+        chunk: option.and_then(|o| o.get("chunk"))
+            .and_then(|c| c.as_str())
+            .map(|c| c.to_owned()),
+    })
+}
+
+/// Encodes the reply acknowledging `chunk`.
+pub fn ack_response(chunk: &str) -> Vec<u8> {
+    let mut out = vec![];
+    msgpack::write_value(&Value::Map(vec![(Value::Str(b"ack".to_vec()),
+                                           Value::Str(chunk.as_bytes().to_vec()))]),
+                         &mut out);
+    out
+}
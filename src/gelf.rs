@@ -0,0 +1,207 @@
+//! Graylog Extended Log Format messages, as sent by the docker `gelf` log driver.
+//!
+//! UDP datagrams may be compressed with gzip or zlib, and messages larger than a
+//! datagram are split into chunks sharing a message id. TCP messages are uncompressed
+//! and delimited by a NUL byte.
+
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::time::{Duration, Instant};
+
+use flate2::read::{GzDecoder, ZlibDecoder};
+use rustc_serialize::json::Json;
+
+use event::{self, Event};
+use syslog;
+
+const CHUNK_MAGIC: [u8; 2] = [0x1e, 0x0f];
+const CHUNK_HEADER_LENGTH: usize = 12;
+/// Chunks per message allowed by the specification
+const MAX_CHUNKS: usize = 128;
+
+/// Decompresses a gzip or zlib payload, detected by its magic bytes. Other payloads
+/// are returned as is. Fails on payloads decompressing to more than `max_length`
+/// bytes.
+pub fn decompress(payload: &[u8], max_length: usize) -> io::Result<Vec<u8>> {
+    let mut decompressed = vec![];
+    let limit = max_length as u64 + 1;
+    if payload.starts_with(&[0x1f, 0x8b]) {
+        let decoder = try!(GzDecoder::new(payload));
+        try!(decoder.take(limit).read_to_end(&mut decompressed));
+    } else if payload.len() >= 2 && payload[0] == 0x78 &&
+              (payload[0] as u16 * 256 + payload[1] as u16) % 31 == 0 {
+        try!(ZlibDecoder::new(payload).take(limit).read_to_end(&mut decompressed));
+    } else {
+        decompressed.extend_from_slice(payload);
+    }
+
+    if decompressed.len() > max_length {
+        return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                  format!("GELF message larger than {} bytes", max_length)));
+    }
+    Ok(decompressed)
+}
+
+struct PendingMessage {
+    first_seen: Instant,
+    chunks: Vec<Option<Vec<u8>>>,
+    received: usize,
+    bytes: usize,
+}
+
+/// Reassembles chunked UDP messages. Incomplete messages are dropped after `timeout`,
+/// and when more than `max_pending` messages are incomplete the oldest is dropped.
+pub struct ChunkAssembler {
+    timeout: Duration,
+    max_pending: usize,
+    max_length: usize,
+    pending: HashMap<u64, PendingMessage>,
+}
+
+impl ChunkAssembler {
+    pub fn new(timeout: Duration, max_pending: usize, max_length: usize) -> ChunkAssembler {
+        ChunkAssembler {
+            timeout: timeout,
+            max_pending: max_pending,
+            max_length: max_length,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Adds a datagram, returning the complete message it holds or completes.
+    pub fn add(&mut self, datagram: &[u8]) -> Result<Option<Vec<u8>>, String> {
+        self.expire();
+        if !datagram.starts_with(&CHUNK_MAGIC) {
+            return Ok(Some(datagram.to_vec()));
+        }
+        if datagram.len() < CHUNK_HEADER_LENGTH {
+            return Err("Truncated GELF chunk header".to_owned());
+        }
+
+        let id = datagram[2..10].iter().fold(0u64, |acc, b| (acc << 8) | *b as u64);
+        let sequence = datagram[10] as usize;
+        let count = datagram[11] as usize;
+        if count == 0 || count > MAX_CHUNKS || sequence >= count {
+            return Err(format!("Invalid GELF chunk {} of {}", sequence, count));
+        }
+
+        if !self.pending.contains_key(&id) && self.pending.len() >= self.max_pending {
+            let oldest = self.pending
+                .iter()
+                .min_by_key(|&(_, m)| m.first_seen)
+                .map(|(id, _)| *id);
+            if let Some(oldest) = oldest {
+                self.pending.remove(&oldest);
+                println!("Too many incomplete GELF messages, dropping the oldest one.");
+            }
+        }
+
+        let complete = {
+            let message = self.pending.entry(id).or_insert_with(|| {
+                PendingMessage {
+                    first_seen: Instant::now(),
+                    chunks: vec![None; count],
+                    received: 0,
+                    bytes: 0,
+                }
+            });
+            if message.chunks.len() != count {
+                return Err(format!("GELF chunk count changed for message {:016x}", id));
+            }
+            if message.chunks[sequence].is_none() {
+                let data = &datagram[CHUNK_HEADER_LENGTH..];
+                message.bytes += data.len();
+                message.received += 1;
+                message.chunks[sequence] = Some(data.to_vec());
+            }
+            if message.bytes > self.max_length {
+                None
+            } else {
+                Some(message.received == count)
+            }
+        };
+
+        match complete {
+            None => {
+                self.pending.remove(&id);
+                Err(format!("GELF message larger than {} bytes", self.max_length))
+            }
+            Some(true) => {
+                let message = self.pending.remove(&id).unwrap();
+                Ok(Some(message.chunks
+                    .into_iter()
+                    .flat_map(|chunk| chunk.unwrap().into_iter())
+                    .collect()))
+            }
+            Some(false) => Ok(None),
+        }
+    }
+
+    fn expire(&mut self) {
+        let timeout = self.timeout;
+        let expired: Vec<u64> = self.pending
+            .iter()
+            .filter(|&(_, m)| m.first_seen.elapsed() > timeout)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in expired {
+            self.pending.remove(&id);
+            println!("Dropping incomplete GELF message {:016x}", id);
+        }
+    }
+
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+fn json_value(value: &Json) -> Option<String> {
+    match *value {
+        Json::String(ref s) => Some(s.clone()),
+        Json::Null => None,
+        ref other => Some(other.to_string()),
+    }
+}
+
+/// Maps a GELF message into a `Logline`: `host` is the hostname, `short_message` is
+/// stored under `line_facet` and `full_message` in its own facet. `level` is kept as a
+/// syslog severity name, and each additional `_field` becomes a `field` facet. The
+/// (deprecated) `facility` field is the facility, `default_facility` otherwise.
+pub fn parse_message(message: &[u8],
+                     received: u64,
+                     line_facet: &str,
+                     default_facility: &str)
+                     -> Result<Event, String> {
+    let json = match Json::from_str(&String::from_utf8_lossy(message)) {
+        Ok(Json::Object(object)) => object,
+        Ok(_) => return Err("GELF message is not a JSON object".to_owned()),
+        Err(e) => return Err(format!("Invalid GELF message: {}", e)),
+    };
+
+    let short_message = match json.get("short_message").and_then(json_value) {
+        Some(short_message) => short_message,
+        None => return Err("GELF message without short_message".to_owned()),
+    };
+
+    let time = json.get("timestamp").and_then(event::json_epoch_micros);
+    let mut e = Event::new(time.unwrap_or(received));
+    e.hostname = json.get("host").and_then(json_value).unwrap_or(String::new());
+    e.facility = json.get("facility")
+        .and_then(json_value)
+        .unwrap_or(default_facility.to_owned());
+    if let Some(full_message) = json.get("full_message").and_then(json_value) {
+        e.facets.insert("full_message".to_owned(), full_message);
+    }
+    if let Some(level) = json.get("level").and_then(|l| l.as_u64()) {
+        e.facets.insert("severity".to_owned(), syslog::severity_name(level as u8).to_owned());
+    }
+    for (key, value) in json.iter() {
+        if key.starts_with('_') && key != "_id" {
+            if let Some(value) = json_value(value) {
+                e.facets.insert(key[1..].to_owned(), value);
+            }
+        }
+    }
+    e.facets.insert(line_facet.to_owned(), short_message);
+    Ok(e)
+}
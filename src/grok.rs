@@ -0,0 +1,209 @@
+//! Grok patterns: regular expressions built from a library of named patterns.
+//!
+//! `%{NAME}` is replaced with the pattern called `NAME`, and `%{NAME:facet}` also
+//! captures what it matched into `facet`. A type suffix, as in `%{NUMBER:bytes:int}`,
+//! is accepted and ignored since facets are strings.
+
+use std::collections::HashMap;
+
+use regex::Regex;
+
+use event::{self, Event};
+
+static PATTERNS: &'static [(&'static str, &'static str)] = &[
+    ("USERNAME", r"[a-zA-Z0-9._-]+"),
+    ("USER", r"%{USERNAME}"),
+    ("INT", r"(?:[+-]?(?:[0-9]+))"),
+    ("BASE10NUM", r"(?:[+-]?(?:[0-9]+(?:\.[0-9]*)?|\.[0-9]+))"),
+    ("NUMBER", r"(?:%{BASE10NUM})"),
+    ("BASE16NUM", r"(?:[+-]?(?:0x)?(?:[0-9A-Fa-f]+))"),
+    ("POSINT", r"\b(?:[1-9][0-9]*)\b"),
+    ("NONNEGINT", r"\b(?:[0-9]+)\b"),
+    ("WORD", r"\b\w+\b"),
+    ("NOTSPACE", r"\S+"),
+    ("SPACE", r"\s*"),
+    ("DATA", r".*?"),
+    ("GREEDYDATA", r".*"),
+    ("QUOTEDSTRING", r#"(?:"(?:[^"\\]|\\.)*"|'(?:[^'\\]|\\.)*')"#),
+    ("QS", r"%{QUOTEDSTRING}"),
+    ("UUID", r"[A-Fa-f0-9]{8}-(?:[A-Fa-f0-9]{4}-){3}[A-Fa-f0-9]{12}"),
+    ("MAC", r"(?:[A-Fa-f0-9]{2}[:-]){5}[A-Fa-f0-9]{2}"),
+    ("IPV4",
+     concat!(r"(?:(?:25[0-5]|2[0-4][0-9]|[01]?[0-9][0-9]?)\.){3}",
+             r"(?:25[0-5]|2[0-4][0-9]|[01]?[0-9][0-9]?)")),
+    ("IPV6",
+     concat!(r"(?:[0-9A-Fa-f]{1,4}:){7}[0-9A-Fa-f]{1,4}|(?:[0-9A-Fa-f]{1,4}:){1,7}:|",
+             r"(?:[0-9A-Fa-f]{1,4}:){0,6}(?::[0-9A-Fa-f]{1,4}){1,7}|::")),
+    ("IP", r"(?:%{IPV4}|%{IPV6})"),
+    ("HOSTNAME", r"\b(?:[0-9A-Za-z][0-9A-Za-z-]{0,62})(?:\.(?:[0-9A-Za-z][0-9A-Za-z-]{0,62}))*\.?"),
+    ("IPORHOST", r"(?:%{IP}|%{HOSTNAME})"),
+    ("HOSTPORT", r"%{IPORHOST}:%{POSINT}"),
+    ("PATH", r"(?:/[^/\s?#]*)+"),
+    ("URIPROTO", r"[A-Za-z]+(?:\+[A-Za-z+]+)?"),
+    ("URIHOST", r"%{IPORHOST}(?::%{POSINT})?"),
+    ("URIPATH", r"(?:/[A-Za-z0-9$.+!*'(){},~:;=@#%&_\-]*)+"),
+    ("URIPARAM", r"\?[A-Za-z0-9$.+!*'|(){},~@#%&/=:;_?\-\[\]<>]*"),
+    ("URIPATHPARAM", r"%{URIPATH}(?:%{URIPARAM})?"),
+    ("URI", r"%{URIPROTO}://(?:%{USER}(?::[^@]*)?@)?(?:%{URIHOST})?(?:%{URIPATHPARAM})?"),
+    ("MONTH",
+     concat!(r"\b(?:Jan(?:uary)?|Feb(?:ruary)?|Mar(?:ch)?|Apr(?:il)?|May|June?|July?|",
+             r"Aug(?:ust)?|Sep(?:tember)?|Oct(?:ober)?|Nov(?:ember)?|Dec(?:ember)?)\b")),
+    ("MONTHNUM", r"(?:0?[1-9]|1[0-2])"),
+    ("MONTHDAY", r"(?:(?:0[1-9])|(?:[12][0-9])|(?:3[01])|[1-9])"),
+    ("DAY",
+     concat!(r"(?:Mon(?:day)?|Tue(?:sday)?|Wed(?:nesday)?|Thu(?:rsday)?|",
+             r"Fri(?:day)?|Sat(?:urday)?|Sun(?:day)?)")),
+    ("YEAR", r"(?:\d\d){1,2}"),
+    ("HOUR", r"(?:2[0123]|[01]?[0-9])"),
+    ("MINUTE", r"(?:[0-5][0-9])"),
+    ("SECOND", r"(?:(?:[0-5]?[0-9]|60)(?:[:.,][0-9]+)?)"),
+    ("TIME", r"%{HOUR}:%{MINUTE}:%{SECOND}"),
+    ("ISO8601_TIMEZONE", r"(?:Z|[+-]%{HOUR}(?::?%{MINUTE}))"),
+    ("TIMESTAMP_ISO8601",
+     concat!(r"%{YEAR}-%{MONTHNUM}-%{MONTHDAY}[T ]%{HOUR}:?%{MINUTE}",
+             r"(?::?%{SECOND})?%{ISO8601_TIMEZONE}?")),
+    ("HTTPDATE", r"%{MONTHDAY}/%{MONTH}/%{YEAR}:%{TIME} %{INT}"),
+    ("SYSLOGTIMESTAMP", r"%{MONTH} +%{MONTHDAY} %{TIME}"),
+    ("SYSLOGPROG", r"%{NOTSPACE:program}(?:\[%{POSINT:pid}\])?"),
+    ("SYSLOGBASE", r"%{SYSLOGTIMESTAMP:timestamp} %{IPORHOST:logsource} %{SYSLOGPROG}:"),
+    ("LOGLEVEL",
+     concat!(r"(?:[Aa]lert|ALERT|[Tt]race|TRACE|[Dd]ebug|DEBUG|[Nn]otice|NOTICE|[Ii]nfo|INFO|",
+             r"[Ww]arn?(?:ing)?|WARN?(?:ING)?|[Ee]rr?(?:or)?|ERR?(?:OR)?|[Cc]rit?(?:ical)?|",
+             r"CRIT?(?:ICAL)?|[Ff]atal|FATAL|[Ss]evere|SEVERE|EMERG(?:ENCY)?|[Ee]merg(?:ency)?)")),
+    ("COMMONAPACHELOG",
+     concat!(r"%{IPORHOST:clientip} %{USER:ident} %{USER:auth} \[%{HTTPDATE:timestamp}\] ",
+             r#""(?:%{WORD:verb} %{NOTSPACE:request}(?: HTTP/%{NUMBER:httpversion})?|"#,
+             r#"%{DATA:rawrequest})" %{NUMBER:response} (?:%{NUMBER:bytes}|-)"#)),
+    ("COMBINEDAPACHELOG", r"%{COMMONAPACHELOG} %{QS:referrer} %{QS:agent}"),
+];
+
+/// Library patterns referencing each other deeper than this are considered recursive.
+const MAX_DEPTH: usize = 32;
+
+/// A library of named patterns, to compile grok expressions with.
+pub struct Grok {
+    patterns: HashMap<String, String>,
+}
+
+/// A compiled grok expression.
+pub struct GrokPattern {
+    regex: Regex,
+    /// Regex group names, which must be identifiers, and the facets they capture
+    captures: Vec<(String, String)>,
+}
+
+impl Grok {
+    /// Starts from the built-in patterns.
+    pub fn new() -> Grok {
+        Grok {
+            patterns: PATTERNS.iter()
+                .map(|&(name, pattern)| (name.to_owned(), pattern.to_owned()))
+                .collect(),
+        }
+    }
+
+    /// Adds a pattern, or replaces a built-in one.
+    pub fn add_pattern(&mut self, name: &str, pattern: &str) {
+        self.patterns.insert(name.to_owned(), pattern.to_owned());
+    }
+
+    fn expand(&self,
+              pattern: &str,
+              depth: usize,
+              captures: &mut Vec<(String, String)>)
+              -> Result<String, String> {
+        if depth > MAX_DEPTH {
+            return Err(format!("Pattern is recursive: {}", pattern));
+        }
+        let mut expanded = String::new();
+        let mut rest = pattern;
+        while let Some(start) = rest.find("%{") {
+            expanded.push_str(&rest[..start]);
+            let end = match rest[start..].find('}') {
+                Some(end) => start + end,
+                None => return Err(format!("Unterminated %{{ in {}", pattern)),
+            };
+            let mut parts = rest[start + 2..end].splitn(3, ':');
+            let name = parts.next().unwrap_or("");
+            let library_pattern = match self.patterns.get(name) {
+                Some(library_pattern) => library_pattern,
+                None => return Err(format!("Unknown pattern {}", name)),
+            };
+            let inner = try!(self.expand(library_pattern, depth + 1, captures));
+            match parts.next() {
+                Some(facet) if !facet.is_empty() => {
+                    let group = format!("g{}", captures.len());
+                    expanded.push_str(&format!("(?P<{}>{})", group, inner));
+                    captures.push((group, facet.to_owned()));
+                }
+                _ => expanded.push_str(&format!("(?:{})", inner)),
+            }
+            rest = &rest[end + 1..];
+        }
+        expanded.push_str(rest);
+        Ok(expanded)
+    }
+
+    pub fn compile(&self, pattern: &str) -> Result<GrokPattern, String> {
+        let mut captures = vec![];
+        let expanded = try!(self.expand(pattern, 0, &mut captures));
+        let regex = try!(Regex::new(&expanded).map_err(|e| format!("{}", e)));
+        Ok(GrokPattern {
+            regex: regex,
+            captures: captures,
+        })
+    }
+}
+
+impl GrokPattern {
+    /// Returns the captured facets, or `None` when `text` does not match. Facets
+    /// captured more than once, in alternatives, get the first value matched.
+    pub fn captures(&self, text: &str) -> Option<Vec<(String, String)>> {
+        self.regex.captures(text).map(|found| {
+            let mut facets: Vec<(String, String)> = vec![];
+            for &(ref group, ref facet) in self.captures.iter() {
+                if let Some(value) = found.name(group) {
+                    if !facets.iter().any(|&(ref f, _)| f == facet) {
+                        facets.push((facet.clone(), value.to_owned()));
+                    }
+                }
+            }
+            facets
+        })
+    }
+}
+
+/// Matches a facet against grok patterns, tried in order, and stores the captures of
+/// the first one matching as facets.
+pub struct GrokFilter {
+    pub source: String,
+    pub patterns: Vec<GrokPattern>,
+    /// Captures replace existing facets when set
+    pub overwrite: bool,
+    /// Facet marking events that did not match, and its value.
+    pub failure_facet: String,
+    pub failure_tag: String,
+}
+
+impl GrokFilter {
+    /// Returns whether a pattern matched.
+    pub fn apply(&self, e: &mut Event) -> bool {
+        let captured = e.facets
+            .get(&self.source)
+            .and_then(|text| self.patterns.iter().filter_map(|p| p.captures(text)).next());
+        match captured {
+            Some(facets) => {
+                for (facet, value) in facets {
+                    if self.overwrite || !e.facets.contains_key(&facet) {
+                        e.facets.insert(facet, value);
+                    }
+                }
+                true
+            }
+            None => {
+                event::add_tag(e, &self.failure_facet, &self.failure_tag);
+                false
+            }
+        }
+    }
+}
@@ -0,0 +1,216 @@
+//! Indexer acknowledgement for the HTTP Event Collector.
+//!
+//! Every request posted on a channel gets the next ack id of that channel. The id is
+//! reported as acknowledged once all the events of the request have been confirmed
+//! by a durable output, and is forgotten as soon as the client has been told so.
+//!
+//! Like Splunk, ids that are never queried, or never confirmed, expire after
+//! `AckLimits::timeout`, and so do idle channels. Channels keep at most
+//! `max_ids_per_channel` ids, dropping the oldest first. A channel dropped and used
+//! again does not reuse the ids it gave out before.
+
+use std::cmp;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use event::Delivery;
+
+/// Ids are expired at most once per this period.
+const EXPIRY_PERIOD_SECONDS: u64 = 1;
+
+#[derive(Debug, Clone)]
+pub struct AckLimits {
+    pub timeout: Duration,
+    pub max_ids_per_channel: usize,
+    /// The least recently used channel is dropped when a new one would exceed this
+    pub max_channels: usize,
+}
+
+impl Default for AckLimits {
+    fn default() -> AckLimits {
+        AckLimits {
+            timeout: Duration::from_secs(600),
+            max_ids_per_channel: 100000,
+            max_channels: 10000,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct AckState {
+    acked: bool,
+    since: Instant,
+}
+
+#[derive(Debug)]
+struct ChannelAcks {
+    next_id: u64,
+    /// Pending and acknowledged ids, oldest first
+    ids: BTreeMap<u64, AckState>,
+    last_used: Instant,
+}
+
+#[derive(Debug)]
+pub struct AckRegistry {
+    channels: Mutex<HashMap<String, ChannelAcks>>,
+    limits: AckLimits,
+    last_expiry: Mutex<Instant>,
+    /// First id of new channels: past every id of the channels dropped so far, so that
+    /// a client polling an id of a dropped channel is never told it was acknowledged.
+    first_id: Mutex<u64>,
+}
+
+impl AckRegistry {
+    pub fn new() -> Arc<AckRegistry> {
+        AckRegistry::with_limits(AckLimits::default())
+    }
+
+    pub fn with_limits(limits: AckLimits) -> Arc<AckRegistry> {
+        Arc::new(AckRegistry {
+            channels: Mutex::new(HashMap::new()),
+            limits: limits,
+            last_expiry: Mutex::new(Instant::now()),
+            first_id: Mutex::new(0),
+        })
+    }
+
+    /// Allocates an ack id on `channel` for a request carrying `event_count` events.
+    pub fn register(registry: &Arc<AckRegistry>,
+                    channel: &str,
+                    event_count: usize)
+                    -> (u64, Arc<Delivery>) {
+        let now = Instant::now();
+        let expire = {
+            let mut last_expiry = registry.last_expiry.lock().unwrap();
+            if now >= *last_expiry &&
+               now.duration_since(*last_expiry) >= Duration::from_secs(EXPIRY_PERIOD_SECONDS) {
+                *last_expiry = now;
+                true
+            } else {
+                false
+            }
+        };
+        if expire {
+            registry.expire(now);
+        }
+
+        let id = {
+            let mut channels = registry.channels.lock().unwrap();
+            if !channels.contains_key(channel) &&
+               channels.len() >= registry.limits.max_channels {
+                let oldest = channels.iter()
+                    .min_by_key(|&(_, acks)| acks.last_used)
+                    .map(|(name, _)| name.clone());
+                if let Some(acks) = oldest.and_then(|oldest| channels.remove(&oldest)) {
+                    registry.dropped(&acks);
+                }
+            }
+            let acks = channels.entry(channel.to_owned()).or_insert_with(|| {
+                ChannelAcks {
+                    next_id: *registry.first_id.lock().unwrap(),
+                    ids: BTreeMap::new(),
+                    last_used: now,
+                }
+            });
+            let id = acks.next_id;
+            acks.next_id += 1;
+            acks.last_used = now;
+            acks.ids.insert(id,
+                            AckState {
+                                acked: false,
+                                since: now,
+                            });
+            while acks.ids.len() > registry.limits.max_ids_per_channel {
+                let oldest = *acks.ids.keys().next().unwrap();
+                acks.ids.remove(&oldest);
+            }
+            id
+        };
+
+        let owner = registry.clone();
+        let owner_channel = channel.to_owned();
+        let delivery = Delivery::new(event_count,
+                                     Box::new(move || owner.acknowledge(&owner_channel, id)));
+        if event_count == 0 {
+            registry.acknowledge(channel, id);
+        }
+        (id, delivery)
+    }
+
+    fn dropped(&self, acks: &ChannelAcks) {
+        let mut first_id = self.first_id.lock().unwrap();
+        *first_id = cmp::max(*first_id, acks.next_id);
+    }
+
+    fn acknowledge(&self, channel: &str, id: u64) {
+        let mut channels = self.channels.lock().unwrap();
+        if let Some(acks) = channels.get_mut(channel) {
+            if let Some(state) = acks.ids.get_mut(&id) {
+                state.acked = true;
+            }
+        }
+    }
+
+    /// Drops an id whose events could not be queued, so it is never reported.
+    pub fn forget(&self, channel: &str, id: u64) {
+        let mut channels = self.channels.lock().unwrap();
+        if let Some(acks) = channels.get_mut(channel) {
+            acks.ids.remove(&id);
+        }
+    }
+
+    /// Drops the ids registered `timeout` before `now`, and the channels left empty
+    /// that were not used since.
+    pub fn expire(&self, now: Instant) {
+        let timeout = self.limits.timeout;
+        // Requests registered concurrently may be more recent than `now`
+        let expired = |since: Instant| now >= since && now.duration_since(since) >= timeout;
+        let mut channels = self.channels.lock().unwrap();
+        for acks in channels.values_mut() {
+            let old: Vec<u64> = acks.ids
+                .iter()
+                .filter(|&(_, state)| expired(state.since))
+                .map(|(id, _)| *id)
+                .collect();
+            for id in old {
+                acks.ids.remove(&id);
+            }
+        }
+        let idle: Vec<String> = channels.iter()
+            .filter(|&(_, acks)| acks.ids.is_empty() && expired(acks.last_used))
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in idle {
+            if let Some(acks) = channels.remove(&name) {
+                self.dropped(&acks);
+            }
+        }
+    }
+
+    /// Returns the number of channels and ids held.
+    pub fn len(&self) -> (usize, usize) {
+        let channels = self.channels.lock().unwrap();
+        (channels.len(), channels.values().map(|acks| acks.ids.len()).sum())
+    }
+
+    /// Reports the status of `ids` on `channel`. Acknowledged ids are reported once.
+    pub fn query(&self, channel: &str, ids: &[u64]) -> Vec<(u64, bool)> {
+        let mut channels = self.channels.lock().unwrap();
+        match channels.get_mut(channel) {
+            Some(acks) => {
+                acks.last_used = Instant::now();
+                ids.iter()
+                    .map(|id| {
+                        let acked = acks.ids.get(id).map_or(false, |state| state.acked);
+                        if acked {
+                            acks.ids.remove(id);
+                        }
+                        (*id, acked)
+                    })
+                    .collect()
+            }
+            None => ids.iter().map(|id| (*id, false)).collect(),
+        }
+    }
+}
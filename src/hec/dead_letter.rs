@@ -0,0 +1,142 @@
+//! Dead-letter store for rejected HEC payloads.
+//!
+//! Every rejected request body is kept as one JSON file, named after its reception time
+//! so that the directory lists in arrival order. The oldest files are removed once the
+//! store holds more than `max_files` files or `max_bytes` bytes, the file just written
+//! excepted. Letters larger than `max_bytes` on their own are not stored.
+
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use rustc_serialize::json;
+
+use event;
+
+#[derive(RustcEncodable, RustcDecodable, Debug)]
+pub struct DeadLetter {
+    /// Reception time in µs
+    pub received: u64,
+    pub remote_addr: Option<String>,
+    pub token: Option<String>,
+    pub error: String,
+    pub body: String,
+}
+
+#[derive(Debug)]
+pub struct DeadLetterStore {
+    directory: String,
+    max_files: usize,
+    max_bytes: u64,
+    sequence: Mutex<u64>,
+}
+
+impl DeadLetterStore {
+    pub fn new(directory: String, max_files: usize, max_bytes: u64) -> DeadLetterStore {
+        DeadLetterStore {
+            directory: directory,
+            max_files: max_files,
+            max_bytes: max_bytes,
+            sequence: Mutex::new(0),
+        }
+    }
+
+    pub fn store(&self, letter: &DeadLetter) -> io::Result<String> {
+        let content = try!(json::encode(letter)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}", e))));
+        if content.len() as u64 > self.max_bytes {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                      format!("{} bytes is more than the {} bytes allowed",
+                                              content.len(),
+                                              self.max_bytes)));
+        }
+
+        let mut sequence = self.sequence.lock().unwrap();
+        try!(fs::create_dir_all(&self.directory));
+
+        let file_name = format!("{}/{:020}-{:06}.json",
+                                self.directory,
+                                letter.received,
+                                *sequence % 1000000);
+        *sequence += 1;
+        {
+            let mut f = try!(File::create(&file_name));
+            try!(f.write_all(content.as_bytes()));
+        }
+        try!(self.rotate(&file_name));
+        Ok(file_name)
+    }
+
+    /// Removes the oldest files past the limits, but never `newest`.
+    fn rotate(&self, newest: &str) -> io::Result<()> {
+        let mut files = try!(letter_files(&self.directory));
+        let mut total = files.iter().fold(0, |acc, &(_, size)| acc + size);
+        let mut count = files.len();
+
+        files.retain(|&(ref name, _)| name != newest);
+        files.reverse();
+        while count > self.max_files || total > self.max_bytes {
+            match files.pop() {
+                Some((name, size)) => {
+                    try!(fs::remove_file(&name));
+                    count -= 1;
+                    total -= size;
+                }
+                None => break,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the dead-letter files of `directory` with their size, oldest first.
+fn letter_files(directory: &str) -> io::Result<Vec<(String, u64)>> {
+    if !Path::new(directory).exists() {
+        return Ok(vec![]);
+    }
+    let mut files = vec![];
+    for entry in try!(fs::read_dir(directory)) {
+        let entry = try!(entry);
+        let path = entry.path();
+        if path.extension().map(|e| e == "json").unwrap_or(false) {
+            let size = try!(entry.metadata()).len();
+            files.push((path.to_string_lossy().into_owned(), size));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+pub fn read_letter(file_name: &str) -> io::Result<DeadLetter> {
+    let mut content = String::new();
+    try!(try!(File::open(file_name)).read_to_string(&mut content));
+    json::decode(&content)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}", e)))
+}
+
+/// Reads the dead letters of `directory`, oldest first.
+pub fn list(directory: &str) -> io::Result<Vec<(String, DeadLetter)>> {
+    let mut letters = vec![];
+    for (file_name, _) in try!(letter_files(directory)) {
+        match read_letter(&file_name) {
+            Ok(letter) => letters.push((file_name, letter)),
+            Err(e) => println!("Skipping {}: {}", file_name, e),
+        }
+    }
+    Ok(letters)
+}
+
+pub fn new_letter(remote_addr: Option<&str>,
+                  token: Option<&str>,
+                  error: String,
+                  body: &str)
+                  -> DeadLetter {
+    DeadLetter {
+        received: event::now_micros(),
+        remote_addr: remote_addr.map(|a| a.to_owned()),
+        token: token.map(|t| t.to_owned()),
+        error: error,
+        body: body.to_owned(),
+    }
+}
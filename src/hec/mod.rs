@@ -0,0 +1,381 @@
+//! Splunk HTTP Event Collector protocol, as spoken by the docker `splunk` log driver.
+
+pub mod ack;
+pub mod dead_letter;
+pub mod quota;
+pub mod response;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::SyncSender;
+use std::time::{Duration, Instant};
+
+use rustc_serialize::json::{self, Json};
+
+use event::{self, Event};
+use queue::{self, BatchSender, QueueError};
+use self::ack::{AckLimits, AckRegistry};
+use self::dead_letter::DeadLetterStore;
+use self::quota::{Limits, RateLimiter, mask_token};
+use self::response::{HecStatus, Reply};
+
+//  {"event":{"line":"2016/05/03 15:41:29 \u001b[1;33m[W] Custom config '/data/gogs/conf/app.ini' not found, ignore this if you're running first time\u001b[0m\r","source":"stdout","tag":"73825581fed7"},"time":"1462290089.642521","host":"default"}
+// {"event":{"line":"2016/05/03 15:41:29 \u001b[1;36m[T] Custom path: /data/gogs\u001b[0m\r","source":"stdout","tag":"73825581fed7"},"time":"1462290089.643815","host":"default"}
+// {"event":{"line":"May  5 06:41:42 sshd[29]: Server listening on :: port 22.\r","source":"stdout","tag":"gogs/gogs/hungry_jones/dee5ed93cbb6","attrs":{"location":"home"}},"time":"1462430502.652300","host":"default"}
+
+#[derive(RustcDecodable, Debug)]
+pub struct SplunkEvent {
+    pub line: String,
+    pub source: Option<String>,
+    pub tag: Option<String>,
+    pub attrs: Option<HashMap<String, String>>,
+}
+
+#[derive(RustcDecodable, Debug)]
+pub struct SplunkLine {
+    pub event: SplunkEvent,
+    /// Fractional epoch seconds, as a string (docker) or a number (other clients)
+    pub time: Option<Json>,
+    pub host: Option<String>,
+}
+
+impl SplunkLine {
+    /// Maps the HEC metadata into a `Logline`: `host` is the hostname, the docker tag the
+    /// facility, and each attr is a facet next to `source` and the line itself, stored
+    /// under `line_facet`. `received` is used when `time` is missing or malformed.
+    pub fn into_event(self, received: u64, line_facet: &str) -> Event {
+        let time = self.time.as_ref().and_then(event::json_epoch_micros);
+        let mut e = Event::new(time.unwrap_or(received));
+        e.hostname = self.host.unwrap_or(String::new());
+        e.facility = self.event.tag.unwrap_or(String::new());
+        if let Some(attrs) = self.event.attrs {
+            e.facets.extend(attrs);
+        }
+        if let Some(source) = self.event.source {
+            e.facets.insert("source".to_owned(), source);
+        }
+        e.facets.insert(line_facet.to_owned(), self.event.line);
+        e
+    }
+}
+
+#[derive(RustcDecodable, Debug)]
+struct AckQuery {
+    acks: Vec<u64>,
+}
+
+/// Splits a request body made of concatenated JSON objects, as sent by batching
+/// clients. A body that is not a sequence of objects is returned whole so that
+/// decoding reports the error.
+pub fn split_payload(body: &str) -> Vec<&str> {
+    let mut objects = vec![];
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut start = 0;
+
+    for (ix, chr) in body.char_indices() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if chr == '\\' {
+                escaped = true;
+            } else if chr == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match chr {
+            '"' => in_string = true,
+            '{' => {
+                if depth == 0 {
+                    start = ix;
+                }
+                depth += 1;
+            }
+            '}' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    objects.push(&body[start..ix + 1]);
+                }
+            }
+            c if depth == 0 && !c.is_whitespace() => return vec![body],
+            _ => {}
+        }
+    }
+    if depth > 0 {
+        return vec![body];
+    }
+    objects
+}
+
+/// Decodes the events of a request body. On failure, returns the status to reply with,
+/// the index of the offending event and the decoding error.
+pub fn decode_events(body: &str,
+                     received: u64,
+                     line_facet: &str)
+                     -> Result<Vec<Event>, (HecStatus, usize, String)> {
+    let mut events = vec![];
+    for (ix, payload) in split_payload(body).into_iter().enumerate() {
+        match json::decode::<SplunkLine>(payload) {
+            Ok(line) => events.push(line.into_event(received, line_facet)),
+            Err(json::DecoderError::MissingFieldError(ref field)) if field == "event" => {
+                return Err((HecStatus::EventFieldRequired,
+                            ix,
+                            format!("Missing field {}", field)));
+            }
+            Err(e) => return Err((HecStatus::InvalidDataFormat, ix, format!("{}", e))),
+        }
+    }
+    Ok(events)
+}
+
+/// What the collector needs to know about an HTTP request.
+pub struct HecRequest<'a> {
+    pub channel: Option<&'a str>,
+    /// Raw `Authorization` header, e.g. `Splunk 0c2f...`
+    pub authorization: Option<&'a str>,
+    pub remote_addr: Option<&'a str>,
+    pub body: &'a str,
+}
+
+impl<'a> HecRequest<'a> {
+    pub fn token(&self) -> Option<&'a str> {
+        self.authorization
+            .and_then(|a| if a.starts_with("Splunk ") {
+                Some(a["Splunk ".len()..].trim())
+            } else {
+                None
+            })
+    }
+}
+
+#[derive(Debug)]
+pub struct CollectorOptions {
+    /// Enables indexer acknowledgement
+    pub ack: bool,
+    /// How long, and how many, ack ids are kept
+    pub ack_limits: AckLimits,
+    /// Accepted tokens. Any token, or none, is accepted when empty.
+    pub tokens: Vec<String>,
+    /// Facet holding the log line
+    pub line_facet: String,
+    /// Where rejected payloads are kept
+    pub dead_letters: Option<DeadLetterStore>,
+    /// Rate limits and daily quota, applied per token
+    pub limits: Limits,
+    /// Events accepted and not handed to the pipeline yet, beyond which requests are
+    /// refused as busy
+    pub queue_size: usize,
+}
+
+impl Default for CollectorOptions {
+    fn default() -> CollectorOptions {
+        CollectorOptions {
+            ack: false,
+            ack_limits: AckLimits::default(),
+            tokens: vec![],
+            line_facet: "stdout".to_owned(),
+            dead_letters: None,
+            limits: Limits {
+                events_per_second: None,
+                bytes_per_second: None,
+                burst_seconds: 1.0,
+                daily_bytes: None,
+            },
+            queue_size: queue::DEFAULT_CAPACITY,
+        }
+    }
+}
+
+/// Health is reported as degraded for this long after the queue was found full.
+const BUSY_SECONDS: u64 = 5;
+
+/// Request handling for the collector endpoints, independent from the HTTP server.
+pub struct Collector {
+    queue: BatchSender,
+    acks: Option<Arc<AckRegistry>>,
+    tokens: Vec<String>,
+    line_facet: String,
+    dead_letters: Option<DeadLetterStore>,
+    limiter: Option<RateLimiter>,
+    last_full: Mutex<Option<Instant>>,
+}
+
+impl Collector {
+    pub fn new(tx: SyncSender<Event>, options: CollectorOptions) -> Collector {
+        Collector {
+            queue: BatchSender::new(tx, options.queue_size),
+            acks: if options.ack {
+                Some(AckRegistry::with_limits(options.ack_limits))
+            } else {
+                None
+            },
+            tokens: options.tokens,
+            line_facet: options.line_facet,
+            dead_letters: options.dead_letters,
+            limiter: if options.limits.is_unlimited() {
+                None
+            } else {
+                Some(RateLimiter::new(options.limits))
+            },
+            last_full: Mutex::new(None),
+        }
+    }
+
+    fn authorize(&self, request: &HecRequest) -> Option<Reply> {
+        if self.tokens.is_empty() {
+            return None;
+        }
+        if request.authorization.is_none() {
+            return Some(Reply::new(HecStatus::TokenRequired));
+        }
+        match request.token() {
+            Some(token) if self.tokens.iter().any(|t| t == token) => None,
+            Some(_) => Some(Reply::new(HecStatus::InvalidToken)),
+            None => Some(Reply::new(HecStatus::InvalidAuthorization)),
+        }
+    }
+
+    fn reject(&self, request: &HecRequest, error: String) {
+        if let Some(ref store) = self.dead_letters {
+            let letter = dead_letter::new_letter(request.remote_addr,
+                                                 request.token(),
+                                                 error,
+                                                 request.body);
+            if let Err(e) = store.store(&letter) {
+                println!("Unable to store dead letter: {}", e);
+            }
+        }
+    }
+
+    fn send(&self, events: Vec<Event>) -> Result<(), HecStatus> {
+        match self.queue.send(events) {
+            Ok(()) => {
+                *self.last_full.lock().unwrap() = None;
+                Ok(())
+            }
+            Err(QueueError::Full) => {
+                *self.last_full.lock().unwrap() = Some(Instant::now());
+                Err(HecStatus::ServerBusy)
+            }
+            Err(QueueError::Disconnected) => Err(HecStatus::InternalError),
+        }
+    }
+
+    /// Gives back the limits taken by a request of `events` events that was not queued.
+    fn refund(&self, request: &HecRequest, events: usize) {
+        if let Some(ref limiter) = self.limiter {
+            limiter.refund(request.token().unwrap_or(""), events, request.body.len());
+        }
+    }
+
+    pub fn post_events(&self, request: &HecRequest) -> Reply {
+        if let Some(reply) = self.authorize(request) {
+            return reply;
+        }
+        if request.body.trim().is_empty() {
+            return Reply::new(HecStatus::NoData);
+        }
+
+        let mut events = match decode_events(request.body,
+                                             event::now_micros(),
+                                             &self.line_facet) {
+            Ok(events) => events,
+            Err((status, ix, error)) => {
+                println!("Parsing failed: {}", error);
+                self.reject(request, error);
+                return Reply::invalid_event(status, ix);
+            }
+        };
+
+        if let Some(ref limiter) = self.limiter {
+            let token = request.token().unwrap_or("");
+            if let Err(retry_after) = limiter.check(token, events.len(), request.body.len()) {
+                println!("Throttled {} events for token {}", events.len(), mask_token(token));
+                return Reply::throttled(retry_after);
+            }
+        }
+
+        let ack_id = match self.acks {
+            Some(ref registry) => {
+                match request.channel {
+                    Some(channel) => {
+                        let (id, delivery) =
+                            AckRegistry::register(registry, channel, events.len());
+                        for e in events.iter_mut() {
+                            e.acks.push(delivery.clone());
+                        }
+                        Some(id)
+                    }
+                    None => {
+                        self.refund(request, events.len());
+                        return Reply::new(HecStatus::DataChannelMissing);
+                    }
+                }
+            }
+            None => None,
+        };
+
+        let count = events.len();
+        if let Err(status) = self.send(events) {
+            self.refund(request, count);
+            // Nothing was queued, the client retries with a new ack id
+            if let (Some(id), Some(registry), Some(channel)) =
+                   (ack_id, self.acks.as_ref(), request.channel) {
+                registry.forget(channel, id);
+            }
+            return Reply::new(status);
+        }
+
+        match ack_id {
+            Some(id) => Reply::acknowledged(id),
+            None => Reply::new(HecStatus::Success),
+        }
+    }
+
+    pub fn query_acks(&self, request: &HecRequest) -> Reply {
+        if let Some(reply) = self.authorize(request) {
+            return reply;
+        }
+        let registry = match self.acks {
+            Some(ref registry) => registry,
+            None => return Reply::new(HecStatus::AckDisabled),
+        };
+        let channel = match request.channel {
+            Some(channel) => channel,
+            None => return Reply::new(HecStatus::DataChannelMissing),
+        };
+        match json::decode::<AckQuery>(request.body) {
+            Ok(query) => Reply::ack_statuses(registry.query(channel, &query.acks)),
+            Err(_) => Reply::new(HecStatus::InvalidDataFormat),
+        }
+    }
+
+    /// Reports how many events were throttled for each token, to the holders of a token.
+    pub fn throttled(&self, request: &HecRequest) -> Reply {
+        if let Some(reply) = self.authorize(request) {
+            return reply;
+        }
+        let counts = match self.limiter {
+            Some(ref limiter) => {
+                limiter.throttled()
+                    .into_iter()
+                    .map(|(token, count)| (mask_token(&token), count))
+                    .collect()
+            }
+            None => vec![],
+        };
+        Reply::throttled_counts(counts)
+    }
+
+    /// Reports the collector as unhealthy while the pipeline queue is full.
+    pub fn health(&self) -> Reply {
+        match *self.last_full.lock().unwrap() {
+            Some(at) if at.elapsed() < Duration::from_secs(BUSY_SECONDS) => {
+                Reply::new(HecStatus::QueuesFull)
+            }
+            _ => Reply::new(HecStatus::Healthy),
+        }
+    }
+}
@@ -0,0 +1,267 @@
+//! Per-token rate limiting and daily ingestion quotas for the HTTP Event Collector.
+//!
+//! Each token gets its own token buckets, one for events and one for bytes, refilled
+//! at the configured rate and holding up to `burst_seconds` worth of traffic. A request
+//! larger than a full bucket is let through, leaving the bucket in debt.
+//!
+//! Tokens unused for `IDLE_SECONDS` are forgotten, with their throttled counts, once
+//! their buckets are full again and their daily quota does not matter anymore. While
+//! `MAX_TOKENS` tokens are tracked, new ones share the limits of requests without a
+//! token.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use event;
+
+const DAY_IN_MICROS: u64 = 86400 * 1000000;
+pub const IDLE_SECONDS: u64 = 3600;
+/// Idle tokens are looked for at most once per this period.
+const EXPIRY_PERIOD_SECONDS: u64 = 60;
+pub const MAX_TOKENS: usize = 10000;
+
+#[derive(Debug, Clone)]
+pub struct Limits {
+    pub events_per_second: Option<f64>,
+    pub bytes_per_second: Option<f64>,
+    pub burst_seconds: f64,
+    pub daily_bytes: Option<u64>,
+}
+
+impl Limits {
+    pub fn is_unlimited(&self) -> bool {
+        self.events_per_second.is_none() && self.bytes_per_second.is_none() &&
+        self.daily_bytes.is_none()
+    }
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    available: f64,
+    last: Instant,
+}
+
+fn seconds(d: Duration) -> f64 {
+    d.as_secs() as f64 + d.subsec_nanos() as f64 / 1e9
+}
+
+impl TokenBucket {
+    fn new(rate: f64, burst_seconds: f64) -> TokenBucket {
+        let capacity = (rate * burst_seconds).max(1.0);
+        TokenBucket {
+            rate: rate,
+            capacity: capacity,
+            available: capacity,
+            last: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        if now < self.last {
+            return;
+        }
+        self.available = (self.available + seconds(now - self.last) * self.rate)
+            .min(self.capacity);
+        self.last = now;
+    }
+
+    /// Returns how many seconds to wait before `amount` can be taken.
+    fn wait(&mut self, amount: f64) -> f64 {
+        self.refill(Instant::now());
+        if self.available >= amount || self.available >= self.capacity {
+            0.0
+        } else {
+            (amount.min(self.capacity) - self.available) / self.rate
+        }
+    }
+
+    fn take(&mut self, amount: f64) {
+        self.available -= amount;
+    }
+
+    fn give(&mut self, amount: f64) {
+        self.available = (self.available + amount).min(self.capacity);
+    }
+
+    fn is_full(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        self.available >= self.capacity
+    }
+}
+
+#[derive(Debug)]
+struct TokenState {
+    events: Option<TokenBucket>,
+    bytes: Option<TokenBucket>,
+    day: u64,
+    day_bytes: u64,
+    throttled_events: u64,
+    last_used: Instant,
+}
+
+impl TokenState {
+    /// Whether forgetting the token would not grant it anything. `today` is only set
+    /// when there is a daily quota.
+    fn is_idle(&mut self, now: Instant, today: Option<u64>) -> bool {
+        now >= self.last_used &&
+        now.duration_since(self.last_used) >= Duration::from_secs(IDLE_SECONDS) &&
+        today.map_or(true, |today| self.day != today || self.day_bytes == 0) &&
+        self.events.as_mut().map_or(true, |bucket| bucket.is_full(now)) &&
+        self.bytes.as_mut().map_or(true, |bucket| bucket.is_full(now))
+    }
+}
+
+/// Returns the entry `token` is accounted under.
+fn tracked<'a>(tokens: &HashMap<String, TokenState>, token: &'a str) -> &'a str {
+    if tokens.len() < MAX_TOKENS || tokens.contains_key(token) {
+        token
+    } else {
+        ""
+    }
+}
+
+#[derive(Debug)]
+pub struct RateLimiter {
+    limits: Limits,
+    tokens: Mutex<HashMap<String, TokenState>>,
+    last_expiry: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: Limits) -> RateLimiter {
+        RateLimiter {
+            limits: limits,
+            tokens: Mutex::new(HashMap::new()),
+            last_expiry: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Accounts for a request of `events` events and `bytes` bytes sent with `token`.
+    /// When it exceeds the limits, returns the number of seconds the client should
+    /// wait before retrying.
+    pub fn check(&self, token: &str, events: usize, bytes: usize) -> Result<(), u64> {
+        let expire = {
+            let mut last_expiry = self.last_expiry.lock().unwrap();
+            if last_expiry.elapsed() >= Duration::from_secs(EXPIRY_PERIOD_SECONDS) {
+                *last_expiry = Instant::now();
+                true
+            } else {
+                false
+            }
+        };
+        if expire {
+            self.expire(Instant::now());
+        }
+
+        let limits = &self.limits;
+        let mut tokens = self.tokens.lock().unwrap();
+        let token = tracked(&tokens, token);
+        let state = tokens.entry(token.to_owned()).or_insert_with(|| {
+            TokenState {
+                events: limits.events_per_second
+                    .map(|rate| TokenBucket::new(rate, limits.burst_seconds)),
+                bytes: limits.bytes_per_second
+                    .map(|rate| TokenBucket::new(rate, limits.burst_seconds)),
+                day: 0,
+                day_bytes: 0,
+                throttled_events: 0,
+                last_used: Instant::now(),
+            }
+        });
+        state.last_used = Instant::now();
+
+        let now = event::now_micros();
+        if now / DAY_IN_MICROS != state.day {
+            state.day = now / DAY_IN_MICROS;
+            state.day_bytes = 0;
+        }
+
+        let mut wait = 0.0f64;
+        if let Some(quota) = limits.daily_bytes {
+            if state.day_bytes + bytes as u64 > quota {
+                wait = ((DAY_IN_MICROS - now % DAY_IN_MICROS) / 1000000) as f64;
+            }
+        }
+        if let Some(ref mut bucket) = state.events {
+            wait = wait.max(bucket.wait(events as f64));
+        }
+        if let Some(ref mut bucket) = state.bytes {
+            wait = wait.max(bucket.wait(bytes as f64));
+        }
+
+        if wait > 0.0 {
+            state.throttled_events += events as u64;
+            return Err(wait.ceil() as u64);
+        }
+
+        state.day_bytes += bytes as u64;
+        if let Some(ref mut bucket) = state.events {
+            bucket.take(events as f64);
+        }
+        if let Some(ref mut bucket) = state.bytes {
+            bucket.take(bytes as f64);
+        }
+        Ok(())
+    }
+
+    /// Gives back what `check` took for a request which was not accepted after all.
+    pub fn refund(&self, token: &str, events: usize, bytes: usize) {
+        let mut tokens = self.tokens.lock().unwrap();
+        let token = tracked(&tokens, token);
+        if let Some(state) = tokens.get_mut(token) {
+            state.day_bytes = state.day_bytes.saturating_sub(bytes as u64);
+            if let Some(ref mut bucket) = state.events {
+                bucket.give(events as f64);
+            }
+            if let Some(ref mut bucket) = state.bytes {
+                bucket.give(bytes as f64);
+            }
+        }
+    }
+
+    /// Forgets the tokens idle at `now`.
+    pub fn expire(&self, now: Instant) {
+        let today = self.limits.daily_bytes.map(|_| event::now_micros() / DAY_IN_MICROS);
+        let mut tokens = self.tokens.lock().unwrap();
+        let idle: Vec<String> = tokens.iter_mut()
+            .filter_map(|(token, state)| {
+                if state.is_idle(now, today) {
+                    Some(token.clone())
+                } else {
+                    None
+                }
+            })
+            .collect();
+        for token in idle {
+            tokens.remove(&token);
+        }
+    }
+
+    /// Returns the number of tokens tracked.
+    pub fn len(&self) -> usize {
+        self.tokens.lock().unwrap().len()
+    }
+
+    /// Returns how many events were throttled for each token, most throttled first.
+    pub fn throttled(&self) -> Vec<(String, u64)> {
+        let tokens = self.tokens.lock().unwrap();
+        let mut throttled: Vec<(String, u64)> = tokens.iter()
+            .filter(|&(_, state)| state.throttled_events > 0)
+            .map(|(token, state)| (token.clone(), state.throttled_events))
+            .collect();
+        throttled.sort_by(|a, b| b.1.cmp(&a.1));
+        throttled
+    }
+}
+
+/// Keeps enough of a token to recognize it in logs and reports.
+pub fn mask_token(token: &str) -> String {
+    if token.is_empty() {
+        return "(none)".to_owned();
+    }
+    let prefix: String = token.chars().take(8).collect();
+    format!("{}...", prefix)
+}
@@ -0,0 +1,164 @@
+//! Splunk-compatible JSON replies of the collector endpoints.
+
+use std::collections::BTreeMap;
+
+use hyper::status::StatusCode;
+use rustc_serialize::json::Json;
+
+/// Status codes of the HTTP Event Collector, as documented by Splunk.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HecStatus {
+    Success,
+    TokenRequired,
+    InvalidAuthorization,
+    InvalidToken,
+    NoData,
+    InvalidDataFormat,
+    InternalError,
+    ServerBusy,
+    DataChannelMissing,
+    InvalidDataChannel,
+    EventFieldRequired,
+    EventFieldBlank,
+    AckDisabled,
+    Healthy,
+    QueuesFull,
+    Throttled,
+    RequestTooLarge,
+}
+
+impl HecStatus {
+    pub fn code(&self) -> u64 {
+        match *self {
+            HecStatus::Success => 0,
+            HecStatus::TokenRequired => 2,
+            HecStatus::InvalidAuthorization => 3,
+            HecStatus::InvalidToken => 4,
+            HecStatus::NoData => 5,
+            HecStatus::InvalidDataFormat | HecStatus::RequestTooLarge => 6,
+            HecStatus::InternalError => 8,
+            HecStatus::ServerBusy | HecStatus::Throttled => 9,
+            HecStatus::DataChannelMissing => 10,
+            HecStatus::InvalidDataChannel => 11,
+            HecStatus::EventFieldRequired => 12,
+            HecStatus::EventFieldBlank => 13,
+            HecStatus::AckDisabled => 14,
+            HecStatus::Healthy => 17,
+            HecStatus::QueuesFull => 18,
+        }
+    }
+
+    pub fn text(&self) -> &'static str {
+        match *self {
+            HecStatus::Success => "Success",
+            HecStatus::TokenRequired => "Token is required",
+            HecStatus::InvalidAuthorization => "Invalid authorization",
+            HecStatus::InvalidToken => "Invalid token",
+            HecStatus::NoData => "No data",
+            HecStatus::InvalidDataFormat => "Invalid data format",
+            HecStatus::InternalError => "Internal server error",
+            HecStatus::ServerBusy => "Server is busy",
+            HecStatus::DataChannelMissing => "Data channel is missing",
+            HecStatus::InvalidDataChannel => "Invalid data channel",
+            HecStatus::EventFieldRequired => "Event field is required",
+            HecStatus::EventFieldBlank => "Event field cannot be blank",
+            HecStatus::AckDisabled => "ACK is disabled",
+            HecStatus::Healthy => "HEC is healthy",
+            HecStatus::QueuesFull => "HEC is unhealthy, queues are full",
+            HecStatus::Throttled => "Too many requests",
+            HecStatus::RequestTooLarge => "Request body is too large",
+        }
+    }
+
+    pub fn http_status(&self) -> StatusCode {
+        match *self {
+            HecStatus::Success | HecStatus::Healthy => StatusCode::Ok,
+            HecStatus::TokenRequired |
+            HecStatus::InvalidAuthorization => StatusCode::Unauthorized,
+            HecStatus::InvalidToken => StatusCode::Forbidden,
+            HecStatus::InternalError => StatusCode::InternalServerError,
+            HecStatus::ServerBusy | HecStatus::QueuesFull => StatusCode::ServiceUnavailable,
+            HecStatus::Throttled => StatusCode::TooManyRequests,
+            HecStatus::RequestTooLarge => StatusCode::PayloadTooLarge,
+            _ => StatusCode::BadRequest,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Reply {
+    pub status: StatusCode,
+    pub body: String,
+    /// Seconds to send in a `Retry-After` header
+    pub retry_after: Option<u64>,
+}
+
+/// Formats `{"text":...,"code":...}` in Splunk's field order, followed by `extra` fields.
+fn status_body(status: HecStatus, extra: &[(&str, u64)]) -> String {
+    let mut body = format!("{{\"text\":{},\"code\":{}",
+                           Json::String(status.text().to_owned()),
+                           status.code());
+    for &(key, value) in extra {
+        body.push_str(&format!(",\"{}\":{}", key, value));
+    }
+    body.push('}');
+    body
+}
+
+impl Reply {
+    pub fn new(status: HecStatus) -> Reply {
+        Reply {
+            status: status.http_status(),
+            body: status_body(status, &[]),
+            retry_after: None,
+        }
+    }
+
+    pub fn throttled(retry_after: u64) -> Reply {
+        Reply { retry_after: Some(retry_after), ..Reply::new(HecStatus::Throttled) }
+    }
+
+    /// Rejects a request because its `event_number`th event (0-based) is invalid.
+    pub fn invalid_event(status: HecStatus, event_number: usize) -> Reply {
+        Reply {
+            status: status.http_status(),
+            body: status_body(status, &[("invalid-event-number", event_number as u64)]),
+            retry_after: None,
+        }
+    }
+
+    pub fn acknowledged(ack_id: u64) -> Reply {
+        Reply {
+            status: StatusCode::Ok,
+            body: status_body(HecStatus::Success, &[("ackId", ack_id)]),
+            retry_after: None,
+        }
+    }
+
+    pub fn ack_statuses(statuses: Vec<(u64, bool)>) -> Reply {
+        let mut acks = BTreeMap::new();
+        for (id, acked) in statuses {
+            acks.insert(id.to_string(), Json::Boolean(acked));
+        }
+        let mut object = BTreeMap::new();
+        object.insert("acks".to_owned(), Json::Object(acks));
+        Reply {
+            status: StatusCode::Ok,
+            body: Json::Object(object).to_string(),
+            retry_after: None,
+        }
+    }
+
+    /// Lists how many events were throttled per (masked) token.
+    pub fn throttled_counts(counts: Vec<(String, u64)>) -> Reply {
+        let mut object = BTreeMap::new();
+        for (token, count) in counts {
+            object.insert(token, Json::U64(count));
+        }
+        Reply {
+            status: StatusCode::Ok,
+            body: Json::Object(object).to_string(),
+            retry_after: None,
+        }
+    }
+}
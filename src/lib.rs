@@ -4,9 +4,42 @@ extern crate chrono;
 extern crate regex;
 extern crate itertools;
 extern crate memmap;
+extern crate rustc_serialize;
+extern crate hyper;
+extern crate glob;
+extern crate flate2;
+extern crate libc;
+extern crate openssl;
+extern crate rand;
 #[macro_use]
-extern crate nom;
+extern crate nickel;
 
 pub mod config;
 pub mod configuration_items;
+pub mod cri;
+pub mod date;
+pub mod docker;
+pub mod docker_plugin;
+pub mod event;
+pub mod fake_apache;
+pub mod fields;
+pub mod fluent;
+pub mod gelf;
+pub mod grok;
+pub mod hec;
+pub mod lines;
 pub mod logmanager;
+pub mod loki;
+pub mod msgpack;
+pub mod multiline;
+pub mod otlp;
+pub mod pipeline;
+pub mod protobuf;
+pub mod queue;
+pub mod replay;
+pub mod sanitize;
+pub mod severity;
+pub mod snappy;
+pub mod syslog;
+pub mod tail;
+pub mod transport;
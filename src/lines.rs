@@ -0,0 +1,175 @@
+//! Newline delimited records received over sockets, either plain text or JSON objects.
+
+use std::cmp;
+use std::io::{self, BufRead};
+use std::sync::mpsc::SyncSender;
+
+use rustc_serialize::json::Json;
+
+use event::{self, Event};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Codec {
+    /// Each line is the content of the line facet
+    Plain,
+    /// Each line is a JSON object, whose fields are facets
+    Json,
+}
+
+impl Codec {
+    pub fn from_name(name: &str) -> Option<Codec> {
+        match name {
+            "plain" => Some(Codec::Plain),
+            "json" => Some(Codec::Json),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LineOptions {
+    pub codec: Codec,
+    pub line_facet: String,
+    /// Facility of records without a `facility` field
+    pub tag: String,
+    /// Longer lines are truncated
+    pub max_line: usize,
+}
+
+/// Reads the next record ending with `delimiter`, without it. Only the first
+/// `max_length` bytes of a longer record are kept, the rest is skipped. Returns `None`
+/// at the end of the stream.
+pub fn read_record<R: BufRead>(reader: &mut R,
+                               delimiter: u8,
+                               max_length: usize)
+                               -> io::Result<Option<Vec<u8>>> {
+    let mut record = vec![];
+    let mut read_any = false;
+    loop {
+        let (done, used) = {
+            let buffer = try!(reader.fill_buf());
+            if buffer.is_empty() {
+                if !read_any {
+                    return Ok(None);
+                }
+                break;
+            }
+            read_any = true;
+            let (chunk, done) = match buffer.iter().position(|b| *b == delimiter) {
+                Some(ix) => (&buffer[..ix], Some(ix + 1)),
+                None => (buffer, None),
+            };
+            let room = max_length.saturating_sub(record.len());
+            record.extend_from_slice(&chunk[..cmp::min(room, chunk.len())]);
+            (done.is_some(), done.unwrap_or(buffer.len()))
+        };
+        reader.consume(used);
+        if done {
+            break;
+        }
+    }
+    Ok(Some(record))
+}
+
+/// Reads the next line, without its terminator, `\n` or `\r\n`. Only the first
+/// `max_line` bytes of a longer line are kept, the rest is skipped. Returns `None` at
+/// the end of the stream.
+pub fn read_line<R: BufRead>(reader: &mut R, max_line: usize) -> io::Result<Option<Vec<u8>>> {
+    let mut line = try!(read_record(reader, b'\n', max_line));
+    if let Some(ref mut line) = line {
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+    }
+    Ok(line)
+}
+
+/// Returns a JSON string as is, and any other value as JSON text.
+pub fn json_string(value: &Json) -> String {
+    match *value {
+        Json::String(ref s) => s.clone(),
+        ref other => other.to_string(),
+    }
+}
+
+/// Maps a line into a `Logline`, next to the `source` facets describing the peer.
+///
+/// With the JSON codec, `time` or `timestamp` (epoch seconds or RFC 3339) is the time,
+/// `host` or `hostname` the hostname, `facility` the facility, and every other field a
+/// facet. Lines that are not JSON objects are kept as plain text. Blank lines are
+/// skipped. The `source` facets override fields of the same name.
+pub fn decode(line: &[u8],
+              received: u64,
+              source: &[(String, String)],
+              options: &LineOptions)
+              -> Option<Event> {
+    let text = String::from_utf8_lossy(line);
+    if text.trim().is_empty() {
+        return None;
+    }
+
+    let mut e = Event::new(received);
+    e.facility = options.tag.clone();
+
+    let object = match options.codec {
+        Codec::Json => {
+            match Json::from_str(&text) {
+                Ok(Json::Object(object)) => Some(object),
+                _ => None,
+            }
+        }
+        Codec::Plain => None,
+    };
+    match object {
+        Some(object) => {
+            for (name, value) in object.into_iter() {
+                match name.as_ref() {
+                    "time" | "timestamp" => {
+                        let time = match value {
+                            Json::String(ref s) => {
+                                event::parse_epoch_micros(s)
+                                    .or_else(|| event::parse_rfc3339_micros(s))
+                            }
+                            ref other => event::json_epoch_micros(other),
+                        };
+                        match time {
+                            Some(time) => e.time = time,
+                            None => {
+                                e.facets.insert(name.clone(), json_string(&value));
+                            }
+                        }
+                    }
+                    "host" | "hostname" => e.hostname = json_string(&value),
+                    "facility" => e.facility = json_string(&value),
+                    _ => {
+                        e.facets.insert(name.clone(), json_string(&value));
+                    }
+                }
+            }
+        }
+        None => {
+            e.facets.insert(options.line_facet.clone(), text.into_owned());
+        }
+    }
+    // Last, so that senders cannot forge them
+    for &(ref name, ref value) in source.iter() {
+        e.facets.insert(name.clone(), value.clone());
+    }
+    Some(e)
+}
+
+/// Sends the records of a stream until its end, or until the pipeline stops.
+pub fn forward<R: BufRead>(reader: &mut R,
+                           source: &[(String, String)],
+                           tx: &SyncSender<Event>,
+                           options: &LineOptions)
+                           -> io::Result<()> {
+    while let Some(line) = try!(read_line(reader, options.max_line)) {
+        if let Some(e) = decode(&line, event::now_micros(), source, options) {
+            if tx.send(e).is_err() {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
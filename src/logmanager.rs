@@ -1,8 +1,9 @@
-use std::fs::File;
+use std::fs::{self, File};
 use std::fmt;
 use std::io::{self, Read, BufReader};
 use std::thread;
 use std::borrow::Borrow;
+use std::path::PathBuf;
 use std::thread::JoinHandle;
 use std::sync::mpsc::{channel, Sender, Receiver};
 use itertools::Itertools;
@@ -20,6 +21,8 @@ use logformat::schema_capnp::{logblock, logline};
 
 use regex::Regex;
 
+use event::Event;
+
 // We derive `Debug` because all types should probably derive `Debug`.
 #[derive(Debug)]
 pub enum ReadError {
@@ -203,6 +206,43 @@ impl LogFile {
         let re = Regex::new(needle).unwrap();
         self.gen_find(field, needle, |_, haystack| re.is_match(haystack))
     }
+
+    /// Decodes every line of the block.
+    pub fn events(&self) -> Result<Vec<Event>, ReadError> {
+        let sl = unsafe { self.content.as_slice() };
+
+        let mut br = BufReader::new(sl);
+        let message_reader = try!(serialize::read_message(&mut br, self.reader_options()));
+        let logblock = try!(message_reader.get_root::<logblock::Reader>());
+        let mut events = vec![];
+        for line_reader in try!(logblock.get_entries()).iter() {
+            events.push(try!(Event::from_reader(&line_reader)));
+        }
+        Ok(events)
+    }
+
+    /// Returns the times of the oldest and newest lines of the block, if it has any.
+    /// Blocks written without their time range in the header are scanned.
+    pub fn time_range(&self) -> Result<Option<(u64, u64)>, ReadError> {
+        let sl = unsafe { self.content.as_slice() };
+
+        let mut br = BufReader::new(sl);
+        let message_reader = try!(serialize::read_message(&mut br, self.reader_options()));
+        let logblock = try!(message_reader.get_root::<logblock::Reader>());
+        if logblock.get_end_time() > 0 {
+            return Ok(Some((logblock.get_start_time(), logblock.get_end_time())));
+        }
+        let range = try!(logblock.get_entries()).iter().fold(None, |range, line_reader| {
+            let time = line_reader.get_time();
+            match range {
+                Some((first, last)) if time < first => Some((time, last)),
+                Some((first, last)) if time > last => Some((first, time)),
+                Some(range) => Some(range),
+                None => Some((time, time)),
+            }
+        });
+        Ok(range)
+    }
 }
 
 pub struct LogFileThread {
@@ -428,9 +468,32 @@ pub fn byte_to_human(byte: usize) -> String {
 
 }
 
+/// Lists the log blocks found in `directory` and its subdirectories, sorted by name.
+pub fn archive_files(directory: &str) -> io::Result<Vec<String>> {
+    let mut files = vec![];
+    let mut directories = vec![PathBuf::from(directory)];
+    while let Some(dir) = directories.pop() {
+        for entry in try!(fs::read_dir(&dir)) {
+            let path = try!(entry).path();
+            if path.is_dir() {
+                directories.push(path);
+            } else if path.extension().map(|e| e == "capnp").unwrap_or(false) {
+                files.push(path.to_string_lossy().into_owned());
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Maps the block `file_name`. Unreadable and empty files, which cannot be mapped, are
+/// reported as errors.
 pub fn read_log_block(file_name: &str) -> Result<LogFile, ReadError> {
+    if try!(fs::metadata(file_name)).len() == 0 {
+        return Err(ReadError::Io(io::Error::new(io::ErrorKind::InvalidData, "empty file")));
+    }
     // let mut f = try!(File::open(file_name));
-    let file_mmap = Mmap::open_path(file_name, Protection::Read).unwrap();
+    let file_mmap = try!(Mmap::open_path(file_name, Protection::Read));
     // let buffer: &[u8] = unsafe { file_mmap.as_slice() };
 
     // let mut buffer = Vec::new();
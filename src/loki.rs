@@ -0,0 +1,295 @@
+//! Loki push API (`POST /loki/api/v1/push`), as used by Promtail and Grafana Agent.
+//!
+//! Requests are either snappy compressed protobuf `PushRequest`s or JSON documents
+//! `{"streams":[{"stream":{LABELS},"values":[["<ns>","<line>"],...]}]}`, optionally
+//! gzip compressed. Stream labels and structured metadata become facets; the `job`
+//! label is the facility and the `host` (or `hostname`) label the hostname.
+
+use std::io::Read;
+use std::sync::mpsc::SyncSender;
+
+use flate2::read::GzDecoder;
+use hyper::status::StatusCode;
+use rustc_serialize::json::Json;
+
+use event::Event;
+use protobuf;
+use queue::{BatchSender, QueueError};
+use snappy;
+
+#[derive(Debug, Clone)]
+pub struct LokiOptions {
+    pub line_facet: String,
+    /// Facility of streams without a `job` label
+    pub tag: String,
+    /// Largest accepted request body, once decompressed
+    pub max_length: usize,
+    /// Entries accepted and not handed to the pipeline yet, beyond which pushes are
+    /// refused as unavailable
+    pub queue_size: usize,
+}
+
+/// Parses a Prometheus label set such as `{job="varlogs", host="web-1"}`.
+pub fn parse_labels(labels: &str) -> Result<Vec<(String, String)>, String> {
+    let labels = labels.trim();
+    if !labels.starts_with('{') || !labels.ends_with('}') {
+        return Err(format!("Invalid labels {}", labels));
+    }
+    let mut chars = labels[1..labels.len() - 1].chars().peekable();
+    let mut pairs = vec![];
+    loop {
+        while chars.peek().map(|c| c.is_whitespace() || *c == ',').unwrap_or(false) {
+            chars.next();
+        }
+        if chars.peek().is_none() {
+            return Ok(pairs);
+        }
+
+        let mut name = String::new();
+        while let Some(&c) = chars.peek() {
+            if c == '=' || c.is_whitespace() {
+                break;
+            }
+            name.push(c);
+            chars.next();
+        }
+        while chars.peek().map(|c| c.is_whitespace()).unwrap_or(false) {
+            chars.next();
+        }
+        if chars.next() != Some('=') {
+            return Err(format!("Missing = after label {}", name));
+        }
+        while chars.peek().map(|c| c.is_whitespace()).unwrap_or(false) {
+            chars.next();
+        }
+        if chars.next() != Some('"') {
+            return Err(format!("Missing value for label {}", name));
+        }
+        let mut value = String::new();
+        loop {
+            match chars.next() {
+                Some('\\') => {
+                    match chars.next() {
+                        Some('n') => value.push('\n'),
+                        Some(c) => value.push(c),
+                        None => return Err(format!("Unterminated value for label {}", name)),
+                    }
+                }
+                Some('"') => break,
+                Some(c) => value.push(c),
+                None => return Err(format!("Unterminated value for label {}", name)),
+            }
+        }
+        pairs.push((name, value));
+    }
+}
+
+fn stream_event(labels: &[(String, String)],
+                time: u64,
+                line: String,
+                metadata: Vec<(String, String)>,
+                options: &LokiOptions)
+                -> Event {
+    let mut e = Event::new(time);
+    e.facility = options.tag.clone();
+    for &(ref name, ref value) in labels.iter() {
+        if name == "job" {
+            e.facility = value.clone();
+        } else if name == "host" || name == "hostname" {
+            e.hostname = value.clone();
+        }
+        e.facets.insert(name.clone(), value.clone());
+    }
+    e.facets.extend(metadata);
+    e.facets.insert(options.line_facet.clone(), line);
+    e
+}
+
+fn pb_pairs(data: &[u8]) -> Result<(String, String), String> {
+    let mut name = String::new();
+    let mut value = String::new();
+    for field in protobuf::Reader::new(data) {
+        match try!(field) {
+            (1, v) => name = try!(v.as_string()),
+            (2, v) => value = try!(v.as_string()),
+            _ => {}
+        }
+    }
+    Ok((name, value))
+}
+
+fn pb_entry(data: &[u8],
+            labels: &[(String, String)],
+            options: &LokiOptions)
+            -> Result<Event, String> {
+    let mut seconds = 0u64;
+    let mut nanos = 0u64;
+    let mut line = String::new();
+    let mut metadata = vec![];
+    for field in protobuf::Reader::new(data) {
+        match try!(field) {
+            (1, timestamp) => {
+                for field in protobuf::Reader::new(try!(timestamp.as_bytes())) {
+                    match try!(field) {
+                        (1, v) => seconds = try!(v.as_u64()),
+                        (2, v) => nanos = try!(v.as_u64()),
+                        _ => {}
+                    }
+                }
+            }
+            (2, v) => line = try!(v.as_string()),
+            (3, v) => metadata.push(try!(pb_pairs(try!(v.as_bytes())))),
+            _ => {}
+        }
+    }
+    let time = match seconds.checked_mul(1000000).and_then(|t| t.checked_add(nanos / 1000)) {
+        Some(time) => time,
+        None => return Err(format!("Invalid timestamp {}s {}ns", seconds, nanos)),
+    };
+    Ok(stream_event(labels, time, line, metadata, options))
+}
+
+pub fn decode_protobuf(body: &[u8], options: &LokiOptions) -> Result<Vec<Event>, String> {
+    let mut events = vec![];
+    for field in protobuf::Reader::new(body) {
+        if let (1, stream) = try!(field) {
+            let mut labels = vec![];
+            let mut entries = vec![];
+            for field in protobuf::Reader::new(try!(stream.as_bytes())) {
+                match try!(field) {
+                    (1, v) => labels = try!(parse_labels(&try!(v.as_string()))),
+                    (2, v) => entries.push(try!(v.as_bytes())),
+                    _ => {}
+                }
+            }
+            for entry in entries {
+                events.push(try!(pb_entry(entry, &labels, options)));
+            }
+        }
+    }
+    Ok(events)
+}
+
+fn json_pairs(object: Option<&Json>) -> Vec<(String, String)> {
+    object.and_then(|o| o.as_object())
+        .map(|o| {
+            o.iter()
+                .map(|(k, v)| {
+                    (k.clone(),
+                     match *v {
+                        Json::String(ref s) => s.clone(),
+                        ref other => other.to_string(),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or(vec![])
+}
+
+pub fn decode_json(body: &[u8], options: &LokiOptions) -> Result<Vec<Event>, String> {
+    let request = try!(Json::from_str(&String::from_utf8_lossy(body))
+        .map_err(|e| format!("Invalid JSON: {}", e)));
+    let streams = match request.find("streams").and_then(|s| s.as_array()) {
+        Some(streams) => streams,
+        None => return Err("Missing streams".to_owned()),
+    };
+
+    let mut events = vec![];
+    for stream in streams {
+        let labels = json_pairs(stream.find("stream"));
+        let values = match stream.find("values").and_then(|v| v.as_array()) {
+            Some(values) => values,
+            None => return Err("Stream without values".to_owned()),
+        };
+        for value in values {
+            let value = match value.as_array() {
+                Some(value) if value.len() >= 2 => value,
+                _ => return Err("Values must be [timestamp, line] arrays".to_owned()),
+            };
+            let time = match value[0].as_string().and_then(|t| t.parse::<u64>().ok()) {
+                Some(nanos) => nanos / 1000,
+                None => return Err(format!("Invalid timestamp {}", value[0])),
+            };
+            let line = match value[1].as_string() {
+                Some(line) => line.to_owned(),
+                None => return Err(format!("Invalid line {}", value[1])),
+            };
+            events.push(stream_event(&labels, time, line, json_pairs(value.get(2)), options));
+        }
+    }
+    Ok(events)
+}
+
+/// Handles push requests, independently from the HTTP server.
+pub struct LokiReceiver {
+    queue: BatchSender,
+    options: LokiOptions,
+}
+
+impl LokiReceiver {
+    pub fn new(tx: SyncSender<Event>, options: LokiOptions) -> LokiReceiver {
+        LokiReceiver {
+            queue: BatchSender::new(tx, options.queue_size),
+            options: options,
+        }
+    }
+
+    /// Returns the status and the (text) body of the reply.
+    pub fn push(&self,
+                content_type: Option<&str>,
+                content_encoding: Option<&str>,
+                body: &[u8])
+                -> (StatusCode, String) {
+        let media_type = content_type.and_then(|c| c.split(';').next())
+            .map(|c| c.trim().to_lowercase())
+            .unwrap_or("application/x-protobuf".to_owned());
+        let max_length = self.options.max_length;
+
+        let mut decompressed = vec![];
+        let body = match content_encoding.map(|e| e.trim().to_lowercase()) {
+            Some(ref encoding) if encoding == "gzip" => {
+                let result = GzDecoder::new(body).and_then(|decoder| {
+                    decoder.take(max_length as u64 + 1).read_to_end(&mut decompressed)
+                });
+                if let Err(e) = result {
+                    return (StatusCode::BadRequest, format!("Invalid gzip body: {}", e));
+                }
+                &decompressed[..]
+            }
+            Some(ref encoding) if encoding != "identity" => {
+                return (StatusCode::UnsupportedMediaType,
+                        format!("Unsupported content encoding {}", encoding))
+            }
+            _ => body,
+        };
+        if body.len() > max_length {
+            return (StatusCode::PayloadTooLarge, "Request body is too large".to_owned());
+        }
+
+        let decoded = match media_type.as_ref() {
+            "application/json" => decode_json(body, &self.options),
+            "application/x-protobuf" => {
+                snappy::decompress(body, max_length)
+                    .and_then(|request| decode_protobuf(&request, &self.options))
+            }
+            other => {
+                return (StatusCode::UnsupportedMediaType,
+                        format!("Unsupported content type {}", other))
+            }
+        };
+        let events = match decoded {
+            Ok(events) => events,
+            Err(e) => return (StatusCode::BadRequest, e),
+        };
+
+        match self.queue.send(events) {
+            Ok(()) => (StatusCode::NoContent, String::new()),
+            Err(QueueError::Full) => {
+                (StatusCode::ServiceUnavailable, "Pipeline queue is full".to_owned())
+            }
+            Err(QueueError::Disconnected) => {
+                (StatusCode::InternalServerError, "Pipeline is stopped".to_owned())
+            }
+        }
+    }
+}
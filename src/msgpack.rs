@@ -0,0 +1,258 @@
+//! Minimal MessagePack decoder and encoder, for the fluentd forward protocol.
+
+use std::io::{self, Read};
+use std::mem;
+use std::str;
+
+/// Deepest nesting of arrays and maps accepted
+const MAX_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    /// Raw bytes, which are not always UTF-8: some clients send the PackedForward
+    /// entries of the forward protocol as a string
+    Str(Vec<u8>),
+    Bin(Vec<u8>),
+    Array(Vec<Value>),
+    Map(Vec<(Value, Value)>),
+    Ext(i8, Vec<u8>),
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Value::Str(ref s) => str::from_utf8(s).ok(),
+            _ => None,
+        }
+    }
+
+    /// Returns the raw bytes of a string or binary value.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match *self {
+            Value::Str(ref s) => Some(s),
+            Value::Bin(ref b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            Value::UInt(u) => Some(u),
+            Value::Int(i) if i >= 0 => Some(i as u64),
+            _ => None,
+        }
+    }
+
+    /// Looks up a string key in a map.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match *self {
+            Value::Map(ref entries) => {
+                entries.iter()
+                    .find(|&&(ref k, _)| k.as_str() == Some(key))
+                    .map(|&(_, ref v)| v)
+            }
+            _ => None,
+        }
+    }
+
+    /// Renders scalars as text, and anything else as JSON-like text.
+    pub fn to_text(&self) -> String {
+        match *self {
+            Value::Nil => "null".to_owned(),
+            Value::Bool(b) => b.to_string(),
+            Value::Int(i) => i.to_string(),
+            Value::UInt(u) => u.to_string(),
+            Value::Float(f) => f.to_string(),
+            Value::Str(ref b) |
+            Value::Bin(ref b) => String::from_utf8_lossy(b).into_owned(),
+            Value::Array(ref values) => {
+                let values: Vec<String> = values.iter().map(|v| v.to_text()).collect();
+                format!("[{}]", values.join(","))
+            }
+            Value::Map(ref entries) => {
+                let entries: Vec<String> = entries.iter()
+                    .map(|&(ref k, ref v)| format!("{}:{}", k.to_text(), v.to_text()))
+                    .collect();
+                format!("{{{}}}", entries.join(","))
+            }
+            Value::Ext(kind, ref data) => format!("ext({},{} bytes)", kind, data.len()),
+        }
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_bytes<R: Read>(reader: &mut R, length: usize, max_length: usize) -> io::Result<Vec<u8>> {
+    if length > max_length {
+        return Err(invalid(format!("MessagePack value of {} bytes is too large", length)));
+    }
+    let mut buffer = vec![0; length];
+    try!(reader.read_exact(&mut buffer));
+    Ok(buffer)
+}
+
+fn read_uint<R: Read>(reader: &mut R, size: usize) -> io::Result<u64> {
+    let mut buffer = [0u8; 8];
+    try!(reader.read_exact(&mut buffer[..size]));
+    Ok(buffer[..size].iter().fold(0u64, |acc, b| (acc << 8) | *b as u64))
+}
+
+fn read_string<R: Read>(reader: &mut R, length: usize, max_length: usize) -> io::Result<Value> {
+    Ok(Value::Str(try!(read_bytes(reader, length, max_length))))
+}
+
+fn read_array<R: Read>(reader: &mut R,
+                       length: usize,
+                       max_length: usize,
+                       depth: usize)
+                       -> io::Result<Value> {
+    if length > max_length {
+        return Err(invalid(format!("MessagePack array of {} values is too large", length)));
+    }
+    let mut values = Vec::with_capacity(length.min(1024));
+    for _ in 0..length {
+        values.push(try!(read_nested(reader, max_length, depth + 1)));
+    }
+    Ok(Value::Array(values))
+}
+
+fn read_map<R: Read>(reader: &mut R,
+                     length: usize,
+                     max_length: usize,
+                     depth: usize)
+                     -> io::Result<Value> {
+    if length > max_length {
+        return Err(invalid(format!("MessagePack map of {} entries is too large", length)));
+    }
+    let mut entries = Vec::with_capacity(length.min(1024));
+    for _ in 0..length {
+        let key = try!(read_nested(reader, max_length, depth + 1));
+        let value = try!(read_nested(reader, max_length, depth + 1));
+        entries.push((key, value));
+    }
+    Ok(Value::Map(entries))
+}
+
+fn read_ext<R: Read>(reader: &mut R, length: usize, max_length: usize) -> io::Result<Value> {
+    let kind = try!(read_uint(reader, 1)) as u8 as i8;
+    Ok(Value::Ext(kind, try!(read_bytes(reader, length, max_length))))
+}
+
+/// Reads one value. Strings, binaries, arrays and maps longer than `max_length` are
+/// rejected. Returns an `UnexpectedEof` error when the reader is exhausted.
+pub fn read_value<R: Read>(reader: &mut R, max_length: usize) -> io::Result<Value> {
+    read_nested(reader, max_length, 0)
+}
+
+fn read_nested<R: Read>(reader: &mut R, max_length: usize, depth: usize) -> io::Result<Value> {
+    if depth > MAX_DEPTH {
+        return Err(invalid("MessagePack value nested too deeply".to_owned()));
+    }
+    let marker = try!(read_uint(reader, 1)) as u8;
+    match marker {
+        0x00...0x7f => Ok(Value::UInt(marker as u64)),
+        0x80...0x8f => read_map(reader, (marker & 0x0f) as usize, max_length, depth),
+        0x90...0x9f => read_array(reader, (marker & 0x0f) as usize, max_length, depth),
+        0xa0...0xbf => read_string(reader, (marker & 0x1f) as usize, max_length),
+        0xc0 => Ok(Value::Nil),
+        0xc2 => Ok(Value::Bool(false)),
+        0xc3 => Ok(Value::Bool(true)),
+        0xc4...0xc6 => {
+            let length = try!(read_uint(reader, 1 << (marker - 0xc4))) as usize;
+            Ok(Value::Bin(try!(read_bytes(reader, length, max_length))))
+        }
+        0xc7...0xc9 => {
+            let length = try!(read_uint(reader, 1 << (marker - 0xc7))) as usize;
+            read_ext(reader, length, max_length)
+        }
+        0xca => {
+            let bits = try!(read_uint(reader, 4)) as u32;
+            Ok(Value::Float(unsafe { mem::transmute::<u32, f32>(bits) } as f64))
+        }
+        0xcb => {
+            let bits = try!(read_uint(reader, 8));
+            Ok(Value::Float(unsafe { mem::transmute::<u64, f64>(bits) }))
+        }
+        0xcc...0xcf => Ok(Value::UInt(try!(read_uint(reader, 1 << (marker - 0xcc))))),
+        0xd0...0xd3 => {
+            let size = 1 << (marker - 0xd0);
+            let raw = try!(read_uint(reader, size));
+            // Sign extension from `size` bytes
+            let shift = 64 - size * 8;
+            Ok(Value::Int(((raw << shift) as i64) >> shift))
+        }
+        0xd4...0xd8 => read_ext(reader, 1 << (marker - 0xd4), max_length),
+        0xd9...0xdb => {
+            let length = try!(read_uint(reader, 1 << (marker - 0xd9))) as usize;
+            read_string(reader, length, max_length)
+        }
+        0xdc | 0xdd => {
+            let length = try!(read_uint(reader, 2 << (marker - 0xdc))) as usize;
+            read_array(reader, length, max_length, depth)
+        }
+        0xde | 0xdf => {
+            let length = try!(read_uint(reader, 2 << (marker - 0xde))) as usize;
+            read_map(reader, length, max_length, depth)
+        }
+        0xe0...0xff => Ok(Value::Int(marker as i8 as i64)),
+        _ => Err(invalid(format!("Invalid MessagePack marker 0x{:02x}", marker))),
+    }
+}
+
+fn write_length(out: &mut Vec<u8>, length: usize, fix: u8, fix_max: usize, markers: [u8; 3]) {
+    if length <= fix_max {
+        out.push(fix | length as u8);
+    } else if length <= 0xff && markers[0] != 0 {
+        out.push(markers[0]);
+        out.push(length as u8);
+    } else if length <= 0xffff {
+        out.push(markers[1]);
+        out.push((length >> 8) as u8);
+        out.push(length as u8);
+    } else {
+        out.push(markers[2]);
+        for shift in [24, 16, 8, 0].iter() {
+            out.push((length >> *shift) as u8);
+        }
+    }
+}
+
+/// Appends the encoding of `value` to `out`. Only what the forward protocol replies
+/// with is supported: nil, booleans, unsigned integers, strings, arrays and maps.
+pub fn write_value(value: &Value, out: &mut Vec<u8>) {
+    match *value {
+        Value::Nil => out.push(0xc0),
+        Value::Bool(b) => out.push(if b { 0xc3 } else { 0xc2 }),
+        Value::UInt(u) => {
+            out.push(0xcf);
+            for shift in (0..8).rev() {
+                out.push((u >> (shift * 8)) as u8);
+            }
+        }
+        Value::Str(ref s) => {
+            write_length(out, s.len(), 0xa0, 31, [0xd9, 0xda, 0xdb]);
+            out.extend_from_slice(s);
+        }
+        Value::Array(ref values) => {
+            write_length(out, values.len(), 0x90, 15, [0, 0xdc, 0xdd]);
+            for v in values.iter() {
+                write_value(v, out);
+            }
+        }
+        Value::Map(ref entries) => {
+            write_length(out, entries.len(), 0x80, 15, [0, 0xde, 0xdf]);
+            for &(ref k, ref v) in entries.iter() {
+                write_value(k, out);
+                write_value(v, out);
+            }
+        }
+        ref other => panic!("Encoding {:?} to MessagePack is not supported", other),
+    }
+}
@@ -0,0 +1,158 @@
+//! Merges the lines of multiline messages, such as stack traces, into one event.
+//!
+//! Events are grouped by source stream: their facility, hostname and key facets. A line
+//! starts a new event when it matches the start pattern; otherwise it is appended to
+//! the pending event of its stream if it matches the continuation pattern or, without a
+//! continuation pattern, unconditionally. Pending events are sent once the next one
+//! starts, once they hold `max_lines` lines or once their stream has been idle for
+//! `timeout`. At most `max_streams` events are pending: the one idle for the longest is
+//! sent to make room for a new stream.
+
+use std::collections::HashMap;
+use std::mem;
+use std::time::{Duration, Instant};
+
+use regex::Regex;
+
+use event::Event;
+
+pub struct MultilineOptions {
+    pub start: Option<Regex>,
+    pub continuation: Option<Regex>,
+    pub line_facet: String,
+    /// Facets identifying a source stream, besides the facility and hostname
+    pub key_facets: Vec<String>,
+    pub max_lines: usize,
+    pub max_streams: usize,
+    pub timeout: Duration,
+}
+
+struct Pending {
+    event: Event,
+    lines: usize,
+    last: Instant,
+}
+
+pub struct Multiline {
+    options: MultilineOptions,
+    pending: HashMap<Vec<String>, Pending>,
+}
+
+impl Multiline {
+    pub fn new(options: MultilineOptions) -> Multiline {
+        if options.start.is_none() && options.continuation.is_none() {
+            panic!("Multiline needs a start or a continuation pattern");
+        }
+        Multiline {
+            options: options,
+            pending: HashMap::new(),
+        }
+    }
+
+    fn key(&self, e: &Event) -> Vec<String> {
+        let mut key = vec![e.facility.clone(), e.hostname.clone()];
+        for facet in self.options.key_facets.iter() {
+            key.push(e.facets.get(facet).cloned().unwrap_or(String::new()));
+        }
+        key
+    }
+
+    fn is_continuation(&self, line: &str) -> bool {
+        if self.options.start.as_ref().map(|re| re.is_match(line)).unwrap_or(false) {
+            return false;
+        }
+        match self.options.continuation {
+            Some(ref re) => re.is_match(line),
+            None => true,
+        }
+    }
+
+    /// Takes the next event, returning the events that are complete.
+    pub fn push(&mut self, mut e: Event) -> Vec<Event> {
+        let continuation = match e.facets.get(&self.options.line_facet) {
+            Some(line) => self.is_continuation(line),
+            // Nothing to merge
+            None => return vec![e],
+        };
+
+        let key = self.key(&e);
+        if continuation {
+            let full = match self.pending.get_mut(&key) {
+                Some(pending) => {
+                    let line = e.facets.remove(&self.options.line_facet).unwrap();
+                    if let Some(merged) = pending.event.facets.get_mut(&self.options.line_facet) {
+                        merged.push('\n');
+                        merged.push_str(&line);
+                    }
+                    pending.event.acks.extend(mem::replace(&mut e.acks, vec![]));
+                    pending.lines += 1;
+                    pending.last = Instant::now();
+                    pending.lines >= self.options.max_lines
+                }
+                // A continuation without a pending event is kept as is
+                None => return vec![e],
+            };
+            if full {
+                if let Some(pending) = self.pending.remove(&key) {
+                    return vec![pending.event];
+                }
+            }
+            return vec![];
+        }
+
+        let mut complete = vec![];
+        if !self.pending.contains_key(&key) && self.pending.len() >= self.options.max_streams {
+            let oldest = self.pending
+                .iter()
+                .min_by_key(|&(_, pending)| pending.last)
+                .map(|(key, _)| key.clone());
+            if let Some(pending) = oldest.and_then(|oldest| self.pending.remove(&oldest)) {
+                complete.push(pending.event);
+            }
+        }
+        let pending = Pending {
+            event: e,
+            lines: 1,
+            last: Instant::now(),
+        };
+        if let Some(previous) = self.pending.insert(key.clone(), pending) {
+            complete.push(previous.event);
+        }
+        if self.options.max_lines <= 1 {
+            if let Some(full) = self.pending.remove(&key) {
+                complete.push(full.event);
+            }
+        }
+        complete
+    }
+
+    /// Returns the pending events of the streams idle since `timeout` at `now`.
+    pub fn expire(&mut self, now: Instant) -> Vec<Event> {
+        let timeout = self.options.timeout;
+        let expired: Vec<Vec<String>> = self.pending
+            .iter()
+            .filter(|&(_, pending)| now >= pending.last + timeout)
+            .map(|(key, _)| key.clone())
+            .collect();
+        let mut events: Vec<Event> = expired.iter()
+            .filter_map(|key| self.pending.remove(key))
+            .map(|pending| pending.event)
+            .collect();
+        events.sort_by(|a, b| a.time.cmp(&b.time));
+        events
+    }
+
+    /// Returns every pending event.
+    pub fn flush(&mut self) -> Vec<Event> {
+        let mut events: Vec<Event> = self.pending
+            .drain()
+            .map(|(_, pending)| pending.event)
+            .collect();
+        events.sort_by(|a, b| a.time.cmp(&b.time));
+        events
+    }
+
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+}
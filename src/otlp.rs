@@ -0,0 +1,582 @@
+//! OpenTelemetry logs over HTTP (OTLP/HTTP), in its protobuf and JSON encodings.
+//!
+//! Log records are mapped into `Logline`s: `service.name` is the facility and
+//! `host.name` the hostname. The body is stored under the line facet, next to
+//! `severity`, `severity_number`, `trace_id`, `span_id`, `scope.name`,
+//! `scope.version`, one `resource.NAME` facet per resource attribute and one
+//! `attributes.NAME` facet per log record attribute.
+
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::mem;
+use std::sync::mpsc::SyncSender;
+
+use flate2::read::GzDecoder;
+use hyper::status::StatusCode;
+use rustc_serialize::base64::{ToBase64, STANDARD};
+use rustc_serialize::json::Json;
+
+use event::{self, Event};
+use protobuf::{self, Field};
+use queue::{BatchSender, QueueError};
+
+pub static PROTOBUF_CONTENT_TYPE: &'static str = "application/x-protobuf";
+pub static JSON_CONTENT_TYPE: &'static str = "application/json";
+
+/// Log records of a request, with the ones that were rejected
+#[derive(Debug)]
+pub struct Export {
+    pub events: Vec<Event>,
+    pub rejected: u64,
+    /// Why the first rejected record was rejected
+    pub error_message: String,
+}
+
+impl Export {
+    fn reject(&mut self, error: String) {
+        if self.rejected == 0 {
+            self.error_message = error;
+        }
+        self.rejected += 1;
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OtlpOptions {
+    pub line_facet: String,
+    /// Facility of records without a `service.name` resource attribute
+    pub tag: String,
+    /// Records with a longer body are rejected
+    pub max_line: usize,
+    /// Largest accepted request body, once decompressed
+    pub max_length: usize,
+    /// Records accepted and not handed to the pipeline yet, beyond which requests are
+    /// refused as unavailable
+    pub queue_size: usize,
+}
+
+/// Renders an attribute value: strings as is, anything else as JSON.
+fn value_text(value: &Json) -> String {
+    match *value {
+        Json::String(ref s) => s.clone(),
+        ref other => other.to_string(),
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn severity_name(number: u64) -> Option<&'static str> {
+    match number {
+        1...4 => Some("TRACE"),
+        5...8 => Some("DEBUG"),
+        9...12 => Some("INFO"),
+        13...16 => Some("WARN"),
+        17...20 => Some("ERROR"),
+        21...24 => Some("FATAL"),
+        _ => None,
+    }
+}
+
+/// What the records of a `ResourceLogs` and `ScopeLogs` share
+#[derive(Clone, Default)]
+struct Context {
+    facility: Option<String>,
+    hostname: String,
+    facets: Vec<(String, String)>,
+}
+
+impl Context {
+    fn add_resource_attribute(&mut self, key: String, value: Json) {
+        let text = value_text(&value);
+        if key == "service.name" {
+            self.facility = Some(text.clone());
+        } else if key == "host.name" {
+            self.hostname = text.clone();
+        }
+        self.facets.push((format!("resource.{}", key), text));
+    }
+}
+
+/// A log record, decoded from either encoding
+#[derive(Default)]
+struct Record {
+    time_unix_nano: u64,
+    observed_time_unix_nano: u64,
+    severity_number: u64,
+    severity_text: String,
+    body: Option<Json>,
+    attributes: Vec<(String, Json)>,
+    trace_id: String,
+    span_id: String,
+}
+
+impl Record {
+    fn into_event(self,
+                  context: &Context,
+                  received: u64,
+                  options: &OtlpOptions)
+                  -> Result<Event, String> {
+        let body = self.body.as_ref().map(value_text).unwrap_or(String::new());
+        if body.len() > options.max_line {
+            return Err(format!("Log record body longer than {} bytes", options.max_line));
+        }
+
+        let time = if self.time_unix_nano > 0 {
+            self.time_unix_nano / 1000
+        } else if self.observed_time_unix_nano > 0 {
+            self.observed_time_unix_nano / 1000
+        } else {
+            received
+        };
+        let mut e = Event::new(time);
+        e.facility = context.facility.clone().unwrap_or(options.tag.clone());
+        e.hostname = context.hostname.clone();
+        e.facets.extend(context.facets.iter().cloned());
+        for (key, value) in self.attributes {
+            e.facets.insert(format!("attributes.{}", key), value_text(&value));
+        }
+        let severity = if self.severity_text.is_empty() {
+            severity_name(self.severity_number).map(|s| s.to_owned())
+        } else {
+            Some(self.severity_text)
+        };
+        if let Some(severity) = severity {
+            e.facets.insert("severity".to_owned(), severity);
+        }
+        if self.severity_number > 0 {
+            e.facets.insert("severity_number".to_owned(), self.severity_number.to_string());
+        }
+        if !self.trace_id.is_empty() {
+            e.facets.insert("trace_id".to_owned(), self.trace_id);
+        }
+        if !self.span_id.is_empty() {
+            e.facets.insert("span_id".to_owned(), self.span_id);
+        }
+        e.facets.insert(options.line_facet.clone(), body);
+        Ok(e)
+    }
+}
+
+// Protobuf encoding, see opentelemetry/proto/logs/v1/logs.proto
+
+fn pb_any_value(data: &[u8]) -> Result<Json, String> {
+    let mut value = Json::Null;
+    for field in protobuf::Reader::new(data) {
+        value = match try!(field) {
+            (1, v) => Json::String(try!(v.as_string())),
+            (2, v) => Json::Boolean(try!(v.as_u64()) != 0),
+            (3, v) => Json::I64(try!(v.as_u64()) as i64),
+            (4, Field::Fixed64(bits)) => Json::F64(unsafe { mem::transmute::<u64, f64>(bits) }),
+            (5, v) => {
+                let mut values = vec![];
+                for field in protobuf::Reader::new(try!(v.as_bytes())) {
+                    if let (1, v) = try!(field) {
+                        values.push(try!(pb_any_value(try!(v.as_bytes()))));
+                    }
+                }
+                Json::Array(values)
+            }
+            (6, v) => {
+                let mut values = BTreeMap::new();
+                for field in protobuf::Reader::new(try!(v.as_bytes())) {
+                    if let (1, v) = try!(field) {
+                        let (key, value) = try!(pb_key_value(try!(v.as_bytes())));
+                        values.insert(key, value);
+                    }
+                }
+                Json::Object(values)
+            }
+            (7, v) => Json::String(try!(v.as_bytes()).to_base64(STANDARD)),
+            _ => value,
+        };
+    }
+    Ok(value)
+}
+
+fn pb_key_value(data: &[u8]) -> Result<(String, Json), String> {
+    let mut key = String::new();
+    let mut value = Json::Null;
+    for field in protobuf::Reader::new(data) {
+        match try!(field) {
+            (1, v) => key = try!(v.as_string()),
+            (2, v) => value = try!(pb_any_value(try!(v.as_bytes()))),
+            _ => {}
+        }
+    }
+    Ok((key, value))
+}
+
+fn pb_log_record(data: &[u8]) -> Result<Record, String> {
+    let mut record = Record::default();
+    for field in protobuf::Reader::new(data) {
+        match try!(field) {
+            (1, v) => record.time_unix_nano = try!(v.as_u64()),
+            (11, v) => record.observed_time_unix_nano = try!(v.as_u64()),
+            (2, v) => record.severity_number = try!(v.as_u64()),
+            (3, v) => record.severity_text = try!(v.as_string()),
+            (5, v) => record.body = Some(try!(pb_any_value(try!(v.as_bytes())))),
+            (6, v) => record.attributes.push(try!(pb_key_value(try!(v.as_bytes())))),
+            (9, v) => record.trace_id = hex(try!(v.as_bytes())),
+            (10, v) => record.span_id = hex(try!(v.as_bytes())),
+            _ => {}
+        }
+    }
+    Ok(record)
+}
+
+fn pb_scope_logs(data: &[u8],
+                 mut context: Context,
+                 received: u64,
+                 options: &OtlpOptions,
+                 export: &mut Export)
+                 -> Result<(), String> {
+    let mut records = vec![];
+    for field in protobuf::Reader::new(data) {
+        match try!(field) {
+            (1, v) => {
+                for field in protobuf::Reader::new(try!(v.as_bytes())) {
+                    match try!(field) {
+                        (1, v) => {
+                            context.facets.push(("scope.name".to_owned(), try!(v.as_string())))
+                        }
+                        (2, v) => {
+                            context.facets.push(("scope.version".to_owned(), try!(v.as_string())))
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            (2, v) => records.push(try!(v.as_bytes())),
+            _ => {}
+        }
+    }
+    // Records come after the scope once all of it is known
+    for data in records {
+        match pb_log_record(data).and_then(|r| r.into_event(&context, received, options)) {
+            Ok(e) => export.events.push(e),
+            Err(e) => export.reject(e),
+        }
+    }
+    Ok(())
+}
+
+pub fn decode_protobuf(body: &[u8],
+                       received: u64,
+                       options: &OtlpOptions)
+                       -> Result<Export, String> {
+    let mut export = Export {
+        events: vec![],
+        rejected: 0,
+        error_message: String::new(),
+    };
+    for field in protobuf::Reader::new(body) {
+        if let (1, resource_logs) = try!(field) {
+            let mut context = Context::default();
+            let mut scopes = vec![];
+            for field in protobuf::Reader::new(try!(resource_logs.as_bytes())) {
+                match try!(field) {
+                    (1, resource) => {
+                        for field in protobuf::Reader::new(try!(resource.as_bytes())) {
+                            if let (1, v) = try!(field) {
+                                let (key, value) = try!(pb_key_value(try!(v.as_bytes())));
+                                context.add_resource_attribute(key, value);
+                            }
+                        }
+                    }
+                    (2, v) => scopes.push(try!(v.as_bytes())),
+                    _ => {}
+                }
+            }
+            for scope in scopes {
+                try!(pb_scope_logs(scope, context.clone(), received, options, &mut export));
+            }
+        }
+    }
+    Ok(export)
+}
+
+// JSON encoding, where bytes are hex (ids) or base64 and 64 bits integers strings
+
+fn json_u64(value: Option<&Json>) -> u64 {
+    match value {
+        Some(&Json::String(ref s)) => s.parse().unwrap_or(0),
+        Some(v) => v.as_u64().unwrap_or(0),
+        None => 0,
+    }
+}
+
+fn json_any_value(value: &Json) -> Json {
+    let object = match value.as_object() {
+        Some(object) => object,
+        None => return Json::Null,
+    };
+    if let Some(v) = object.get("stringValue") {
+        v.clone()
+    } else if let Some(v) = object.get("boolValue") {
+        v.clone()
+    } else if let Some(v) = object.get("intValue") {
+        match *v {
+            Json::String(ref s) => s.parse::<i64>().map(Json::I64).unwrap_or(v.clone()),
+            ref other => other.clone(),
+        }
+    } else if let Some(v) = object.get("doubleValue") {
+        v.clone()
+    } else if let Some(v) = object.get("bytesValue") {
+        v.clone()
+    } else if let Some(v) = object.get("arrayValue") {
+        Json::Array(v.find("values")
+            .and_then(|values| values.as_array())
+            .map(|values| values.iter().map(json_any_value).collect())
+            .unwrap_or(vec![]))
+    } else if let Some(v) = object.get("kvlistValue") {
+        Json::Object(json_attributes(v.find("values")).into_iter().collect())
+    } else {
+        Json::Null
+    }
+}
+
+fn json_attributes(attributes: Option<&Json>) -> Vec<(String, Json)> {
+    attributes.and_then(|a| a.as_array())
+        .map(|attributes| {
+            attributes.iter()
+                .filter_map(|kv| {
+                    kv.find("key").and_then(|k| k.as_string()).map(|key| {
+                        (key.to_owned(), kv.find("value").map(json_any_value).unwrap_or(Json::Null))
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or(vec![])
+}
+
+fn json_log_record(record: &Json) -> Result<Record, String> {
+    if !record.is_object() {
+        return Err("Log record is not an object".to_owned());
+    }
+    let text = |name: &str| {
+        record.find(name).and_then(|v| v.as_string()).unwrap_or("").to_owned()
+    };
+    Ok(Record {
+        time_unix_nano: json_u64(record.find("timeUnixNano")),
+        observed_time_unix_nano: json_u64(record.find("observedTimeUnixNano")),
+        severity_number: json_u64(record.find("severityNumber")),
+        severity_text: text("severityText"),
+        body: record.find("body").map(json_any_value),
+        attributes: json_attributes(record.find("attributes")),
+        trace_id: text("traceId").to_lowercase(),
+        span_id: text("spanId").to_lowercase(),
+    })
+}
+
+fn json_array<'a>(value: &'a Json, name: &str) -> &'a [Json] {
+    value.find(name).and_then(|v| v.as_array()).map(|v| &v[..]).unwrap_or(&[][..])
+}
+
+pub fn decode_json(body: &[u8], received: u64, options: &OtlpOptions) -> Result<Export, String> {
+    let request = try!(Json::from_str(&String::from_utf8_lossy(body))
+        .map_err(|e| format!("Invalid JSON: {}", e)));
+    if !request.is_object() {
+        return Err("Request is not an object".to_owned());
+    }
+
+    let mut export = Export {
+        events: vec![],
+        rejected: 0,
+        error_message: String::new(),
+    };
+    for resource_logs in json_array(&request, "resourceLogs") {
+        let mut context = Context::default();
+        let resource_attributes = json_attributes(resource_logs.find_path(&["resource",
+                                                                            "attributes"]));
+        for (key, value) in resource_attributes {
+            context.add_resource_attribute(key, value);
+        }
+        for scope_logs in json_array(resource_logs, "scopeLogs") {
+            let mut context = context.clone();
+            for name in ["name", "version"].iter() {
+                if let Some(value) = scope_logs.find_path(&["scope", *name])
+                    .and_then(|v| v.as_string()) {
+                    context.facets.push((format!("scope.{}", name), value.to_owned()));
+                }
+            }
+            for record in json_array(scope_logs, "logRecords") {
+                match json_log_record(record)
+                    .and_then(|r| r.into_event(&context, received, options)) {
+                    Ok(e) => export.events.push(e),
+                    Err(e) => export.reject(e),
+                }
+            }
+        }
+    }
+    Ok(export)
+}
+
+/// Reply to an export request, in the encoding of the request
+#[derive(Debug)]
+pub struct OtlpReply {
+    pub status: StatusCode,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl OtlpReply {
+    /// A `google.rpc.Status` error
+    fn error(status: StatusCode, code: u64, message: &str, protobuf: bool) -> OtlpReply {
+        let body = if protobuf {
+            let mut writer = protobuf::Writer::new();
+            writer.varint(1, code);
+            writer.bytes(2, message.as_bytes());
+            writer.buffer
+        } else {
+            format!("{{\"code\":{},\"message\":{}}}",
+                    code,
+                    Json::String(message.to_owned()))
+                .into_bytes()
+        };
+        OtlpReply {
+            status: status,
+            content_type: if protobuf {
+                PROTOBUF_CONTENT_TYPE
+            } else {
+                JSON_CONTENT_TYPE
+            },
+            body: body,
+        }
+    }
+
+    /// An `ExportLogsServiceResponse`, with a partial success when records were rejected
+    fn success(export: &Export, protobuf: bool) -> OtlpReply {
+        let body = if protobuf {
+            let mut writer = protobuf::Writer::new();
+            if export.rejected > 0 {
+                let mut partial = protobuf::Writer::new();
+                partial.varint(1, export.rejected);
+                partial.bytes(2, export.error_message.as_bytes());
+                writer.bytes(1, &partial.buffer);
+            }
+            writer.buffer
+        } else if export.rejected > 0 {
+            format!("{{\"partialSuccess\":{{\"rejectedLogRecords\":\"{}\",\"errorMessage\":{}}}}}",
+                    export.rejected,
+                    Json::String(export.error_message.clone()))
+                .into_bytes()
+        } else {
+            b"{}".to_vec()
+        };
+        OtlpReply {
+            status: StatusCode::Ok,
+            content_type: if protobuf {
+                PROTOBUF_CONTENT_TYPE
+            } else {
+                JSON_CONTENT_TYPE
+            },
+            body: body,
+        }
+    }
+}
+
+fn media_type(content_type: Option<&str>) -> String {
+    content_type.and_then(|c| c.split(';').next())
+        .map(|c| c.trim().to_lowercase())
+        .unwrap_or(String::new())
+}
+
+/// Handles `/v1/logs` requests, independently from the HTTP server.
+pub struct OtlpReceiver {
+    queue: BatchSender,
+    options: OtlpOptions,
+}
+
+impl OtlpReceiver {
+    pub fn new(tx: SyncSender<Event>, options: OtlpOptions) -> OtlpReceiver {
+        OtlpReceiver {
+            queue: BatchSender::new(tx, options.queue_size),
+            options: options,
+        }
+    }
+
+    /// Refuses a request whose body is longer than `max_length`, without reading it.
+    pub fn too_large(&self, content_type: Option<&str>) -> OtlpReply {
+        OtlpReply::error(StatusCode::PayloadTooLarge,
+                         3,
+                         "Request body is too large",
+                         media_type(content_type) != "application/json")
+    }
+
+    pub fn export(&self,
+                  content_type: Option<&str>,
+                  content_encoding: Option<&str>,
+                  body: &[u8])
+                  -> OtlpReply {
+        let media_type = media_type(content_type);
+        let protobuf = match media_type.as_ref() {
+            "application/x-protobuf" | "application/protobuf" => true,
+            "application/json" => false,
+            _ => {
+                return OtlpReply::error(StatusCode::UnsupportedMediaType,
+                                        3,
+                                        &format!("Unsupported content type {}", media_type),
+                                        false)
+            }
+        };
+
+        let mut decompressed = vec![];
+        let body = match content_encoding.map(|e| e.trim().to_lowercase()) {
+            Some(ref encoding) if encoding == "gzip" => {
+                let limit = self.options.max_length as u64 + 1;
+                let result = GzDecoder::new(body)
+                    .and_then(|decoder| decoder.take(limit).read_to_end(&mut decompressed));
+                if let Err(e) = result {
+                    return OtlpReply::error(StatusCode::BadRequest,
+                                            3,
+                                            &format!("Invalid gzip body: {}", e),
+                                            protobuf);
+                }
+                &decompressed[..]
+            }
+            Some(ref encoding) if encoding != "identity" => {
+                return OtlpReply::error(StatusCode::UnsupportedMediaType,
+                                        3,
+                                        &format!("Unsupported content encoding {}", encoding),
+                                        protobuf);
+            }
+            _ => body,
+        };
+        if body.len() > self.options.max_length {
+            return OtlpReply::error(StatusCode::PayloadTooLarge,
+                                    3,
+                                    "Request body is too large",
+                                    protobuf);
+        }
+
+        let received = event::now_micros();
+        let decoded = if protobuf {
+            decode_protobuf(body, received, &self.options)
+        } else {
+            decode_json(body, received, &self.options)
+        };
+        let export = match decoded {
+            Ok(export) => export,
+            Err(e) => return OtlpReply::error(StatusCode::BadRequest, 3, &e, protobuf),
+        };
+
+        let reply = OtlpReply::success(&export, protobuf);
+        match self.queue.send(export.events) {
+            Ok(()) => reply,
+            Err(QueueError::Full) => {
+                OtlpReply::error(StatusCode::ServiceUnavailable,
+                                 14,
+                                 "Pipeline queue is full",
+                                 protobuf)
+            }
+            Err(QueueError::Disconnected) => {
+                OtlpReply::error(StatusCode::InternalServerError,
+                                 13,
+                                 "Pipeline is stopped",
+                                 protobuf)
+            }
+        }
+    }
+}
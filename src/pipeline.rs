@@ -0,0 +1,93 @@
+//! Wires the configured inputs to the configured outputs.
+//!
+//! Every input runs in its own thread and sends its events to a shared channel. Events
+//! go through the filters, in order, and are then dispatched to every output. The
+//! pipeline stops once all inputs are done and all outputs have drained their channel.
+
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::thread;
+
+use config::Configuration;
+use configuration_items::{input_processor, filter_processor, output_processor};
+use event::Event;
+
+fn merge(receivers: Vec<Receiver<Event>>) -> Receiver<Event> {
+    let (tx, rx) = sync_channel(10000);
+    for (ix, receiver) in receivers.into_iter().enumerate() {
+        let tx = tx.clone();
+        let _ = thread::Builder::new().name(format!("input-{}", ix)).spawn(move || {
+            for event in receiver.iter() {
+                if tx.send(event).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    rx
+}
+
+/// Sends every event to all outputs. Only the first output confirming deliveries
+/// receives the event acknowledgements; without such an output, events are confirmed
+/// as soon as they are dispatched.
+fn dispatch(events: Receiver<Event>, outputs: Vec<(SyncSender<Event>, bool)>) {
+    let durable = outputs.iter().position(|&(_, confirms)| confirms);
+
+    for event in events.iter() {
+        for (ix, &(ref tx, _)) in outputs.iter().enumerate() {
+            let mut copy = event.clone();
+            if Some(ix) != durable {
+                copy.acks.clear();
+            }
+            let _ = tx.send(copy);
+        }
+        if durable.is_none() {
+            event.confirm();
+        }
+    }
+}
+
+pub fn run(configuration: Configuration) -> Result<(), String> {
+    let mut receivers = vec![];
+    for &(ref name, ref params) in configuration.inputs.iter() {
+        match input_processor(name) {
+            Some(input) => {
+                input.requires_fields(params, input.mandatory_fields());
+                receivers.push(input.start(params));
+            }
+            None => return Err(format!("Unknown input \"{}\"", name)),
+        }
+    }
+
+    let mut events = merge(receivers);
+    for &(ref name, ref params) in configuration.filters.iter() {
+        match filter_processor(name) {
+            Some(filter) => {
+                filter.requires_fields(params, filter.mandatory_fields());
+                events = filter.start(events, params);
+            }
+            None => return Err(format!("Unknown filter \"{}\"", name)),
+        }
+    }
+
+    let mut outputs = vec![];
+    let mut handles = vec![];
+    for &(ref name, ref params) in configuration.outputs.iter() {
+        match output_processor(name) {
+            Some(output) => {
+                output.requires_fields(params, output.mandatory_fields());
+                let (tx, rx) = sync_channel(10000);
+                handles.push(try!(output.start(rx, params)));
+                outputs.push((tx, output.confirms_delivery()));
+            }
+            None => return Err(format!("Unknown output \"{}\"", name)),
+        }
+    }
+
+    dispatch(events, outputs);
+
+    for handle in handles {
+        let _ = handle.join();
+    }
+    println!("Pipeline stopped.");
+    Ok(())
+}
@@ -0,0 +1,138 @@
+//! Minimal protocol buffers wire format reader and writer, enough for the handful of
+//! messages kiosque receives (docker log entries, OTLP logs, Loki pushes).
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Field<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    Bytes(&'a [u8]),
+    Fixed32(u32),
+}
+
+impl<'a> Field<'a> {
+    pub fn as_u64(&self) -> Result<u64, String> {
+        match *self {
+            Field::Varint(v) | Field::Fixed64(v) => Ok(v),
+            Field::Fixed32(v) => Ok(v as u64),
+            Field::Bytes(_) => Err("Expected a numeric field".to_owned()),
+        }
+    }
+
+    pub fn as_bytes(&self) -> Result<&'a [u8], String> {
+        match *self {
+            Field::Bytes(b) => Ok(b),
+            _ => Err("Expected a length-delimited field".to_owned()),
+        }
+    }
+
+    pub fn as_string(&self) -> Result<String, String> {
+        self.as_bytes().map(|b| String::from_utf8_lossy(b).into_owned())
+    }
+}
+
+/// Iterates over the `(field number, value)` pairs of an encoded message.
+pub struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Reader<'a> {
+        Reader {
+            data: data,
+            position: 0,
+        }
+    }
+
+    fn varint(&mut self) -> Result<u64, String> {
+        let mut value = 0u64;
+        for shift in 0..10 {
+            let byte = match self.data.get(self.position) {
+                Some(byte) => *byte,
+                None => return Err("Truncated varint".to_owned()),
+            };
+            self.position += 1;
+            value |= ((byte & 0x7f) as u64) << (shift * 7);
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("Varint longer than 10 bytes".to_owned())
+    }
+
+    fn take(&mut self, length: usize) -> Result<&'a [u8], String> {
+        if length > self.data.len() - self.position {
+            return Err("Truncated field".to_owned());
+        }
+        let bytes = &self.data[self.position..self.position + length];
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn fixed(&mut self, length: usize) -> Result<u64, String> {
+        let bytes = try!(self.take(length));
+        Ok(bytes.iter().rev().fold(0u64, |acc, b| (acc << 8) | *b as u64))
+    }
+
+    fn field(&mut self) -> Result<(u32, Field<'a>), String> {
+        let key = try!(self.varint());
+        let number = (key >> 3) as u32;
+        let field = match key & 7 {
+            0 => Field::Varint(try!(self.varint())),
+            1 => Field::Fixed64(try!(self.fixed(8))),
+            2 => {
+                let length = try!(self.varint()) as usize;
+                Field::Bytes(try!(self.take(length)))
+            }
+            5 => Field::Fixed32(try!(self.fixed(4)) as u32),
+            wire_type => return Err(format!("Unsupported wire type {}", wire_type)),
+        };
+        Ok((number, field))
+    }
+}
+
+impl<'a> Iterator for Reader<'a> {
+    type Item = Result<(u32, Field<'a>), String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.position >= self.data.len() {
+            return None;
+        }
+        let field = self.field();
+        if field.is_err() {
+            // Nothing can be read past a malformed field
+            self.position = self.data.len();
+        }
+        Some(field)
+    }
+}
+
+/// Encodes a message field by field.
+pub struct Writer {
+    pub buffer: Vec<u8>,
+}
+
+impl Writer {
+    pub fn new() -> Writer {
+        Writer { buffer: vec![] }
+    }
+
+    fn raw_varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.buffer.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        self.buffer.push(value as u8);
+    }
+
+    pub fn varint(&mut self, number: u32, value: u64) {
+        self.raw_varint((number as u64) << 3);
+        self.raw_varint(value);
+    }
+
+    pub fn bytes(&mut self, number: u32, value: &[u8]) {
+        self.raw_varint(((number as u64) << 3) | 2);
+        self.raw_varint(value.len() as u64);
+        self.buffer.extend_from_slice(value);
+    }
+}
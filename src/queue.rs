@@ -0,0 +1,75 @@
+//! Queues the batches of events received in one request all or nothing.
+//!
+//! Clients retry the whole request when told the server is busy, so queueing part of
+//! it would duplicate that part. Batches are reserved against a capacity, then handed
+//! to the pipeline by a forwarding thread, which blocks while the pipeline is full.
+
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Sender, SyncSender};
+use std::thread;
+
+use event::Event;
+
+/// Events accepted and not handed to the pipeline yet, by default.
+pub const DEFAULT_CAPACITY: usize = 10000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QueueError {
+    /// Nothing was queued, the request can be retried
+    Full,
+    /// The pipeline stopped
+    Disconnected,
+}
+
+pub struct BatchSender {
+    batches: Mutex<Sender<Vec<Event>>>,
+    pending: Arc<AtomicUsize>,
+    capacity: usize,
+}
+
+impl BatchSender {
+    pub fn new(tx: SyncSender<Event>, capacity: usize) -> BatchSender {
+        let (batches, rx) = channel::<Vec<Event>>();
+        let pending = Arc::new(AtomicUsize::new(0));
+        let forwarded = pending.clone();
+        thread::spawn(move || {
+            for batch in rx.iter() {
+                for e in batch {
+                    if tx.send(e).is_err() {
+                        return;
+                    }
+                    forwarded.fetch_sub(1, Ordering::SeqCst);
+                }
+            }
+        });
+        BatchSender {
+            batches: Mutex::new(batches),
+            pending: pending,
+            capacity: capacity,
+        }
+    }
+
+    /// Queues every event of `events`, or none. A batch larger than the capacity is
+    /// accepted when nothing else is pending, so that it is not refused forever.
+    pub fn send(&self, events: Vec<Event>) -> Result<(), QueueError> {
+        let count = events.len();
+        // Holding the lock makes checking and reserving one step
+        let batches = self.batches.lock().unwrap();
+        let pending = self.pending.load(Ordering::SeqCst);
+        if pending > 0 && pending + count > self.capacity {
+            return Err(QueueError::Full);
+        }
+        self.pending.fetch_add(count, Ordering::SeqCst);
+        if batches.send(events).is_err() {
+            self.pending.fetch_sub(count, Ordering::SeqCst);
+            return Err(QueueError::Disconnected);
+        }
+        Ok(())
+    }
+
+    /// Returns the number of events accepted and not handed to the pipeline yet.
+    pub fn pending(&self) -> usize {
+        self.pending.load(Ordering::SeqCst)
+    }
+}
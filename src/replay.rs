@@ -0,0 +1,131 @@
+//! Reads archived log blocks back as events, in time order.
+//!
+//! Blocks are loaded lazily: a block is only decoded once every event older than its
+//! oldest line has been returned, so that memory holds the overlapping blocks only.
+
+use regex::Regex;
+
+use event::Event;
+use logmanager;
+
+pub enum Needle {
+    Substring(String),
+    Regex(Regex),
+}
+
+/// Selects archived events. Every criterion is optional.
+pub struct ReplayQuery {
+    /// µs since EPOCH, inclusive
+    pub since: Option<u64>,
+    /// µs since EPOCH, inclusive
+    pub until: Option<u64>,
+    /// Facet searched for `needle`. Events without it do not match.
+    pub field: Option<String>,
+    pub needle: Option<Needle>,
+}
+
+impl ReplayQuery {
+    fn overlaps(&self, first: u64, last: u64) -> bool {
+        self.since.map(|since| last >= since).unwrap_or(true) &&
+        self.until.map(|until| first <= until).unwrap_or(true)
+    }
+
+    pub fn matches(&self, e: &Event) -> bool {
+        if !self.overlaps(e.time, e.time) {
+            return false;
+        }
+        let needle = match self.needle {
+            Some(ref needle) => needle,
+            None => return true,
+        };
+        let haystack = match self.field {
+            Some(ref field) => {
+                match e.facets.get(field) {
+                    Some(value) => vec![value],
+                    None => return false,
+                }
+            }
+            None => e.facets.values().collect(),
+        };
+        haystack.iter().any(|value| {
+            match *needle {
+                Needle::Substring(ref s) => value.contains(&s[..]),
+                Needle::Regex(ref re) => re.is_match(value),
+            }
+        })
+    }
+}
+
+struct Block {
+    file_name: String,
+    first: u64,
+}
+
+pub struct Replay {
+    query: ReplayQuery,
+    /// Blocks not loaded yet, newest first
+    blocks: Vec<Block>,
+    /// Events of the loaded blocks, newest first
+    events: Vec<Event>,
+}
+
+impl Replay {
+    /// Lists the blocks of `files` holding events in the time range of the query.
+    pub fn new(files: Vec<String>, query: ReplayQuery) -> Replay {
+        let mut blocks = vec![];
+        for file_name in files {
+            let range = logmanager::read_log_block(&file_name).and_then(|b| b.time_range());
+            match range {
+                Ok(Some((first, last))) if query.overlaps(first, last) => {
+                    blocks.push(Block {
+                        file_name: file_name,
+                        first: first,
+                    })
+                }
+                Ok(_) => {}
+                Err(e) => println!("Skipping {}: {:?}", file_name, e),
+            }
+        }
+        blocks.sort_by(|a, b| b.first.cmp(&a.first));
+        Replay {
+            query: query,
+            blocks: blocks,
+            events: vec![],
+        }
+    }
+
+    pub fn remaining_blocks(&self) -> usize {
+        self.blocks.len()
+    }
+
+    fn load(&mut self, block: Block) {
+        match logmanager::read_log_block(&block.file_name).and_then(|b| b.events()) {
+            Ok(events) => {
+                let query = &self.query;
+                self.events.extend(events.into_iter().filter(|e| query.matches(e)));
+                self.events.sort_by(|a, b| b.time.cmp(&a.time));
+            }
+            Err(e) => println!("Skipping {}: {:?}", block.file_name, e),
+        }
+    }
+}
+
+impl Iterator for Replay {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        loop {
+            let load = match (self.events.last(), self.blocks.last()) {
+                (_, None) => false,
+                (None, Some(_)) => true,
+                (Some(e), Some(block)) => block.first <= e.time,
+            };
+            if !load {
+                return self.events.pop();
+            }
+            if let Some(block) = self.blocks.pop() {
+                self.load(block);
+            }
+        }
+    }
+}
@@ -0,0 +1,109 @@
+//! Strips terminal escape sequences and control characters from log lines, such as
+//! `\u{1b}[1;33m[W] Custom config\u{1b}[0m\r`.
+
+use std::iter::Peekable;
+use std::str::Chars;
+
+use event::Event;
+
+const ESC: char = '\u{1b}';
+const BEL: char = '\u{7}';
+const REPLACEMENT_CHARACTER: char = '\u{fffd}';
+
+#[derive(Debug, Clone)]
+pub struct SanitizeOptions {
+    /// Facets to clean up, every facet when empty
+    pub facets: Vec<String>,
+    /// Keeps `\n` and `\t`, e.g. for events merged by the multiline filter. Otherwise
+    /// each run of them becomes a space.
+    pub keep_whitespace: bool,
+    /// Replaces each run of U+FFFD, which inputs decode invalid UTF-8 sequences to, with
+    /// `replacement`. Text is valid UTF-8 either way, this only makes the loss shorter.
+    pub collapse_replacements: bool,
+    pub replacement: String,
+}
+
+/// Skips the rest of an escape sequence, once its `ESC` has been read.
+fn skip_escape(chars: &mut Peekable<Chars>) {
+    match chars.next() {
+        // CSI: colors, cursor moves... parameters and intermediates, then a final byte
+        Some('[') => {
+            while let Some(c) = chars.next() {
+                if c >= '@' && c <= '~' {
+                    break;
+                }
+            }
+        }
+        // OSC, e.g. window titles, and other strings, up to BEL or ST (`ESC \`)
+        Some(']') | Some('P') | Some('X') | Some('^') | Some('_') => {
+            while let Some(c) = chars.next() {
+                if c == BEL {
+                    break;
+                }
+                if c == ESC {
+                    if chars.peek() == Some(&'\\') {
+                        chars.next();
+                    }
+                    break;
+                }
+            }
+        }
+        // Character set selection takes one more character
+        Some('(') | Some(')') | Some('*') | Some('+') => {
+            chars.next();
+        }
+        // Two characters sequences, e.g. `ESC 7` to save the cursor
+        _ => {}
+    }
+}
+
+/// Removes ANSI escape sequences and control characters, `\r` included. `\n` and `\t`
+/// are kept, or turned into spaces, depending on `keep_whitespace`.
+pub fn sanitize(text: &str, options: &SanitizeOptions) -> String {
+    let mut clean = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ESC => skip_escape(&mut chars),
+            // 8 bits CSI
+            '\u{9b}' => {
+                while let Some(c) = chars.next() {
+                    if c >= '@' && c <= '~' {
+                        break;
+                    }
+                }
+            }
+            '\n' | '\t' if options.keep_whitespace => clean.push(c),
+            '\n' | '\t' => {
+                while chars.peek() == Some(&'\n') || chars.peek() == Some(&'\t') {
+                    chars.next();
+                }
+                clean.push(' ');
+            }
+            REPLACEMENT_CHARACTER if options.collapse_replacements => {
+                while chars.peek() == Some(&REPLACEMENT_CHARACTER) {
+                    chars.next();
+                }
+                clean.push_str(&options.replacement);
+            }
+            c if c.is_control() => {}
+            c => clean.push(c),
+        }
+    }
+    clean
+}
+
+/// Sanitizes the configured facets of an event. Returns whether any changed.
+pub fn sanitize_event(e: &mut Event, options: &SanitizeOptions) -> bool {
+    let mut changed = false;
+    for (name, value) in e.facets.iter_mut() {
+        if options.facets.is_empty() || options.facets.contains(name) {
+            let clean = sanitize(value, options);
+            if clean != *value {
+                *value = clean;
+                changed = true;
+            }
+        }
+    }
+    changed
+}
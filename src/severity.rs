@@ -0,0 +1,203 @@
+//! Normalized severity levels, inferred from the markers applications put in their
+//! lines: `[W]`, `WARN`, `E0503 15:41:29.642521`, `<11>`, `{"level":"warning"}`...
+
+use regex::Regex;
+use rustc_serialize::json::Json;
+
+use event::Event;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl Level {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Level::Trace => "trace",
+            Level::Debug => "debug",
+            Level::Info => "info",
+            Level::Warn => "warn",
+            Level::Error => "error",
+            Level::Fatal => "fatal",
+        }
+    }
+
+    /// Maps level names, whatever their case, including syslog severities and the
+    /// single letters of gogs and glog, or numbers.
+    pub fn from_name(name: &str) -> Option<Level> {
+        let name = name.trim();
+        if let Ok(number) = name.parse::<u64>() {
+            return Level::from_number(number);
+        }
+        match name.to_lowercase().as_ref() {
+            "trace" | "t" | "verbose" | "finest" | "finer" => Some(Level::Trace),
+            "debug" | "d" | "fine" | "dbg" => Some(Level::Debug),
+            "info" | "i" | "information" | "informational" | "notice" | "config" => {
+                Some(Level::Info)
+            }
+            "warn" | "w" | "warning" => Some(Level::Warn),
+            "error" | "e" | "err" | "severe" => Some(Level::Error),
+            "fatal" | "f" | "c" | "crit" | "critical" | "alert" | "emerg" | "emergency" |
+            "panic" => Some(Level::Fatal),
+            _ => None,
+        }
+    }
+
+    /// Maps syslog severities, 0 to 7, and bunyan or pino levels: 10, 20... 60.
+    pub fn from_number(number: u64) -> Option<Level> {
+        match number {
+            0...2 => Some(Level::Fatal),
+            3 => Some(Level::Error),
+            4 => Some(Level::Warn),
+            5 | 6 => Some(Level::Info),
+            7 => Some(Level::Debug),
+            10 => Some(Level::Trace),
+            20 => Some(Level::Debug),
+            30 => Some(Level::Info),
+            40 => Some(Level::Warn),
+            50 => Some(Level::Error),
+            60 => Some(Level::Fatal),
+            _ => None,
+        }
+    }
+}
+
+/// Sets `level` on the lines matching `pattern`.
+pub struct Rule {
+    pub pattern: Regex,
+    pub level: Level,
+}
+
+impl Rule {
+    /// Parses `level:regex`, e.g. `error:^Traceback`.
+    pub fn parse(rule: &str) -> Result<Rule, String> {
+        let ix = match rule.find(':') {
+            Some(ix) => ix,
+            None => return Err(format!("Expected level:regex, found {}", rule)),
+        };
+        let level = match Level::from_name(&rule[..ix]) {
+            Some(level) => level,
+            None => return Err(format!("Unknown level {}", &rule[..ix])),
+        };
+        let pattern = try!(Regex::new(&rule[ix + 1..]).map_err(|e| format!("{}", e)));
+        Ok(Rule {
+            pattern: pattern,
+            level: level,
+        })
+    }
+}
+
+pub struct SeverityOptions {
+    /// Facets holding the text of the event
+    pub text_facets: Vec<String>,
+    /// Facets, or fields of JSON lines, holding a level name or number
+    pub level_fields: Vec<String>,
+    /// Tried before the built-in markers
+    pub rules: Vec<Rule>,
+    pub level_facet: String,
+    /// Level of the events without any marker, which get no level facet otherwise
+    pub default: Option<Level>,
+}
+
+pub struct SeverityDetector {
+    options: SeverityOptions,
+    syslog_pri: Regex,
+    /// Built-in markers, whose first capture is a level name
+    markers: Vec<Regex>,
+}
+
+impl SeverityDetector {
+    pub fn new(options: SeverityOptions) -> SeverityDetector {
+        let markers = [// glog: E0503 15:41:29.642521
+                       r"^([IWEF])\d{4} \d\d:\d\d:\d\d",
+                       // gogs: 2016/05/03 15:41:29 [W] Custom config
+                       r"(?:^|\s)\[([TDIWECF])\](?:\s|$)",
+                       // [warn], <error>, (info)
+                       concat!(r"(?i)[\[<(](trace|debug|info|notice|warn|warning|error|err|",
+                               r"crit|critical|fatal|panic)[\]>)]"),
+                       // logfmt
+                       r"(?i)\blevel=(\w+)",
+                       // Upper case words
+                       concat!(r"\b(TRACE|DEBUG|INFO|NOTICE|WARN|WARNING|ERROR|CRITICAL|CRIT|",
+                               r"FATAL|PANIC|SEVERE)\b")];
+        SeverityDetector {
+            options: options,
+            syslog_pri: Regex::new(r"^<(\d{1,3})>").unwrap(),
+            markers: markers.iter().map(|m| Regex::new(m).unwrap()).collect(),
+        }
+    }
+
+    fn json_level(&self, text: &str) -> Option<Level> {
+        if !text.trim_left().starts_with('{') {
+            return None;
+        }
+        let object = match Json::from_str(text) {
+            Ok(Json::Object(object)) => object,
+            _ => return None,
+        };
+        self.options
+            .level_fields
+            .iter()
+            .filter_map(|field| object.get(field))
+            .filter_map(|value| {
+                match *value {
+                    Json::String(ref name) => Level::from_name(name),
+                    Json::U64(number) => Level::from_number(number),
+                    Json::I64(number) if number >= 0 => Level::from_number(number as u64),
+                    _ => None,
+                }
+            })
+            .next()
+    }
+
+    /// Returns the level of a line, from the custom rules first, then the level field of
+    /// JSON objects, then the built-in markers.
+    pub fn detect(&self, text: &str) -> Option<Level> {
+        if let Some(rule) = self.options.rules.iter().find(|r| r.pattern.is_match(text)) {
+            return Some(rule.level);
+        }
+        if let Some(level) = self.json_level(text) {
+            return Some(level);
+        }
+        if let Some(pri) = self.syslog_pri.captures(text).and_then(|c| c.at(1)) {
+            return pri.parse::<u64>().ok().and_then(|pri| Level::from_number(pri % 8));
+        }
+        self.markers
+            .iter()
+            .filter_map(|marker| marker.captures(text).and_then(|c| c.at(1)))
+            .filter_map(Level::from_name)
+            .next()
+    }
+
+    /// Sets the level facet of an event, from its level facets first, then from its text.
+    /// Returns whether a level was found.
+    pub fn apply(&self, e: &mut Event) -> bool {
+        let level = {
+            let from_facets = self.options
+                .level_fields
+                .iter()
+                .filter_map(|field| e.facets.get(field))
+                .filter_map(|name| Level::from_name(name))
+                .next();
+            from_facets.or_else(|| {
+                self.options
+                    .text_facets
+                    .iter()
+                    .filter_map(|facet| e.facets.get(facet))
+                    .filter_map(|text| self.detect(text))
+                    .next()
+            })
+        };
+        let found = level.is_some();
+        if let Some(level) = level.or(self.options.default) {
+            e.facets.insert(self.options.level_facet.clone(), level.name().to_owned());
+        }
+        found
+    }
+}
@@ -0,0 +1,109 @@
+//! Snappy block format decompression, as used by Loki push requests.
+
+fn invalid(message: &str) -> String {
+    format!("Invalid snappy block: {}", message)
+}
+
+/// Decompresses a snappy block, refusing blocks larger than `max_length` once
+/// decompressed.
+pub fn decompress(input: &[u8], max_length: usize) -> Result<Vec<u8>, String> {
+    let mut position = 0;
+
+    // The block starts with its decompressed length, as a varint
+    let mut length = 0u64;
+    let mut shift = 0;
+    loop {
+        let byte = match input.get(position) {
+            Some(byte) => *byte,
+            None => return Err(invalid("truncated length")),
+        };
+        position += 1;
+        length |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+        if shift > 32 {
+            return Err(invalid("length is too large"));
+        }
+    }
+    if length > max_length as u64 {
+        return Err(format!("Snappy block of {} bytes is too large", length));
+    }
+    let length = length as usize;
+
+    let mut output = Vec::with_capacity(length);
+    while position < input.len() {
+        let tag = input[position];
+        position += 1;
+        let (literal_length, copy) = match tag & 3 {
+            0 => {
+                let short_length = (tag >> 2) as usize;
+                if short_length < 60 {
+                    (short_length + 1, None)
+                } else {
+                    let bytes = short_length - 59;
+                    if position + bytes > input.len() {
+                        return Err(invalid("truncated literal length"));
+                    }
+                    let long_length = input[position..position + bytes]
+                        .iter()
+                        .rev()
+                        .fold(0usize, |acc, b| (acc << 8) | *b as usize);
+                    position += bytes;
+                    (long_length + 1, None)
+                }
+            }
+            1 => {
+                if position >= input.len() {
+                    return Err(invalid("truncated copy"));
+                }
+                let offset = (((tag >> 5) as usize) << 8) | input[position] as usize;
+                position += 1;
+                (0, Some((((tag >> 2) & 7) as usize + 4, offset)))
+            }
+            kind => {
+                let bytes = if kind == 2 { 2 } else { 4 };
+                if position + bytes > input.len() {
+                    return Err(invalid("truncated copy"));
+                }
+                let offset = input[position..position + bytes]
+                    .iter()
+                    .rev()
+                    .fold(0usize, |acc, b| (acc << 8) | *b as usize);
+                position += bytes;
+                (0, Some(((tag >> 2) as usize + 1, offset)))
+            }
+        };
+
+        if literal_length > 0 {
+            if position + literal_length > input.len() {
+                return Err(invalid("truncated literal"));
+            }
+            if output.len() + literal_length > length {
+                return Err(invalid("longer than its length"));
+            }
+            output.extend_from_slice(&input[position..position + literal_length]);
+            position += literal_length;
+        }
+        if let Some((copy_length, offset)) = copy {
+            if offset == 0 || offset > output.len() {
+                return Err(invalid("copy offset out of range"));
+            }
+            if output.len() + copy_length > length {
+                return Err(invalid("longer than its length"));
+            }
+            // Copies may overlap what they produce, so go byte by byte
+            let start = output.len() - offset;
+            for ix in 0..copy_length {
+                let byte = output[start + ix];
+                output.push(byte);
+            }
+        }
+    }
+
+    if output.len() != length {
+        return Err(invalid("shorter than its length"));
+    }
+    Ok(output)
+}
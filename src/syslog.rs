@@ -0,0 +1,357 @@
+//! Syslog messages, in the BSD (RFC 3164) and RFC 5424 formats, with the TCP framings
+//! of RFC 6587.
+
+use std::io::{self, BufRead, Read};
+
+use chrono::{Datelike, DateTime, NaiveDateTime, UTC};
+
+use event::{self, Event};
+use lines;
+
+static FACILITIES: &'static [&'static str] = &["kern", "user", "mail", "daemon", "auth",
+                                               "syslog", "lpr", "news", "uucp", "cron",
+                                               "authpriv", "ftp", "ntp", "security",
+                                               "console", "solaris-cron", "local0", "local1",
+                                               "local2", "local3", "local4", "local5",
+                                               "local6", "local7"];
+
+static SEVERITIES: &'static [&'static str] = &["emerg", "alert", "crit", "err", "warning",
+                                               "notice", "info", "debug"];
+
+/// Priority of messages without a PRI part: user.notice
+const DEFAULT_PRI: u8 = 13;
+
+pub fn facility_name(facility: u8) -> &'static str {
+    FACILITIES.get(facility as usize).map(|f| *f).unwrap_or("unknown")
+}
+
+pub fn severity_name(severity: u8) -> &'static str {
+    SEVERITIES.get(severity as usize).map(|s| *s).unwrap_or("unknown")
+}
+
+#[derive(Debug, PartialEq)]
+pub struct StructuredElement {
+    pub id: String,
+    pub params: Vec<(String, String)>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SyslogMessage {
+    pub facility: u8,
+    pub severity: u8,
+    /// Time since EPOCH in µs
+    pub timestamp: Option<u64>,
+    pub hostname: Option<String>,
+    pub app_name: Option<String>,
+    pub procid: Option<String>,
+    pub msgid: Option<String>,
+    pub structured_data: Vec<StructuredElement>,
+    pub message: String,
+}
+
+impl SyslogMessage {
+    /// Maps the message into a `Logline`: the syslog facility is the facility and the
+    /// message is stored under `line_facet`, next to `severity`, `app_name`, `procid`,
+    /// `msgid` and one `sd.ID.PARAM` facet per structured data parameter.
+    pub fn into_event(self, received: u64, line_facet: &str) -> Event {
+        let mut e = Event::new(self.timestamp.unwrap_or(received));
+        e.facility = facility_name(self.facility).to_owned();
+        e.hostname = self.hostname.unwrap_or(String::new());
+        e.facets.insert("severity".to_owned(), severity_name(self.severity).to_owned());
+        if let Some(app_name) = self.app_name {
+            e.facets.insert("app_name".to_owned(), app_name);
+        }
+        if let Some(procid) = self.procid {
+            e.facets.insert("procid".to_owned(), procid);
+        }
+        if let Some(msgid) = self.msgid {
+            e.facets.insert("msgid".to_owned(), msgid);
+        }
+        for element in self.structured_data {
+            for (name, value) in element.params {
+                e.facets.insert(format!("sd.{}.{}", element.id, name), value);
+            }
+        }
+        e.facets.insert(line_facet.to_owned(), self.message);
+        e
+    }
+}
+
+/// Splits `input` at the first space.
+fn next_field(input: &str) -> (&str, &str) {
+    match input.find(' ') {
+        Some(ix) => (&input[..ix], &input[ix + 1..]),
+        None => (input, ""),
+    }
+}
+
+fn nil_or(field: &str) -> Option<String> {
+    if field == "-" || field.is_empty() {
+        None
+    } else {
+        Some(field.to_owned())
+    }
+}
+
+/// Parses `<PRI>`, returning the priority and the rest of the message.
+fn parse_pri(input: &str) -> Option<(u8, &str)> {
+    if !input.starts_with('<') {
+        return None;
+    }
+    let end = match input.find('>') {
+        Some(end) if end > 1 && end <= 4 => end,
+        _ => return None,
+    };
+    match input[1..end].parse::<u8>() {
+        Ok(pri) if pri < 192 => Some((pri, &input[end + 1..])),
+        _ => None,
+    }
+}
+
+/// Parses a BSD timestamp such as `Oct 11 22:14:15`. The year is not part of it: the
+/// current one is assumed, or the previous one for dates more than a day ahead.
+fn parse_bsd_timestamp(timestamp: &str, now: DateTime<UTC>) -> Option<u64> {
+    let normalized = timestamp.split_whitespace().collect::<Vec<&str>>().join(" ");
+    let parse = |year: i32| {
+        let dated = format!("{} {}", year, normalized);
+        NaiveDateTime::parse_from_str(&dated, "%Y %b %d %H:%M:%S")
+            .ok()
+            .map(|naive| DateTime::<UTC>::from_utc(naive, UTC))
+    };
+    match parse(now.year()) {
+        Some(dt) if dt.timestamp() > now.timestamp() + 86400 => {
+            parse(now.year() - 1).map(event::timestamp_micros)
+        }
+        Some(dt) => Some(event::timestamp_micros(dt)),
+        None => None,
+    }
+}
+
+/// Parses `[id name="value" ...][id2 ...]`, returning the elements and the rest of the
+/// message.
+fn parse_structured_data(input: &str) -> Option<(Vec<StructuredElement>, &str)> {
+    if input.starts_with("-") {
+        return Some((vec![], input[1..].trim_left_matches(' ')));
+    }
+
+    let mut elements = vec![];
+    let mut rest = input;
+    while rest.starts_with('[') {
+        let mut chars = rest.char_indices().skip(1).peekable();
+        let mut id = String::new();
+        while let Some(&(_, c)) = chars.peek() {
+            if c == ' ' || c == ']' {
+                break;
+            }
+            id.push(c);
+            chars.next();
+        }
+
+        let mut params = vec![];
+        let mut end = None;
+        loop {
+            match chars.next() {
+                Some((ix, ']')) => {
+                    end = Some(ix);
+                    break;
+                }
+                Some((_, ' ')) => {}
+                Some((_, c)) => {
+                    let mut name = String::new();
+                    name.push(c);
+                    while let Some((_, c)) = chars.next() {
+                        if c == '=' {
+                            break;
+                        }
+                        name.push(c);
+                    }
+                    if chars.next().map(|(_, c)| c) != Some('"') {
+                        return None;
+                    }
+                    let mut value = String::new();
+                    let mut closed = false;
+                    while let Some((_, c)) = chars.next() {
+                        match c {
+                            '\\' => {
+                                match chars.next() {
+                                    Some((_, c)) if c == '"' || c == '\\' || c == ']' => {
+                                        value.push(c)
+                                    }
+                                    Some((_, c)) => {
+                                        value.push('\\');
+                                        value.push(c);
+                                    }
+                                    None => return None,
+                                }
+                            }
+                            '"' => {
+                                closed = true;
+                                break;
+                            }
+                            c => value.push(c),
+                        }
+                    }
+                    if !closed {
+                        return None;
+                    }
+                    params.push((name, value));
+                }
+                None => return None,
+            }
+        }
+
+        elements.push(StructuredElement {
+            id: id,
+            params: params,
+        });
+        rest = match end {
+            Some(ix) => &rest[ix + 1..],
+            None => return None,
+        };
+    }
+    Some((elements, rest.trim_left_matches(' ')))
+}
+
+fn parse_rfc5424(pri: u8, input: &str) -> Option<SyslogMessage> {
+    let (timestamp, rest) = next_field(input);
+    let (hostname, rest) = next_field(rest);
+    let (app_name, rest) = next_field(rest);
+    let (procid, rest) = next_field(rest);
+    let (msgid, rest) = next_field(rest);
+    let (structured_data, message) = match parse_structured_data(rest) {
+        Some(parsed) => parsed,
+        None => return None,
+    };
+
+    Some(SyslogMessage {
+        facility: pri / 8,
+        severity: pri % 8,
+        timestamp: if timestamp == "-" {
+            None
+        } else {
+            event::parse_rfc3339_micros(timestamp)
+        },
+        hostname: nil_or(hostname),
+        app_name: nil_or(app_name),
+        procid: nil_or(procid),
+        msgid: nil_or(msgid),
+        structured_data: structured_data,
+        message: message.trim_left_matches('\u{feff}').to_owned(),
+    })
+}
+
+fn parse_rfc3164(pri: u8, input: &str, now: DateTime<UTC>) -> SyslogMessage {
+    let (timestamp, rest) = if input.len() >= 15 && input.is_char_boundary(15) {
+        match parse_bsd_timestamp(&input[..15], now) {
+            Some(ts) => (Some(ts), input[15..].trim_left_matches(' ')),
+            None => (None, input),
+        }
+    } else {
+        (None, input)
+    };
+
+    // The hostname is optional: a first word ending with ':' or holding a '[' is the tag
+    let (hostname, rest) = match timestamp {
+        Some(_) => {
+            let (first, after) = next_field(rest);
+            if first.ends_with(':') || first.contains('[') || after.is_empty() {
+                (None, rest)
+            } else {
+                (Some(first.to_owned()), after)
+            }
+        }
+        None => (None, rest),
+    };
+
+    let tag_end = rest.find(|c: char| c == '[' || c == ':' || c == ' ').unwrap_or(0);
+    let (app_name, procid, message) = if tag_end > 0 && tag_end <= 48 {
+        let tag = &rest[..tag_end];
+        let after_tag = &rest[tag_end..];
+        let (procid, after_pid) = if after_tag.starts_with('[') {
+            match after_tag.find(']') {
+                Some(end) => (Some(after_tag[1..end].to_owned()), &after_tag[end + 1..]),
+                None => (None, after_tag),
+            }
+        } else {
+            (None, after_tag)
+        };
+        if after_pid.starts_with(':') {
+            (Some(tag.to_owned()), procid, after_pid[1..].trim_left_matches(' '))
+        } else {
+            (None, None, rest)
+        }
+    } else {
+        (None, None, rest)
+    };
+
+    SyslogMessage {
+        facility: pri / 8,
+        severity: pri % 8,
+        timestamp: timestamp,
+        hostname: hostname,
+        app_name: app_name,
+        procid: procid,
+        msgid: None,
+        structured_data: vec![],
+        message: message.to_owned(),
+    }
+}
+
+/// Parses a syslog message. Anything that is not RFC 5424 is handled as BSD syslog,
+/// which accepts any content.
+pub fn parse_at(line: &str, now: DateTime<UTC>) -> SyslogMessage {
+    let line = line.trim_right_matches(|c| c == '\n' || c == '\r' || c == '\0');
+    let (pri, rest) = match parse_pri(line) {
+        Some(parsed) => parsed,
+        None => return parse_rfc3164(DEFAULT_PRI, line, now),
+    };
+    if rest.starts_with("1 ") {
+        if let Some(message) = parse_rfc5424(pri, &rest[2..]) {
+            return message;
+        }
+    }
+    parse_rfc3164(pri, rest, now)
+}
+
+pub fn parse(line: &str) -> SyslogMessage {
+    parse_at(line, UTC::now())
+}
+
+/// Digits of the octet count, and its space, read at most.
+const MAX_OCTET_COUNT_LENGTH: u64 = 20;
+
+/// Reads the next message of a TCP stream, framed either by octet counting
+/// (`LEN SP MSG`) or by a trailing newline, which is dropped. Only the first
+/// `max_length` bytes of a longer line are kept. Returns `None` at the end of the stream.
+pub fn read_frame<R: BufRead>(reader: &mut R, max_length: usize) -> io::Result<Option<String>> {
+    let octet_counted = {
+        let buffer = try!(reader.fill_buf());
+        if buffer.is_empty() {
+            return Ok(None);
+        }
+        (buffer[0] as char).is_digit(10)
+    };
+
+    let mut frame = vec![];
+    if octet_counted {
+        let mut length = vec![];
+        try!(reader.by_ref().take(MAX_OCTET_COUNT_LENGTH).read_until(b' ', &mut length));
+        let length = match length.pop() {
+            Some(b' ') => String::from_utf8_lossy(&length).parse::<usize>().ok(),
+            _ => None,
+        };
+        match length {
+            Some(length) if length <= max_length => {
+                frame.resize(length, 0);
+                try!(reader.read_exact(&mut frame));
+            }
+            _ => {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                          "Invalid octet count in syslog frame"))
+            }
+        }
+    } else {
+        frame = try!(lines::read_line(reader, max_length)).unwrap_or(frame);
+    }
+    Ok(Some(String::from_utf8_lossy(&frame).into_owned()))
+}
@@ -0,0 +1,275 @@
+//! Follows files matching glob patterns, line by line.
+//!
+//! Files are followed by inode rather than by path: a file renamed by a rotation keeps
+//! its offset, and a file no longer matching the patterns is read to its end before
+//! being dropped, so no line is lost or read twice. A file shrinking
+//! below the current offset was truncated in place (copytruncate) and is read again
+//! from its start. Offsets are checkpointed to a state file so that a restart resumes
+//! where the previous run stopped: each line comes with a `Delivery`, and checkpoints
+//! stop at the first line whose delivery was not confirmed.
+
+use std::cmp;
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::mem;
+use std::os::unix::fs::MetadataExt;
+use std::sync::{Arc, Mutex};
+
+use glob::glob;
+use rustc_serialize::json;
+
+use event::Delivery;
+
+/// Files are read this many bytes at a time.
+const CHUNK_SIZE: usize = 65536;
+
+#[derive(RustcEncodable, RustcDecodable, Debug, Clone, PartialEq)]
+pub struct FileState {
+    pub inode: u64,
+    pub offset: u64,
+}
+
+struct TailedFile {
+    path: String,
+    file: File,
+    /// Start of the line being read: every line before was handed over
+    offset: u64,
+    /// First `max_line` bytes of the line being read
+    partial: Vec<u8>,
+    /// Bytes of the line being read so far
+    partial_len: u64,
+    /// Starts of the lines handed over and not confirmed yet
+    unconfirmed: Arc<Mutex<BTreeSet<u64>>>,
+}
+
+impl TailedFile {
+    fn open(path: &str, offset: u64) -> io::Result<TailedFile> {
+        Ok(TailedFile {
+            path: path.to_owned(),
+            file: try!(File::open(path)),
+            offset: offset,
+            partial: vec![],
+            partial_len: 0,
+            unconfirmed: Arc::new(Mutex::new(BTreeSet::new())),
+        })
+    }
+
+    fn push_partial(&mut self, chunk: &[u8], max_line: usize) {
+        let room = max_line.saturating_sub(self.partial.len());
+        self.partial.extend_from_slice(&chunk[..cmp::min(room, chunk.len())]);
+        self.partial_len += chunk.len() as u64;
+    }
+
+    /// Offset up to which every line was confirmed.
+    fn checkpoint(&self) -> u64 {
+        self.unconfirmed.lock().unwrap().iter().next().cloned().unwrap_or(self.offset)
+    }
+
+    /// Forgets everything read, to read the file again from its start.
+    fn rewind(&mut self) {
+        self.offset = 0;
+        self.partial.clear();
+        self.partial_len = 0;
+        self.unconfirmed = Arc::new(Mutex::new(BTreeSet::new()));
+    }
+
+    /// Hands the complete lines appended since the last call to `f`, reading
+    /// `CHUNK_SIZE` bytes at a time. Returns false when `f` refused a line, which is
+    /// read again on the next call.
+    fn read_lines<F>(&mut self, max_line: usize, f: &mut F) -> io::Result<bool>
+        where F: FnMut(&str, Vec<u8>, Arc<Delivery>) -> bool
+    {
+        try!(self.file.seek(SeekFrom::Start(self.offset + self.partial_len)));
+        let mut buffer = vec![0; CHUNK_SIZE];
+        loop {
+            let read = try!(self.file.read(&mut buffer));
+            if read == 0 {
+                return Ok(true);
+            }
+            let mut start = 0;
+            while let Some(ix) = buffer[start..read].iter().position(|b| *b == b'\n') {
+                self.push_partial(&buffer[start..start + ix], max_line);
+                let line = mem::replace(&mut self.partial, vec![]);
+                let line_start = self.offset;
+                let unconfirmed = self.unconfirmed.clone();
+                unconfirmed.lock().unwrap().insert(line_start);
+                let delivery = Delivery::new(1,
+                                             Box::new(move || {
+                                                 unconfirmed.lock().unwrap().remove(&line_start);
+                                             }));
+                if !f(&self.path, line, delivery) {
+                    self.unconfirmed.lock().unwrap().remove(&line_start);
+                    self.partial_len = 0;
+                    return Ok(false);
+                }
+                self.offset += self.partial_len + 1;
+                self.partial_len = 0;
+                start += ix + 1;
+            }
+            self.push_partial(&buffer[start..read], max_line);
+        }
+    }
+}
+
+pub struct Tailer {
+    patterns: Vec<String>,
+    state_file: Option<String>,
+    from_beginning: bool,
+    max_line: usize,
+    checkpoints: HashMap<String, FileState>,
+    /// Followed files, by inode
+    files: HashMap<u64, TailedFile>,
+    first_scan: bool,
+}
+
+impl Tailer {
+    /// Follows the files matching `patterns`. Files found on the first scan without a
+    /// checkpoint are read from their end, unless `from_beginning` is set; files
+    /// appearing later are always read from their start.
+    pub fn new(patterns: Vec<String>,
+               state_file: Option<String>,
+               from_beginning: bool,
+               max_line: usize)
+               -> Tailer {
+        let checkpoints = state_file.as_ref()
+            .and_then(|f| load_checkpoints(f).ok())
+            .unwrap_or(HashMap::new());
+        Tailer {
+            patterns: patterns,
+            state_file: state_file,
+            from_beginning: from_beginning,
+            max_line: max_line,
+            checkpoints: checkpoints,
+            files: HashMap::new(),
+            first_scan: true,
+        }
+    }
+
+    /// Returns the inode and size of the regular files matching the patterns, by path.
+    fn matching_files(&self) -> Vec<(String, u64, u64)> {
+        let mut paths = vec![];
+        for pattern in self.patterns.iter() {
+            match glob(pattern) {
+                Ok(entries) => {
+                    for entry in entries {
+                        if let Ok(path) = entry {
+                            if path.is_file() {
+                                paths.push(path.to_string_lossy().into_owned());
+                            }
+                        }
+                    }
+                }
+                Err(e) => println!("Invalid pattern {}: {}", pattern, e),
+            }
+        }
+        paths.sort();
+        paths.dedup();
+        paths.into_iter()
+            .filter_map(|path| {
+                fs::metadata(&path).ok().map(|m| (path, m.ino(), m.len()))
+            })
+            .collect()
+    }
+
+    fn start_offset(&self, inode: u64, size: u64) -> u64 {
+        match self.checkpoints.values().find(|state| state.inode == inode) {
+            Some(state) if state.offset <= size => state.offset,
+            Some(_) => 0,
+            None if self.first_scan && !self.from_beginning => size,
+            None => 0,
+        }
+    }
+
+    /// Reads the new lines of every followed file, calling `f` with the path, the
+    /// content of each line and the delivery to confirm once it is handled, then
+    /// checkpoints the offsets. Reading stops when `f` returns false.
+    pub fn poll<F>(&mut self, mut f: F) -> io::Result<()>
+        where F: FnMut(&str, Vec<u8>, Arc<Delivery>) -> bool
+    {
+        let matching = self.matching_files();
+
+        // Files no longer matching were rotated away or deleted: read them to their end
+        let gone: Vec<u64> = self.files
+            .keys()
+            .filter(|inode| !matching.iter().any(|&(_, i, _)| i == **inode))
+            .cloned()
+            .collect();
+        let mut reading = true;
+        for inode in gone {
+            if let Some(mut tailed) = self.files.remove(&inode) {
+                if reading {
+                    reading = try!(tailed.read_lines(self.max_line, &mut f));
+                }
+            }
+        }
+
+        for &(ref path, inode, size) in matching.iter() {
+            if !self.files.contains_key(&inode) {
+                let offset = self.start_offset(inode, size);
+                match TailedFile::open(path, offset) {
+                    Ok(tailed) => {
+                        self.files.insert(inode, tailed);
+                    }
+                    Err(e) => {
+                        println!("Unable to open {}: {}", path, e);
+                        continue;
+                    }
+                }
+            }
+
+            if let Some(tailed) = self.files.get_mut(&inode) {
+                // A renamed file keeps its inode, and its offset
+                tailed.path = path.clone();
+                if size < tailed.offset + tailed.partial_len {
+                    println!("{} was truncated, reading it from its start.", path);
+                    tailed.rewind();
+                }
+                if reading {
+                    reading = try!(tailed.read_lines(self.max_line, &mut f));
+                }
+            }
+        }
+
+        self.first_scan = false;
+        self.checkpoints = self.files
+            .iter()
+            .map(|(inode, tailed)| {
+                (tailed.path.clone(),
+                 FileState {
+                    inode: *inode,
+                    offset: tailed.checkpoint(),
+                })
+            })
+            .collect();
+        self.save()
+    }
+
+    fn save(&self) -> io::Result<()> {
+        match self.state_file {
+            Some(ref state_file) => save_checkpoints(state_file, &self.checkpoints),
+            None => Ok(()),
+        }
+    }
+}
+
+pub fn load_checkpoints(state_file: &str) -> io::Result<HashMap<String, FileState>> {
+    let mut content = String::new();
+    try!(try!(File::open(state_file)).read_to_string(&mut content));
+    json::decode(&content)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}", e)))
+}
+
+pub fn save_checkpoints(state_file: &str,
+                        checkpoints: &HashMap<String, FileState>)
+                        -> io::Result<()> {
+    let content = try!(json::encode(checkpoints)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}", e))));
+    let tmp_name = format!("{}.tmp", state_file);
+    {
+        let mut f = try!(File::create(&tmp_name));
+        try!(f.write_all(content.as_bytes()));
+        try!(f.sync_all());
+    }
+    fs::rename(&tmp_name, state_file)
+}
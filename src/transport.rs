@@ -0,0 +1,237 @@
+//! Kiosque to kiosque transport: batches of events shipped as `Logblock` messages.
+//!
+//! The sender writes frames made of a batch id (u64), the length of the message (u32),
+//! both big endian, and the serialized `Logblock`. The receiver answers with the id of
+//! each batch, as a big endian u64, once all its events are durably stored. Batches
+//! not acknowledged when the connection drops are sent again on the next one, so a
+//! batch may be received twice.
+//!
+//! When the receiver requires a shared token, the sender starts each connection with
+//! it: its length (u32, big endian) followed by its bytes.
+
+use std::collections::VecDeque;
+use std::io;
+
+use capnp::message::ReaderOptions;
+use capnp::serialize;
+use logformat::schema_capnp::logblock;
+
+use event::{self, Event};
+
+const HEADER_LENGTH: usize = 12;
+/// Longest accepted token
+const MAX_TOKEN_LENGTH: usize = 1024;
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    bytes.iter().take(8).fold(0u64, |acc, b| (acc << 8) | *b as u64)
+}
+
+fn write_u64(value: u64, out: &mut Vec<u8>) {
+    for shift in (0..8).rev() {
+        out.push((value >> (shift * 8)) as u8);
+    }
+}
+
+/// Encodes a batch frame.
+pub fn encode_batch(id: u64, events: &[Event]) -> Vec<u8> {
+    let mut message = vec![];
+    serialize::write_message(&mut message, &event::log_block(events)).unwrap();
+
+    let mut frame = Vec::with_capacity(HEADER_LENGTH + message.len());
+    write_u64(id, &mut frame);
+    let length = message.len() as u32;
+    for shift in (0..4).rev() {
+        frame.push((length >> (shift * 8)) as u8);
+    }
+    frame.extend_from_slice(&message);
+    frame
+}
+
+pub fn encode_token(token: &str) -> Vec<u8> {
+    let length = token.len() as u32;
+    let mut frame: Vec<u8> = (0..4).rev().map(|shift| (length >> (shift * 8)) as u8).collect();
+    frame.extend_from_slice(token.as_bytes());
+    frame
+}
+
+/// Compares tokens in a time which does not depend on where they differ.
+pub fn same_token(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b.iter()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+pub fn encode_ack(id: u64) -> Vec<u8> {
+    let mut ack = Vec::with_capacity(8);
+    write_u64(id, &mut ack);
+    ack
+}
+
+fn decode_log_block(mut message: &[u8]) -> Result<Vec<Event>, String> {
+    let mut options = ReaderOptions::new();
+    options.traversal_limit_in_words(20000000);
+    let reader = try!(serialize::read_message(&mut message, options)
+        .map_err(|e| format!("Invalid log block: {:?}", e)));
+    let block = try!(reader.get_root::<logblock::Reader>()
+        .map_err(|e| format!("Invalid log block: {:?}", e)));
+    let lines = try!(block.get_entries().map_err(|e| format!("Invalid log block: {:?}", e)));
+    let mut events = vec![];
+    for line in lines.iter() {
+        events.push(try!(Event::from_reader(&line)
+            .map_err(|e| format!("Invalid log line: {:?}", e))));
+    }
+    Ok(events)
+}
+
+/// Accumulates the bytes received from a sender and splits them into batches.
+pub struct BatchReader {
+    buffer: Vec<u8>,
+    max_length: usize,
+}
+
+impl BatchReader {
+    pub fn new(max_length: usize) -> BatchReader {
+        BatchReader {
+            buffer: vec![],
+            max_length: max_length,
+        }
+    }
+
+    pub fn push(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+    }
+
+    /// Returns the token starting the connection, once complete.
+    pub fn next_token(&mut self) -> Result<Option<Vec<u8>>, String> {
+        if self.buffer.len() < 4 {
+            return Ok(None);
+        }
+        let length = self.buffer[..4].iter().fold(0usize, |acc, b| (acc << 8) | *b as usize);
+        if length > MAX_TOKEN_LENGTH {
+            return Err(format!("Token of {} bytes is too large", length));
+        }
+        if self.buffer.len() < 4 + length {
+            return Ok(None);
+        }
+        let token = self.buffer[4..4 + length].to_vec();
+        self.buffer.drain(..4 + length);
+        Ok(Some(token))
+    }
+
+    /// Returns the next complete batch, if any. Errors are not recoverable: the
+    /// connection should be closed.
+    pub fn next_batch(&mut self) -> Result<Option<(u64, Vec<Event>)>, String> {
+        if self.buffer.len() < HEADER_LENGTH {
+            return Ok(None);
+        }
+        let id = read_u64(&self.buffer);
+        let length = self.buffer[8..HEADER_LENGTH]
+            .iter()
+            .fold(0usize, |acc, b| (acc << 8) | *b as usize);
+        if length > self.max_length {
+            return Err(format!("Batch {} of {} bytes is too large", id, length));
+        }
+        if self.buffer.len() < HEADER_LENGTH + length {
+            return Ok(None);
+        }
+        let events = try!(decode_log_block(&self.buffer[HEADER_LENGTH..HEADER_LENGTH + length]));
+        self.buffer.drain(..HEADER_LENGTH + length);
+        Ok(Some((id, events)))
+    }
+}
+
+/// Accumulates the bytes received from a receiver and splits them into batch ids.
+pub struct AckReader {
+    buffer: Vec<u8>,
+}
+
+impl AckReader {
+    pub fn new() -> AckReader {
+        AckReader { buffer: vec![] }
+    }
+
+    pub fn push(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+    }
+
+    pub fn next_ack(&mut self) -> Option<u64> {
+        if self.buffer.len() < 8 {
+            return None;
+        }
+        let id = read_u64(&self.buffer);
+        self.buffer.drain(..8);
+        Some(id)
+    }
+}
+
+struct Batch {
+    id: u64,
+    frame: Vec<u8>,
+    events: Vec<Event>,
+}
+
+/// The batches sent but not acknowledged yet, oldest first.
+pub struct Outbox {
+    next_id: u64,
+    batches: VecDeque<Batch>,
+    max_batches: usize,
+}
+
+impl Outbox {
+    /// Batch ids start at `first_id`, which should differ between runs so that a
+    /// late acknowledgement from a previous connection is not mistaken for a new one.
+    pub fn new(first_id: u64, max_batches: usize) -> Outbox {
+        Outbox {
+            next_id: first_id,
+            batches: VecDeque::new(),
+            max_batches: max_batches,
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.batches.len() >= self.max_batches
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.batches.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.batches.len()
+    }
+
+    /// Queues a batch and returns its frame.
+    pub fn add(&mut self, events: Vec<Event>) -> &[u8] {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.batches.push_back(Batch {
+            id: id,
+            frame: encode_batch(id, &events),
+            events: events,
+        });
+        &self.batches.back().unwrap().frame
+    }
+
+    /// The frames to send again on a new connection.
+    pub fn frames(&self) -> Vec<&[u8]> {
+        self.batches.iter().map(|batch| &batch.frame[..]).collect()
+    }
+
+    /// Removes an acknowledged batch, confirming its events. Returns how many events
+    /// were confirmed; unknown ids are ignored.
+    pub fn acknowledge(&mut self, id: u64) -> usize {
+        match self.batches.iter().position(|batch| batch.id == id) {
+            Some(ix) => {
+                let batch = self.batches.remove(ix).unwrap();
+                for e in batch.events.iter() {
+                    e.confirm();
+                }
+                batch.events.len()
+            }
+            None => 0,
+        }
+    }
+}
+
+/// Reads on sockets with a read timeout fail with either kind, depending on the platform.
+pub fn is_timeout(error: &io::Error) -> bool {
+    error.kind() == io::ErrorKind::WouldBlock || error.kind() == io::ErrorKind::TimedOut
+}
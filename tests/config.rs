@@ -1,115 +1,118 @@
-extern crate nom;
 extern crate log_archive;
 
-macro_rules! test_gen { ($t:expr, $fun:expr, [ $( $it:expr ),* ])   => {
-  $(
-    {
-      let res = $fun($it);
-      if let Done(r,p) = res {
-        println!("
->>>>>>>>>>>>>>
-Parsing a {}:
-\"\"
-{}
-\"\"
-Found. {:?}
-Remaining: {:?}
-<<<<<<<<<<<<<<
-", $t, $it, p, r);
-      } else {
-        assert!(false, format!("{}: Failed to parse correctly {:?}: {:?}", $t, $it, res));
-      }
-    }
-   )*
-}
-}
-
-macro_rules! test_gen_complete { ($t:expr, $fun:expr, [ $( $it:expr ),* ])   => {
-  $(
-    {
-      let res = $fun($it);
-      if let Done(r,_) = res {
-        assert!(0 == r.len(), format!("{}: Rest is not empty: \"{}\" for {}", $t, r, $it));
-      } else {
-        assert!(false, format!("{}: Failed to parse correctly {:?}: {:?}", $t, $it, res));
-      }
-    }
-   )*
-}
-}
-
 #[cfg(test)]
 mod config_test {
-  use nom::IResult::*;
-  use log_archive::config::{
-    multispace_and_comment,
-    declaration,
-    key_value,
-    keys_and_values_aggregator};
+  use log_archive::config::parse_configuration;
 
   #[test]
   fn test_multispace_content() {
-      test_gen!(
-      "multispace_and_comment",
-      multispace_and_comment,
-      [
-      "\n",
-      "\n\n",
-      "   ",
-      "  \n  ",
-      "\t \n ",
-      "#",
-      "   # this is a sample comment",
-      "   # this is a sample comment\n# with multiline things\n  \t",
-      "\n#\n# \n\t#\n"]);
+    for blank in &["\n",
+                   "\n\n",
+                   "   ",
+                   "  \n  ",
+                   "\t \n ",
+                   "#",
+                   "   # this is a sample comment",
+                   "   # this is a sample comment\n# with multiline things\n  \t",
+                   "\n#\n# \n\t#\n"] {
+      let conf = parse_configuration(blank).unwrap();
+      assert!(conf.inputs.is_empty() && conf.outputs.is_empty(), "{:?}", blank);
+      let conf = parse_configuration(&format!("input {{{}}}{}output {{}}", blank, blank))
+        .unwrap();
+      assert!(conf.inputs.is_empty() && conf.outputs.is_empty(), "{:?}", blank);
+    }
   }
 
   #[test]
   fn test_declarations() {
-      test_gen!(
-      "declaration",
-      declaration,
-      [
-      "splunk { }",
-      "empty_declaration   \n",
-      "empty_declaration # with a comment\n",
-      "💩 {}",
-      "         💩 {}",
-      " 💩 { \n }",
-      " 💩  # coucou\n{ \n }",
-      " 💩 { # 🍓 \n }",
-      " splunk { \n foo=bar\n # 🍓 \n }",
-      " 💩 { \n # coucou \n  }"]);
+    for &(declaration, name) in &[("splunk { }", "splunk"),
+                                  ("empty_declaration   \n", "empty_declaration"),
+                                  ("empty_declaration # with a comment\n", "empty_declaration"),
+                                  ("💩 {}", "💩"),
+                                  ("         💩 {}", "💩"),
+                                  (" 💩 { \n }", "💩"),
+                                  (" 💩  # coucou\n{ \n }", "💩"),
+                                  (" 💩 { # 🍓 \n }", "💩"),
+                                  (" splunk { \n foo=bar\n # 🍓 \n }", "splunk"),
+                                  (" 💩 { \n # coucou \n  }", "💩")] {
+      let conf = parse_configuration(&format!("input {{{}}}", declaration)).unwrap();
+      assert_eq!(conf.inputs.len(), 1, "{:?}", declaration);
+      assert_eq!(conf.inputs[0].0, name);
+    }
   }
 
-
   #[test]
   fn test_key_value() {
-    test_gen!(
-      "key_value",
-      key_value,
-      [
-        "foo = \"bar\"  \n",
-        "f_a = 12\n",
-        "length=12\n",
-        "length = 12\n",
-        "length = 12\n",
-        "length = douze\n",
-        "length = 🍓\n",
-        "length = \"🍓\"\n",
-        "length = \"🍓\" # and now with some comment\n"
-      ]);
+    for &(line, key, value) in &[("foo = \"bar\"  \n", "foo", "bar"),
+                                 ("f_a = 12\n", "f_a", "12"),
+                                 ("length=12\n", "length", "12"),
+                                 ("length = 12\n", "length", "12"),
+                                 ("length = douze\n", "length", "douze"),
+                                 ("length = 🍓\n", "length", "🍓"),
+                                 ("length = \"🍓\"\n", "length", "🍓"),
+                                 ("length = \"🍓\" # and now with some comment\n",
+                                  "length",
+                                  "🍓")] {
+      let conf = parse_configuration(&format!("input {{ file {{ {} }} }}", line)).unwrap();
+      let params = conf.inputs[0].1.as_ref().unwrap();
+      assert_eq!(params.len(), 1, "{:?}", line);
+      assert_eq!(params.get(key).unwrap(), value);
+    }
   }
 
   #[test]
   fn test_keys_and_values_aggregator() {
-    test_gen!(
-      "keys_and_values_aggregator",
-      keys_and_values_aggregator,
-      [
-        "foo = bar\n\tlength=12\noutput_folder = \"./logs/$APP/\"   \n👔 = OFF #Who cares ?\n}"
-      ]
-    );
+    let params = "foo = bar\n\tlength=12\noutput_folder = \"./logs/$APP/\"   \n👔 = OFF #Who \
+                  cares ?\n}";
+    let conf = parse_configuration(&format!("output {{ file {{ {} }}", params)).unwrap();
+    let params = conf.outputs[0].1.as_ref().unwrap();
+    assert_eq!(params.len(), 4);
+    assert_eq!(params.get("foo").unwrap(), "bar");
+    assert_eq!(params.get("length").unwrap(), "12");
+    assert_eq!(params.get("output_folder").unwrap(), "./logs/$APP/");
+    assert_eq!(params.get("👔").unwrap(), "OFF");
   }
 }
 
+#[cfg(test)]
+mod config_file_test {
+  use log_archive::config::{read_config_file, parse_configuration};
+
+  #[test]
+  fn test_read_config_file() {
+    let conf = read_config_file("tests/test_config.conf").unwrap();
+    assert_eq!(conf.inputs.len(), 2);
+    assert_eq!(conf.outputs.len(), 0);
+
+    let (ref name, ref params) = conf.inputs[0];
+    assert_eq!(name, "file");
+    let params = params.as_ref().unwrap();
+    assert_eq!(params.get("pipo").unwrap(), "12");
+    assert_eq!(params.get("path").unwrap(), "some literal string");
+
+    let (ref name, ref params) = conf.inputs[1];
+    assert_eq!(name, "stdin");
+    assert_eq!(params.as_ref().unwrap().get("tag").unwrap(), "stdin");
+  }
+
+  #[test]
+  fn test_parse_configuration() {
+    let conf = parse_configuration("input { fake-apache { rate 10 } stdin }\n\
+                                    output { file { directory = \"./logs/#{host}\" } }")
+      .unwrap();
+    assert_eq!(conf.inputs[0].0, "fake-apache");
+    assert_eq!(conf.inputs[0].1.as_ref().unwrap().get("rate").unwrap(), "10");
+    assert_eq!(conf.inputs[1], ("stdin".to_owned(), None));
+    assert_eq!(conf.outputs[0].1.as_ref().unwrap().get("directory").unwrap(),
+               "./logs/#{host}");
+    assert!(conf.filters.is_empty());
+
+    let conf = parse_configuration("filter { multiline { start_pattern \"^\\d+\" } }").unwrap();
+    assert_eq!(conf.filters[0].0, "multiline");
+    assert_eq!(conf.filters[0].1.as_ref().unwrap().get("start_pattern").unwrap(), "^\\d+");
+
+    assert!(parse_configuration("input { file { path } }").is_err());
+    assert!(parse_configuration("input { file {").is_err());
+    assert!(parse_configuration("unknown { }").is_err());
+  }
+}
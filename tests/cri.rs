@@ -0,0 +1,94 @@
+#[cfg(test)]
+mod cri_test {
+  use std::fs::File;
+  use std::io::{BufRead, BufReader};
+  use std::sync::{Arc, Mutex};
+  use log_archive::event::Delivery;
+  use log_archive::cri::{parse_line, parse_path, ContainerMeta, CriEntry, CriReader};
+
+  static FIXTURE: &'static str = "tests/fixtures/containers/web-7d4b9c6f5-x2v8k_shop_nginx-3f2b1c0d9e8a7f6b5c4d3e2f1a0b9c8d7e6f5a4b3c2d1e0f9a8b7c6d5e4f3a2b.log";
+
+  #[test]
+  fn test_parse_line() {
+    assert_eq!(parse_line("2016-10-06T00:17:09.669794202Z stdout F hello world").unwrap(),
+               CriEntry {
+                 time: Some(1475713029669794),
+                 stream: "stdout".to_owned(),
+                 partial: false,
+                 message: "hello world".to_owned(),
+               });
+    assert!(parse_line("2016-10-06T00:17:09.669794202+02:00 stderr P part ").unwrap().partial);
+    assert_eq!(parse_line("2016-10-06T00:17:09Z stdout F").unwrap().message, "");
+    assert!(parse_line("2016-10-06T00:17:09Z stdout X message").is_err());
+    assert!(parse_line("garbage").is_err());
+  }
+
+  #[test]
+  fn test_parse_path() {
+    assert_eq!(parse_path("/var/log/containers/coredns-5c98db65d4-6qr2v_kube-system_coredns-0e1f.log"),
+               Some(ContainerMeta {
+                 pod: "coredns-5c98db65d4-6qr2v".to_owned(),
+                 namespace: "kube-system".to_owned(),
+                 container: "coredns".to_owned(),
+                 container_id: "0e1f".to_owned(),
+               }));
+    assert_eq!(parse_path("/var/log/containers/no-metadata.log"), None);
+    assert_eq!(parse_path("/var/log/containers/pod_ns_container.log"), None);
+  }
+
+  #[test]
+  fn test_fixture() {
+    let mut reader = CriReader::new("kubernetes".to_owned(), "stdout".to_owned(), 1024);
+    let mut events = vec![];
+    let mut errors = 0;
+    for line in BufReader::new(File::open(FIXTURE).unwrap()).lines() {
+      match reader.read_line(FIXTURE, &line.unwrap()) {
+        Ok(Some(e)) => events.push(e),
+        Ok(None) => {}
+        Err(_) => errors += 1,
+      }
+    }
+    assert_eq!(errors, 1);
+    assert_eq!(events.len(), 4);
+
+    assert_eq!(events[0].facility, "kubernetes");
+    assert_eq!(events[0].facets["pod"], "web-7d4b9c6f5-x2v8k");
+    assert_eq!(events[0].facets["namespace"], "shop");
+    assert_eq!(events[0].facets["container"], "nginx");
+    assert_eq!(events[0].facets["container_id"].len(), 64);
+    assert_eq!(events[0].facets["source"], "stdout");
+
+    assert_eq!(events[1].facets["stdout"], "10.0.0.2 - - \"GET /favicon.ico HTTP/1.1\" 404 153");
+    // The partial line gets the time of its first part
+    assert_eq!(events[2].time, 1475713030113242);
+    assert_eq!(events[2].facets["source"], "stderr");
+    assert_eq!(events[2].facets["stdout"], "a long line split in three parts");
+    assert_eq!(events[3].facets["stdout"], "");
+  }
+
+  #[test]
+  fn test_deliveries() {
+    let confirmed = Arc::new(Mutex::new(vec![]));
+    let delivery = |id: u32| {
+      let confirmed = confirmed.clone();
+      Delivery::new(1, Box::new(move || confirmed.lock().unwrap().push(id)))
+    };
+    let mut reader = CriReader::new("kubernetes".to_owned(), "stdout".to_owned(), 1024);
+
+    let part = parse_line("2016-10-06T00:17:10.113242941Z stderr P a long ").unwrap();
+    assert!(reader.read_entry("a.log", part, Some(delivery(1))).is_none());
+    // A line of another file or stream does not carry the held part
+    let other = parse_line("2016-10-06T00:17:10.113242941Z stdout F other").unwrap();
+    let e = reader.read_entry("b.log", other, Some(delivery(2))).unwrap();
+    assert_eq!(e.acks.len(), 1);
+    e.confirm();
+    assert_eq!(*confirmed.lock().unwrap(), vec![2]);
+
+    let last = parse_line("2016-10-06T00:17:10.113242941Z stderr F line").unwrap();
+    let e = reader.read_entry("a.log", last, Some(delivery(3))).unwrap();
+    assert_eq!(e.facets["stdout"], "a long line");
+    assert_eq!(e.acks.len(), 2);
+    e.confirm();
+    assert_eq!(*confirmed.lock().unwrap(), vec![2, 1, 3]);
+  }
+}
@@ -0,0 +1,69 @@
+#[cfg(test)]
+mod date_test {
+  use chrono::FixedOffset;
+  use log_archive::date::{parse, DateFilter, DateFormat, Timezone};
+  use log_archive::event::Event;
+
+  fn utc() -> Timezone {
+    Timezone::Fixed(FixedOffset::east(0))
+  }
+
+  #[test]
+  fn test_formats() {
+    let apache = vec![DateFormat::from_name("%d/%b/%Y:%H:%M:%S %z")];
+    assert_eq!(parse("03/May/2016:15:41:29 +0000", &apache, &utc()),
+               Some(1462290089000000));
+    assert_eq!(parse("03/May/2016:17:41:29 +0200", &apache, &utc()),
+               Some(1462290089000000));
+    assert_eq!(parse("2016-05-03T15:41:29Z", &apache, &utc()), None);
+
+    let iso = vec![DateFormat::from_name("ISO8601")];
+    assert_eq!(parse("2016-05-03T15:41:29.642521Z", &iso, &utc()),
+               Some(1462290089642521));
+    assert_eq!(parse("2016-05-03 17:41:29+0200", &iso, &utc()), Some(1462290089000000));
+    let paris = Timezone::from_name("+02:00").unwrap();
+    assert_eq!(parse("2016-05-03 17:41:29", &iso, &paris), Some(1462290089000000));
+    assert_eq!(parse("2016-05-03 15:41:29", &iso, &utc()), Some(1462290089000000));
+
+    let epochs = vec![DateFormat::from_name("UNIX_MS"), DateFormat::from_name("UNIX")];
+    assert_eq!(parse("1462290089642", &epochs, &utc()), Some(1462290089642000));
+    assert_eq!(parse(" 1462290089.5", &vec![DateFormat::Unix], &utc()),
+               Some(1462290089500000));
+    assert_eq!(parse("yesterday", &epochs, &utc()), None);
+  }
+
+  #[test]
+  fn test_timezones() {
+    assert_eq!(Timezone::from_name("UTC"), Some(utc()));
+    assert_eq!(Timezone::from_name("-0530"),
+               Some(Timezone::Fixed(FixedOffset::west(5 * 3600 + 30 * 60))));
+    assert_eq!(Timezone::from_name("local"), Some(Timezone::Local));
+    assert_eq!(Timezone::from_name("Europe/Paris"), None);
+    assert_eq!(Timezone::from_name("+25:00"), None);
+  }
+
+  #[test]
+  fn test_filter() {
+    let filter = DateFilter {
+      source: "timestamp".to_owned(),
+      formats: vec![DateFormat::from_name("%d/%b/%Y:%H:%M:%S %z")],
+      timezone: utc(),
+      received_facet: "received_at".to_owned(),
+      failure_facet: "tags".to_owned(),
+      failure_tag: "_dateparsefailure".to_owned(),
+    };
+
+    let mut e = Event::new(1462290389642521);
+    e.facets.insert("timestamp".to_owned(), "03/May/2016:15:41:29 +0000".to_owned());
+    assert!(filter.apply(&mut e));
+    assert_eq!(e.time, 1462290089000000);
+    assert!(e.facets["received_at"].starts_with("2016-05-03T15:46:29."));
+
+    let mut e = Event::new(1462290389642521);
+    e.facets.insert("timestamp".to_owned(), "soon".to_owned());
+    assert!(!filter.apply(&mut e));
+    assert_eq!(e.time, 1462290389642521);
+    assert_eq!(e.facets["tags"], "_dateparsefailure");
+    assert!(!e.facets.contains_key("received_at"));
+  }
+}
@@ -0,0 +1,41 @@
+#[cfg(test)]
+mod docker_test {
+  use log_archive::docker::{container_id, parse_json_file_line, PartialLines};
+
+  #[test]
+  fn test_parse_json_file_line() {
+    let entry = parse_json_file_line("{\"log\":\"Server listening on :: port 22.\\r\\n\",\"stream\":\"stdout\",\"time\":\"2016-05-05T06:41:42.652300123Z\"}").unwrap();
+    assert_eq!(entry.log, "Server listening on :: port 22.\r\n");
+    assert_eq!(entry.stream, "stdout");
+    assert_eq!(entry.time, Some(1462430502652300));
+
+    let entry = parse_json_file_line("{\"log\":\"no time\\n\"}").unwrap();
+    assert_eq!(entry.stream, "");
+    assert_eq!(entry.time, None);
+
+    assert!(parse_json_file_line("{\"stream\":\"stdout\"}").is_err());
+    assert!(parse_json_file_line("{\"log\":\"truncat").is_err());
+  }
+
+  #[test]
+  fn test_container_id() {
+    assert_eq!(container_id("/var/lib/docker/containers/dee5ed93cbb6/dee5ed93cbb6-json.log"),
+               Some("dee5ed93cbb6".to_owned()));
+    assert_eq!(container_id("/var/lib/docker/containers/dee5ed93cbb6/config.v2.json"), None);
+    assert_eq!(container_id("/var/lib/docker/containers/x/-json.log"), None);
+  }
+
+  #[test]
+  fn test_partial_lines() {
+    let mut partials = PartialLines::new(10);
+    assert_eq!(partials.push("a:stdout", 1, "hel", false), None);
+    assert_eq!(partials.push("a:stderr", 2, "oops\n", true), Some((2, "oops\n".to_owned())));
+    assert_eq!(partials.push("a:stdout", 3, "lo\n", true), Some((1, "hello\n".to_owned())));
+
+    // Too long lines are returned in parts
+    assert_eq!(partials.push("a:stdout", 4, "0123456", false), None);
+    assert_eq!(partials.push("a:stdout", 5, "789abc", false),
+               Some((4, "0123456789abc".to_owned())));
+    assert_eq!(partials.push("a:stdout", 6, "d\n", true), Some((6, "d\n".to_owned())));
+  }
+}
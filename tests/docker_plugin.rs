@@ -0,0 +1,133 @@
+#[cfg(test)]
+mod docker_plugin_test {
+  use std::io::Cursor;
+  use rustc_serialize::json::Json;
+  use log_archive::docker_plugin::{read_frame, read_request, write_frame, ContainerInfo,
+                                   EntryReader, LogEntry, ReadLogsQuery};
+  use log_archive::event::Event;
+
+  fn entry(line: &str, partial: bool) -> LogEntry {
+    LogEntry {
+      source: "stdout".to_owned(),
+      time_nano: 1462290089642521000,
+      line: line.as_bytes().to_vec(),
+      partial: partial,
+      partial_last: None,
+    }
+  }
+
+  fn container() -> ContainerInfo {
+    ContainerInfo::from_json(Json::from_str("{\"ContainerID\":\"dee5ed93cbb6f00d\",\"ContainerName\":\"/hungry_jones\",\"ContainerImageName\":\"gogs/gogs\"}").ok().as_ref())
+  }
+
+  #[test]
+  fn test_frames() {
+    let mut out = vec![];
+    write_frame(&entry("hello", false), &mut out);
+    write_frame(&entry("world", true), &mut out);
+    let mut reader = Cursor::new(out);
+    let frame = read_frame(&mut reader, 1024).unwrap().unwrap();
+    assert_eq!(LogEntry::decode(&frame).unwrap(), entry("hello", false));
+    let frame = read_frame(&mut reader, 1024).unwrap().unwrap();
+    assert_eq!(LogEntry::decode(&frame).unwrap(), entry("world", true));
+    assert_eq!(read_frame(&mut reader, 1024).unwrap(), None);
+
+    assert!(read_frame(&mut Cursor::new(vec![0, 0, 4, 0]), 1000).is_err());
+    assert!(read_frame(&mut Cursor::new(vec![0, 0, 0, 4, 1]), 1000).is_err());
+  }
+
+  #[test]
+  fn test_partial_metadata() {
+    // source "stdout", partial_log_metadata { last: true, id: "x", ordinal: 2 }
+    let data = vec![0x0a, 6, b's', b't', b'd', b'o', b'u', b't', 0x2a, 7, 0x08, 1, 0x12, 1,
+                    b'x', 0x18, 2];
+    let decoded = LogEntry::decode(&data).unwrap();
+    assert_eq!(decoded.source, "stdout");
+    assert_eq!(decoded.partial_last, Some(true));
+    assert!(decoded.is_complete());
+    assert!(LogEntry::decode(&[0x0a, 6, b's']).is_err());
+  }
+
+  #[test]
+  fn test_entry_reader() {
+    let mut reader = EntryReader::new(container(), "stdout".to_owned(), 1024);
+    assert!(reader.read_entry(entry("hel", true)).is_none());
+    let e = reader.read_entry(entry("lo", false)).unwrap();
+    assert_eq!(e.time, 1462290089642521);
+    assert_eq!(e.facility, "dee5ed93cbb6");
+    assert_eq!(e.facets["stdout"], "hello");
+    assert_eq!(e.facets["source"], "stdout");
+    assert_eq!(e.facets["container_name"], "hungry_jones");
+    assert_eq!(e.facets["image"], "gogs/gogs");
+  }
+
+  #[test]
+  fn test_read_logs() {
+    let request = Json::from_str("{\"Info\":{\"ContainerID\":\"dee5ed93cbb6f00d\"},\"Config\":{\"Since\":\"0001-01-01T00:00:00Z\",\"Until\":\"2016-05-03T15:41:31Z\",\"Tail\":2,\"Follow\":false}}").unwrap();
+    let query = ReadLogsQuery::from_json(&request);
+    assert_eq!(query,
+               ReadLogsQuery {
+                 container_id: "dee5ed93cbb6f00d".to_owned(),
+                 since: None,
+                 until: Some(1462290091000000),
+                 tail: Some(2),
+                 follow: false,
+               });
+
+    let mut events = vec![];
+    for (ix, &(id, time)) in [("dee5ed93cbb6f00d", 1462290088000000u64),
+                              ("other", 1462290089000000),
+                              ("dee5ed93cbb6f00d", 1462290089000000),
+                              ("dee5ed93cbb6f00d", 1462290090000000),
+                              ("dee5ed93cbb6f00d", 1462290092000000)]
+      .iter()
+      .enumerate() {
+      let mut e = Event::new(time);
+      e.facets.insert("container_id".to_owned(), id.to_owned());
+      e.facets.insert("stdout".to_owned(), format!("line {}", ix));
+      events.push(e);
+    }
+
+    let lines = |query: &ReadLogsQuery, events: Vec<Event>| {
+      let mut out = vec![];
+      let last = query.write_events(events.into_iter(), "stdout", &mut out).unwrap();
+      let mut frames = Cursor::new(out);
+      let mut lines = vec![];
+      while let Some(frame) = read_frame(&mut frames, 1024).unwrap() {
+        lines.push(String::from_utf8(LogEntry::decode(&frame).unwrap().line).unwrap());
+      }
+      let last: Vec<String> = last.iter().map(|e| e.facets["stdout"].clone()).collect();
+      (lines, last)
+    };
+    assert_eq!(lines(&query, events.clone()),
+               (vec!["line 2".to_owned(), "line 3".to_owned()], vec!["line 3".to_owned()]));
+    let query = ReadLogsQuery { tail: None, until: None, ..query };
+    assert_eq!(lines(&query, events.clone()).0,
+               vec!["line 0", "line 2", "line 3", "line 4"]);
+    let query = ReadLogsQuery { tail: Some(0), ..query };
+    assert_eq!(lines(&query, events.clone()), (vec![], vec![]));
+    // Every line of the last time is returned
+    let mut same_time = events[3].clone();
+    same_time.facets.insert("stdout".to_owned(), "line 5".to_owned());
+    events.insert(4, same_time);
+    let query = ReadLogsQuery { tail: None, until: Some(1462290091000000), ..query };
+    assert_eq!(lines(&query, events).1, vec!["line 3", "line 5"]);
+
+    let request = Json::from_str(concat!("{\"Info\":{\"ContainerID\":\"dee5ed93cbb6f00d\"},",
+                                         "\"Config\":{\"Tail\":-1,\"Follow\":true}}"))
+      .unwrap();
+    let query = ReadLogsQuery::from_json(&request);
+    assert_eq!(query.tail, None);
+    assert!(query.follow);
+  }
+
+  #[test]
+  fn test_read_request() {
+    let raw = "POST /LogDriver.StartLogging HTTP/1.1\r\nHost: plugin\r\nContent-Length: 13\r\n\r\n{\"File\":\"/f\"}";
+    let request = read_request(&mut Cursor::new(raw.as_bytes()), 1024).unwrap();
+    assert_eq!(request.method, "POST");
+    assert_eq!(request.path, "/LogDriver.StartLogging");
+    assert_eq!(request.body, b"{\"File\":\"/f\"}".to_vec());
+    assert!(read_request(&mut Cursor::new(raw.as_bytes()), 10).is_err());
+  }
+}
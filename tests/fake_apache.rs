@@ -0,0 +1,80 @@
+#[cfg(test)]
+mod fake_apache_test {
+  use chrono::{TimeZone, UTC};
+  use std::collections::HashMap;
+  use rand::{SeedableRng, StdRng};
+  use log_archive::configuration_items::fake_apache_input::FakeApache;
+  use log_archive::configuration_items::processor::InputProcessor;
+  use log_archive::fake_apache::line_generator;
+
+  fn config(entries: &[(&str, &str)]) -> Option<HashMap<String, String>> {
+    Some(entries.iter().map(|&(k, v)| (k.to_owned(), v.to_owned())).collect())
+  }
+
+  #[test]
+  fn test_seeded_generator() {
+    let start = UTC.timestamp(1462290089, 0);
+    let seed: &[usize] = &[42];
+    let lines = line_generator(&mut StdRng::from_seed(seed), 100, start, 3600);
+    assert_eq!(lines.len(), 100);
+    assert_eq!(lines, line_generator(&mut StdRng::from_seed(seed), 100, start, 3600));
+
+    for window in lines.windows(2) {
+      assert!(window[0].0 <= window[1].0);
+    }
+    for &(time, ref line) in lines.iter() {
+      assert!(time >= start && time < start + ::chrono::Duration::seconds(3600));
+      assert!(line.contains(" HTTP/1.0\" 200 "), "{}", line);
+    }
+  }
+
+  #[test]
+  fn test_input() {
+    let input = FakeApache::new("fake-apache".to_owned());
+    let rx = input.start(&config(&[("seed", "42"), ("count", "5"), ("rate", "0"),
+                                   ("burst", "2"), ("tag", "load")]));
+    // The input stops after `count` lines
+    let events: Vec<_> = rx.iter().collect();
+    assert_eq!(events.len(), 5);
+    for e in events.iter() {
+      assert_eq!(e.facility, "load");
+      assert!(e.facets["stdout"].contains(" HTTP/1.0\" 200 "), "{}", e.facets["stdout"]);
+    }
+  }
+
+  #[test]
+  fn test_seeded_input() {
+    let run = || {
+      let input = FakeApache::new("fake-apache".to_owned());
+      input.start(&config(&[("seed", "7"), ("count", "20"), ("rate", "0"), ("burst", "20"),
+                           ("time_spread", "60"), ("start_time", "1462290089")]))
+        .iter()
+        .map(|e| (e.time, e.facets["stdout"].clone()))
+        .collect::<Vec<_>>()
+    };
+    let events = run();
+    assert_eq!(events.len(), 20);
+    assert_eq!(events, run());
+    for &(time, _) in events.iter() {
+      assert!(time <= 1462290089000000 && time > 1462290029000000, "{}", time);
+    }
+  }
+
+  #[test]
+  #[should_panic(expected = "Invalid value for \"count\": ten")]
+  fn test_invalid_count() {
+    FakeApache::new("fake-apache".to_owned()).start(&config(&[("count", "ten")]));
+  }
+
+  #[test]
+  #[should_panic(expected = "Invalid value for \"rate\": fast")]
+  fn test_invalid_rate() {
+    FakeApache::new("fake-apache".to_owned()).start(&config(&[("rate", "fast")]));
+  }
+
+  #[test]
+  #[should_panic(expected = "Invalid value for \"seed\": -1")]
+  fn test_invalid_seed() {
+    FakeApache::new("fake-apache".to_owned()).start(&config(&[("seed", "-1")]));
+  }
+}
@@ -0,0 +1,103 @@
+#[cfg(test)]
+mod fields_test {
+  use log_archive::event::Event;
+  use log_archive::fields::{parse_json, parse_kv, Arrays, FieldFilter, FieldOptions, Format};
+
+  fn options(flatten: bool, arrays: Arrays, max_depth: usize) -> FieldOptions {
+    FieldOptions {
+      prefix: "".to_owned(),
+      flatten: flatten,
+      arrays: arrays,
+      max_depth: max_depth,
+    }
+  }
+
+  fn pairs(fields: &[(&str, &str)]) -> Vec<(String, String)> {
+    fields.iter().map(|&(k, v)| (k.to_owned(), v.to_owned())).collect()
+  }
+
+  #[test]
+  fn test_json() {
+    let text = concat!(r#"{"level":"info","http":{"status":200,"tls":{"v":"1.3"}},"#,
+                       r#""ports":[80,443],"x":null}"#);
+    assert_eq!(parse_json(text, &options(true, Arrays::Json, 10)).unwrap(),
+               pairs(&[("http.status", "200"),
+                       ("http.tls.v", "1.3"),
+                       ("level", "info"),
+                       ("ports", "[80,443]"),
+                       ("x", "null")]));
+    assert_eq!(parse_json(text, &options(true, Arrays::Index, 2)).unwrap(),
+               pairs(&[("http.status", "200"),
+                       ("http.tls", r#"{"v":"1.3"}"#),
+                       ("level", "info"),
+                       ("ports.0", "80"),
+                       ("ports.1", "443"),
+                       ("x", "null")]));
+    assert_eq!(parse_json(text, &options(false, Arrays::Join, 10)).unwrap(),
+               pairs(&[("http", r#"{"status":200,"tls":{"v":"1.3"}}"#),
+                       ("level", "info"),
+                       ("ports", "80,443"),
+                       ("x", "null")]));
+
+    let mut prefixed = options(true, Arrays::Json, 10);
+    prefixed.prefix = "app.".to_owned();
+    assert_eq!(parse_json(r#"{"a":{"b":true}}"#, &prefixed).unwrap(),
+               pairs(&[("app.a.b", "true")]));
+
+    assert!(parse_json("[1, 2]", &prefixed).is_err());
+    assert!(parse_json("{\"a\":", &prefixed).is_err());
+  }
+
+  #[test]
+  fn test_kv() {
+    let o = options(true, Arrays::Json, 10);
+    assert_eq!(parse_kv(r#"level=info msg="user \"bob\" logged in" admin  took=12ms"#, &o)
+                 .unwrap(),
+               pairs(&[("level", "info"),
+                       ("msg", "user \"bob\" logged in"),
+                       ("admin", "true"),
+                       ("took", "12ms")]));
+    assert_eq!(parse_kv("empty= a=b", &o).unwrap(), pairs(&[("empty", ""), ("a", "b")]));
+
+    assert!(parse_kv("Started GET /health", &o).is_err());
+    assert!(parse_kv(r#"msg="unterminated"#, &o).is_err());
+    assert!(parse_kv("=value", &o).is_err());
+    assert!(parse_kv("", &o).is_err());
+  }
+
+  #[test]
+  fn test_filter() {
+    let filter = FieldFilter {
+      source: "line".to_owned(),
+      format: Format::Kv,
+      options: options(true, Arrays::Json, 10),
+      overwrite: false,
+      failure_facet: "tags".to_owned(),
+      failure_tag: "_kvparsefailure".to_owned(),
+    };
+
+    let mut e = Event::new(0);
+    e.facets.insert("line".to_owned(), "line=other level=warn".to_owned());
+    assert!(filter.apply(&mut e));
+    assert_eq!(e.facets["line"], "line=other level=warn");
+    assert_eq!(e.facets["level"], "warn");
+
+    let mut e = Event::new(0);
+    e.facets.insert("line".to_owned(), "not structured".to_owned());
+    assert!(!filter.apply(&mut e));
+    assert_eq!(e.facets["line"], "not structured");
+    assert_eq!(e.facets["tags"], "_kvparsefailure");
+    assert_eq!(e.facets.len(), 2);
+
+    let mut e = Event::new(0);
+    e.facets.insert("tags".to_owned(), "web".to_owned());
+    e.facets.insert("line".to_owned(), "still not structured".to_owned());
+    assert!(!filter.apply(&mut e));
+    assert_eq!(e.facets["tags"], "web,_kvparsefailure");
+
+    let mut e = Event::new(0);
+    e.facets.insert("message".to_owned(), "level=warn".to_owned());
+    assert!(!filter.apply(&mut e));
+    assert_eq!(e.facets.len(), 1);
+  }
+}
@@ -0,0 +1,115 @@
+#[cfg(test)]
+mod fluent_test {
+  use std::io::{Cursor, Write};
+  use std::iter;
+  use flate2::Compression;
+  use flate2::write::GzEncoder;
+  use log_archive::fluent::{ack_response, decode_request};
+  use log_archive::msgpack::{read_value, write_value, Value};
+
+  fn s(value: &str) -> Value {
+    Value::Str(value.as_bytes().to_vec())
+  }
+
+  fn record() -> Value {
+    Value::Map(vec![(s("log"), s("hello")), (s("source"), s("stdout"))])
+  }
+
+  fn encode(value: &Value) -> Vec<u8> {
+    let mut out = vec![];
+    write_value(value, &mut out);
+    out
+  }
+
+  #[test]
+  fn test_msgpack() {
+    let value = Value::Array(vec![s("tag"),
+                                  Value::UInt(1462290089),
+                                  Value::Map(vec![(s(&iter::repeat("k").take(40).collect::<String>()), Value::Nil)]),
+                                  Value::Bool(true)]);
+    assert_eq!(read_value(&mut Cursor::new(encode(&value)), 1024).unwrap(), value);
+
+    // negative fixint, int16, float64, bin8, fixext8
+    let bytes = vec![0x95, 0xff, 0xd1, 0xff, 0x38, 0xcb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0,
+                     0xc4, 0x01, 0x61, 0xd7, 0x00, 0, 0, 0, 1, 0, 0, 0, 2];
+    assert_eq!(read_value(&mut Cursor::new(bytes), 1024).unwrap(),
+               Value::Array(vec![Value::Int(-1),
+                                 Value::Int(-200),
+                                 Value::Float(1.5),
+                                 Value::Bin(vec![0x61]),
+                                 Value::Ext(0, vec![0, 0, 0, 1, 0, 0, 0, 2])]));
+
+    assert!(read_value(&mut Cursor::new(vec![0xdd, 0xff, 0xff, 0xff, 0xff]), 1024).is_err());
+    assert!(read_value(&mut Cursor::new(vec![0x91; 100]), 1024).is_err());
+    assert!(read_value(&mut Cursor::new(vec![0xc1]), 1024).is_err());
+  }
+
+  #[test]
+  fn test_message_mode() {
+    let message = Value::Array(vec![s("docker.app"), Value::UInt(1462290089), record()]);
+    let request = decode_request(&message, 42, 1024).unwrap();
+    assert_eq!(request.chunk, None);
+    assert_eq!(request.events.len(), 1);
+    let e = &request.events[0];
+    assert_eq!(e.time, 1462290089000000);
+    assert_eq!(e.facility, "docker.app");
+    assert_eq!(e.facets["log"], "hello");
+    assert_eq!(e.facets["source"], "stdout");
+  }
+
+  #[test]
+  fn test_forward_modes() {
+    let event_time = Value::Ext(0, vec![0x57, 0x28, 0xc2, 0xa9, 0x26, 0x4b, 0xf8, 0x68]);
+    let option = Value::Map(vec![(s("chunk"), s("p8n9gmxTQVC8/nh2wlKKeQ=="))]);
+    let message = Value::Array(vec![s("app"),
+                                    Value::Array(vec![Value::Array(vec![event_time.clone(),
+                                                                        record()]),
+                                                      Value::Array(vec![Value::UInt(0),
+                                                                        record()])]),
+                                    option]);
+    let request = decode_request(&message, 42, 1024).unwrap();
+    assert_eq!(request.chunk, Some("p8n9gmxTQVC8/nh2wlKKeQ==".to_owned()));
+    assert_eq!(request.events.len(), 2);
+    assert_eq!(request.events[0].time, 1462289065642513);
+    assert_eq!(request.events[1].time, 0);
+
+    let mut entries = encode(&Value::Array(vec![event_time, record()]));
+    entries.extend(encode(&Value::Array(vec![Value::UInt(1), record()])));
+    let packed = Value::Array(vec![s("app"), Value::Bin(entries.clone())]);
+    assert_eq!(decode_request(&packed, 42, 1024).unwrap().events.len(), 2);
+
+    let mut gz = GzEncoder::new(vec![], Compression::Default);
+    gz.write_all(&entries).unwrap();
+    let compressed = Value::Array(vec![s("app"),
+                                       Value::Bin(gz.finish().unwrap()),
+                                       Value::Map(vec![(s("compressed"), s("gzip"))])]);
+    let request = decode_request(&compressed, 42, 1024).unwrap();
+    assert_eq!(request.events.len(), 2);
+    assert_eq!(request.events[1].time, 1000000);
+    assert!(decode_request(&compressed, 42, 10).is_err());
+
+    // Entries packed as a string are not UTF-8, compressed or not
+    let packed = Value::Array(vec![s("app"), Value::Str(entries.clone())]);
+    let packed = read_value(&mut Cursor::new(encode(&packed)), 1024).unwrap();
+    let request = decode_request(&packed, 42, 1024).unwrap();
+    assert_eq!(request.events.len(), 2);
+    assert_eq!(request.events[0].time, 1462289065642513);
+    assert_eq!(request.events[0].facets["log"], "hello");
+    let mut gz = GzEncoder::new(vec![], Compression::Default);
+    gz.write_all(&entries).unwrap();
+    let compressed = Value::Array(vec![s("app"),
+                                       Value::Str(gz.finish().unwrap()),
+                                       Value::Map(vec![(s("compressed"), s("gzip"))])]);
+    let compressed = read_value(&mut Cursor::new(encode(&compressed)), 1024).unwrap();
+    assert_eq!(decode_request(&compressed, 42, 1024).unwrap().events.len(), 2);
+
+    assert!(decode_request(&Value::Array(vec![Value::UInt(1), record()]), 42, 1024).is_err());
+    assert!(decode_request(&Value::Array(vec![s("app"), Value::UInt(1)]), 42, 1024).is_err());
+  }
+
+  #[test]
+  fn test_ack_response() {
+    assert_eq!(read_value(&mut Cursor::new(ack_response("abc")), 1024).unwrap(),
+               Value::Map(vec![(s("ack"), s("abc"))]));
+  }
+}
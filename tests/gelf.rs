@@ -0,0 +1,83 @@
+#[cfg(test)]
+mod gelf_test {
+  use std::io::Write;
+  use std::time::Duration;
+  use flate2::Compression;
+  use flate2::write::{GzEncoder, ZlibEncoder};
+  use log_archive::gelf::{decompress, parse_message, ChunkAssembler};
+
+  static MESSAGE: &'static str = "{\"version\":\"1.1\",\"host\":\"example.org\",\"short_message\":\"A short message\",\"full_message\":\"Backtrace here\\n\\nmore stuff\",\"timestamp\":1385053862.3072,\"level\":1,\"_user_id\":9001,\"_some_info\":\"foo\",\"_id\":\"ignored\"}";
+
+  fn chunk(id: u8, sequence: u8, count: u8, data: &[u8]) -> Vec<u8> {
+    let mut chunk = vec![0x1e, 0x0f, 0, 0, 0, 0, 0, 0, 0, id, sequence, count];
+    chunk.extend_from_slice(data);
+    chunk
+  }
+
+  #[test]
+  fn test_parse_message() {
+    let e = parse_message(MESSAGE.as_bytes(), 42, "message", "gelf").unwrap();
+    assert_eq!(e.time, 1385053862307200);
+    assert_eq!(e.hostname, "example.org");
+    assert_eq!(e.facility, "gelf");
+    assert_eq!(e.facets["message"], "A short message");
+    assert_eq!(e.facets["full_message"], "Backtrace here\n\nmore stuff");
+    assert_eq!(e.facets["severity"], "alert");
+    assert_eq!(e.facets["user_id"], "9001");
+    assert_eq!(e.facets["some_info"], "foo");
+    assert!(!e.facets.contains_key("id"));
+
+    let e = parse_message(b"{\"short_message\":\"hi\",\"facility\":\"app\"}", 42, "message", "gelf")
+      .unwrap();
+    assert_eq!(e.time, 42);
+    assert_eq!(e.facility, "app");
+
+    assert!(parse_message(b"{\"host\":\"example.org\"}", 42, "message", "gelf").is_err());
+    assert!(parse_message(b"[1]", 42, "message", "gelf").is_err());
+  }
+
+  #[test]
+  fn test_decompress() {
+    let mut gz = GzEncoder::new(vec![], Compression::Default);
+    gz.write_all(MESSAGE.as_bytes()).unwrap();
+    let gz = gz.finish().unwrap();
+    assert_eq!(decompress(&gz, 1024).unwrap(), MESSAGE.as_bytes());
+
+    let mut zlib = ZlibEncoder::new(vec![], Compression::Default);
+    zlib.write_all(MESSAGE.as_bytes()).unwrap();
+    let zlib = zlib.finish().unwrap();
+    assert_eq!(decompress(&zlib, 1024).unwrap(), MESSAGE.as_bytes());
+
+    assert_eq!(decompress(MESSAGE.as_bytes(), 1024).unwrap(), MESSAGE.as_bytes());
+    assert!(decompress(&zlib, 10).is_err());
+  }
+
+  #[test]
+  fn test_chunks() {
+    let mut assembler = ChunkAssembler::new(Duration::from_secs(5), 2, 1024);
+    assert_eq!(assembler.add(b"{}").unwrap(), Some(b"{}".to_vec()));
+
+    assert_eq!(assembler.add(&chunk(1, 1, 2, b"world")).unwrap(), None);
+    assert_eq!(assembler.add(&chunk(2, 0, 3, b"a")).unwrap(), None);
+    // Duplicates are ignored
+    assert_eq!(assembler.add(&chunk(1, 1, 2, b"world")).unwrap(), None);
+    assert_eq!(assembler.add(&chunk(1, 0, 2, b"hello ")).unwrap(),
+               Some(b"hello world".to_vec()));
+    assert_eq!(assembler.pending(), 1);
+
+    // Over max_pending, the oldest message is dropped
+    assert_eq!(assembler.add(&chunk(3, 0, 2, b"x")).unwrap(), None);
+    assert_eq!(assembler.add(&chunk(4, 0, 2, b"y")).unwrap(), None);
+    assert_eq!(assembler.pending(), 2);
+    assert_eq!(assembler.add(&chunk(2, 1, 3, b"b")).unwrap(), None);
+
+    assert!(assembler.add(&chunk(5, 3, 2, b"z")).is_err());
+    assert!(assembler.add(&chunk(5, 0, 129, b"z")).is_err());
+    assert!(assembler.add(&[0x1e, 0x0f, 1]).is_err());
+
+    let mut assembler = ChunkAssembler::new(Duration::from_millis(0), 10, 1024);
+    assert_eq!(assembler.add(&chunk(1, 0, 2, b"a")).unwrap(), None);
+    ::std::thread::sleep(Duration::from_millis(5));
+    assert_eq!(assembler.add(&chunk(1, 1, 2, b"b")).unwrap(), None);
+  }
+}
@@ -0,0 +1,80 @@
+#[cfg(test)]
+mod grok_test {
+  use chrono::{TimeZone, UTC};
+  use rand::{SeedableRng, StdRng};
+  use log_archive::event::Event;
+  use log_archive::fake_apache::apache_line;
+  use log_archive::grok::{Grok, GrokFilter};
+
+  fn message(text: &str) -> Event {
+    let mut e = Event::new(1462290089642521);
+    e.facets.insert("message".to_owned(), text.to_owned());
+    e
+  }
+
+  #[test]
+  fn test_combined_apache_log() {
+    let seed: &[usize] = &[42];
+    let line = apache_line(&mut StdRng::from_seed(seed), UTC.timestamp(1462290089, 0));
+    let pattern = Grok::new().compile("^%{COMBINEDAPACHELOG}$").unwrap();
+    let facets = pattern.captures(&line).expect(&line);
+    let get = |name: &str| facets.iter().find(|&&(ref f, _)| f == name).map(|f| f.1.clone());
+
+    assert_eq!(get("timestamp"), Some("03/May/2016:15:41:29 +0000".to_owned()));
+    assert_eq!(get("httpversion"), Some("1.0".to_owned()));
+    assert_eq!(get("response"), Some("200".to_owned()));
+    assert!(line.starts_with(&format!("{} - - ", get("clientip").unwrap())));
+    assert!(get("request").unwrap().starts_with('/'));
+    assert!(get("agent").unwrap().starts_with("\"Mozilla/"));
+    assert_eq!(get("rawrequest"), None);
+  }
+
+  #[test]
+  fn test_custom_patterns() {
+    let mut grok = Grok::new();
+    grok.add_pattern("QUEUE", r"q-[a-z]+");
+    let pattern = grok.compile(r"%{QUEUE:queue} took %{NUMBER:ms:float}ms on %{IP}").unwrap();
+    assert_eq!(pattern.captures("q-mail took 12.5ms on 10.0.0.1"),
+               Some(vec![("queue".to_owned(), "q-mail".to_owned()),
+                         ("ms".to_owned(), "12.5".to_owned())]));
+    assert_eq!(pattern.captures("q-mail took a while"), None);
+
+    assert!(grok.compile("%{NOPE}").is_err());
+    assert!(grok.compile("%{WORD").is_err());
+    grok.add_pattern("LOOP", "a%{LOOP}");
+    assert!(grok.compile("%{LOOP}").is_err());
+  }
+
+  #[test]
+  fn test_filter() {
+    let grok = Grok::new();
+    let filter = GrokFilter {
+      source: "message".to_owned(),
+      patterns: vec![grok.compile(r"^%{LOGLEVEL:level} %{GREEDYDATA:message}$").unwrap(),
+                     grok.compile(r"^%{WORD:program}: %{GREEDYDATA:detail}$").unwrap()],
+      overwrite: false,
+      failure_facet: "tags".to_owned(),
+      failure_tag: "_grokparsefailure".to_owned(),
+    };
+
+    let mut e = message("sshd: session opened");
+    assert!(filter.apply(&mut e));
+    assert_eq!(e.facets["program"], "sshd");
+    assert_eq!(e.facets["detail"], "session opened");
+
+    // Existing facets are kept unless overwrite is set
+    let mut e = message("WARN disk is full");
+    assert!(filter.apply(&mut e));
+    assert_eq!(e.facets["level"], "WARN");
+    assert_eq!(e.facets["message"], "WARN disk is full");
+
+    let mut e = message("");
+    e.facets.insert("tags".to_owned(), "docker".to_owned());
+    assert!(!filter.apply(&mut e));
+    assert_eq!(e.facets["tags"], "docker,_grokparsefailure");
+
+    let mut e = Event::new(0);
+    assert!(!filter.apply(&mut e));
+    assert_eq!(e.facets["tags"], "_grokparsefailure");
+  }
+}
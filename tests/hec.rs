@@ -0,0 +1,374 @@
+#[cfg(test)]
+mod hec_test {
+  use std::sync::mpsc::sync_channel;
+  use std::env;
+  use std::fs;
+  use std::iter;
+  use std::thread;
+  use std::time::{Duration, Instant};
+  use rustc_serialize::json;
+  use log_archive::event::now_micros;
+  use log_archive::hec::ack::{AckLimits, AckRegistry};
+  use log_archive::hec::dead_letter::{self, DeadLetterStore};
+  use log_archive::hec::quota::{IDLE_SECONDS, MAX_TOKENS, Limits, RateLimiter};
+  use log_archive::event::parse_epoch_micros;
+  use log_archive::hec::{Collector, CollectorOptions, HecRequest, SplunkLine, split_payload};
+
+  static LINE: &'static str = "{\"event\":{\"line\":\"2016/05/03 15:41:29 \\u001b[1;36m[T] Custom path: /data/gogs\\u001b[0m\\r\",\"source\":\"stdout\",\"tag\":\"73825581fed7\"},\"time\":\"1462290089.643815\",\"host\":\"default\"}";
+
+  fn request<'a>(channel: Option<&'a str>, body: &'a str) -> HecRequest<'a> {
+    HecRequest {
+      channel: channel,
+      authorization: None,
+      remote_addr: None,
+      body: body,
+    }
+  }
+
+  fn with_ack() -> CollectorOptions {
+    CollectorOptions { ack: true, ..CollectorOptions::default() }
+  }
+
+  #[test]
+  fn test_split_payload() {
+    let body = format!("{}\n{}{}", LINE, LINE, LINE);
+    assert_eq!(split_payload(&body), vec![LINE, LINE, LINE]);
+    assert_eq!(split_payload("{\"a\":\"}{\"}"), vec!["{\"a\":\"}{\"}"]);
+    assert_eq!(split_payload("{\"a\":1} garbage"), vec!["{\"a\":1} garbage"]);
+    assert_eq!(split_payload("{\"a\":{"), vec!["{\"a\":{"]);
+  }
+
+  #[test]
+  fn test_ack_after_confirmation() {
+    let (tx, rx) = sync_channel(10);
+    let collector = Collector::new(tx, with_ack());
+    let query = "{\"acks\":[0]}";
+
+    let reply = collector.post_events(&request(Some("chan"), &format!("{}{}", LINE, LINE)));
+    assert_eq!(reply.body, "{\"text\":\"Success\",\"code\":0,\"ackId\":0}");
+    let reply = collector.query_acks(&request(Some("chan"), query));
+    assert_eq!(reply.body, "{\"acks\":{\"0\":false}}");
+
+    rx.recv().unwrap().confirm();
+    let reply = collector.query_acks(&request(Some("chan"), query));
+    assert_eq!(reply.body, "{\"acks\":{\"0\":false}}");
+
+    rx.recv().unwrap().confirm();
+    let reply = collector.query_acks(&request(Some("chan"), query));
+    assert_eq!(reply.body, "{\"acks\":{\"0\":true}}");
+    // An acknowledged id is only reported once
+    let reply = collector.query_acks(&request(Some("chan"), query));
+    assert_eq!(reply.body, "{\"acks\":{\"0\":false}}");
+  }
+
+  #[test]
+  fn test_ack_ids_per_channel() {
+    let (tx, _rx) = sync_channel(10);
+    let collector = Collector::new(tx, with_ack());
+
+    let reply = collector.post_events(&request(Some("a"), LINE));
+    assert_eq!(reply.body, "{\"text\":\"Success\",\"code\":0,\"ackId\":0}");
+    let reply = collector.post_events(&request(Some("a"), LINE));
+    assert_eq!(reply.body, "{\"text\":\"Success\",\"code\":0,\"ackId\":1}");
+    let reply = collector.post_events(&request(Some("b"), LINE));
+    assert_eq!(reply.body, "{\"text\":\"Success\",\"code\":0,\"ackId\":0}");
+    let reply = collector.post_events(&request(None, LINE));
+    assert_eq!(reply.body, "{\"text\":\"Data channel is missing\",\"code\":10}");
+  }
+
+  #[test]
+  fn test_ack_eviction() {
+    let registry = AckRegistry::with_limits(AckLimits {
+      timeout: Duration::from_secs(60),
+      max_ids_per_channel: 2,
+      max_channels: 2,
+    });
+    for _ in 0..3 {
+      AckRegistry::register(&registry, "a", 1);
+    }
+    // Only the two most recent ids of "a" are kept
+    assert_eq!(registry.len(), (1, 2));
+    assert_eq!(registry.query("a", &[0]), vec![(0, false)]);
+
+    thread::sleep(Duration::from_millis(10));
+    AckRegistry::register(&registry, "b", 1);
+    thread::sleep(Duration::from_millis(10));
+    // A third channel replaces "a", the least recently used
+    AckRegistry::register(&registry, "c", 0);
+    assert_eq!(registry.len(), (2, 2));
+    assert_eq!(registry.query("a", &[1, 2]), vec![(1, false), (2, false)]);
+    assert_eq!(registry.query("c", &[0]), vec![(0, true)]);
+
+    // Ids never confirmed or never queried expire, then their channels
+    registry.expire(Instant::now());
+    assert_eq!(registry.len(), (2, 1));
+    registry.expire(Instant::now() + Duration::from_secs(61));
+    assert_eq!(registry.len(), (0, 0));
+  }
+
+  #[test]
+  fn test_ack_ids_after_eviction() {
+    let registry = AckRegistry::with_limits(AckLimits {
+      timeout: Duration::from_secs(60),
+      max_ids_per_channel: 10,
+      max_channels: 1,
+    });
+    assert_eq!(AckRegistry::register(&registry, "a", 1).0, 0);
+    assert_eq!(AckRegistry::register(&registry, "a", 0).0, 1);
+    // "b" replaces "a", which then replaces "b": ids keep growing
+    assert_eq!(AckRegistry::register(&registry, "b", 0).0, 2);
+    assert_eq!(AckRegistry::register(&registry, "a", 0).0, 3);
+    assert_eq!(registry.query("a", &[0, 1, 3]), vec![(0, false), (1, false), (3, true)]);
+
+    // So do they once idle channels expired
+    registry.expire(Instant::now() + Duration::from_secs(61));
+    assert_eq!(registry.len(), (0, 0));
+    assert_eq!(AckRegistry::register(&registry, "a", 0).0, 4);
+  }
+
+  #[test]
+  fn test_splunk_replies() {
+    let (tx, _rx) = sync_channel(0);
+    let collector = Collector::new(tx, CollectorOptions {
+      queue_size: 1,
+      ..CollectorOptions::default()
+    });
+
+    assert_eq!(collector.health().body, "{\"text\":\"HEC is healthy\",\"code\":17}");
+    assert_eq!(collector.post_events(&request(None, LINE)).body,
+               "{\"text\":\"Success\",\"code\":0}");
+    assert_eq!(collector.post_events(&request(None, "  ")).body,
+               "{\"text\":\"No data\",\"code\":5}");
+    assert_eq!(collector.post_events(&request(None, &format!("{}{{\"time\":\"1\"}}", LINE)))
+                 .body,
+               "{\"text\":\"Event field is required\",\"code\":12,\"invalid-event-number\":1}");
+    assert_eq!(collector.post_events(&request(None, "{\"event\":12}")).body,
+               "{\"text\":\"Invalid data format\",\"code\":6,\"invalid-event-number\":0}");
+    assert_eq!(collector.query_acks(&request(Some("a"), "{\"acks\":[0]}")).body,
+               "{\"text\":\"ACK is disabled\",\"code\":14}");
+
+    // The queue only holds one event
+    assert_eq!(collector.post_events(&request(None, LINE)).body,
+               "{\"text\":\"Server is busy\",\"code\":9}");
+    assert_eq!(collector.health().body,
+               "{\"text\":\"HEC is unhealthy, queues are full\",\"code\":18}");
+  }
+
+  #[test]
+  fn test_busy_batch_is_not_queued() {
+    let (tx, rx) = sync_channel(0);
+    let collector = Collector::new(tx, CollectorOptions {
+      queue_size: 2,
+      ..with_ack()
+    });
+
+    assert_eq!(collector.post_events(&request(Some("chan"), LINE)).body,
+               "{\"text\":\"Success\",\"code\":0,\"ackId\":0}");
+    // One event is pending, the next two do not fit
+    assert_eq!(collector.post_events(&request(Some("chan"), &format!("{}{}", LINE, LINE)))
+                 .body,
+               "{\"text\":\"Server is busy\",\"code\":9}");
+
+    rx.recv().unwrap().confirm();
+    assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+    assert_eq!(collector.query_acks(&request(Some("chan"), "{\"acks\":[0,1]}")).body,
+               "{\"acks\":{\"0\":true,\"1\":false}}");
+  }
+
+  #[test]
+  fn test_tokens() {
+    let (tx, _rx) = sync_channel(10);
+    let collector = Collector::new(tx, CollectorOptions {
+      tokens: vec!["secret".to_owned()],
+      ..CollectorOptions::default()
+    });
+    let mut req = request(None, LINE);
+    assert_eq!(collector.post_events(&req).body, "{\"text\":\"Token is required\",\"code\":2}");
+    req.authorization = Some("Bearer secret");
+    assert_eq!(collector.post_events(&req).body, "{\"text\":\"Invalid authorization\",\"code\":3}");
+    req.authorization = Some("Splunk other");
+    assert_eq!(collector.post_events(&req).body, "{\"text\":\"Invalid token\",\"code\":4}");
+    req.authorization = Some("Splunk secret");
+    assert_eq!(collector.post_events(&req).body, "{\"text\":\"Success\",\"code\":0}");
+  }
+
+  #[test]
+  fn test_parse_epoch_micros() {
+    assert_eq!(parse_epoch_micros("1462290089.642521"), Some(1462290089642521));
+    assert_eq!(parse_epoch_micros("1462290089.6"), Some(1462290089600000));
+    assert_eq!(parse_epoch_micros("1462290089.123456789"), Some(1462290089123456));
+    assert_eq!(parse_epoch_micros("1462290089"), Some(1462290089000000));
+    assert_eq!(parse_epoch_micros(""), None);
+    assert_eq!(parse_epoch_micros("-1.5"), None);
+    assert_eq!(parse_epoch_micros("12a.5"), None);
+    assert_eq!(parse_epoch_micros("1.5e3"), None);
+  }
+
+  #[test]
+  fn test_into_event() {
+    let line = json::decode::<SplunkLine>("{\"event\":{\"line\":\"May  5 06:41:42 sshd[29]: Server listening on :: port 22.\\r\",\"source\":\"stdout\",\"tag\":\"gogs/gogs/hungry_jones/dee5ed93cbb6\",\"attrs\":{\"location\":\"home\"}},\"time\":\"1462430502.652300\",\"host\":\"default\"}").unwrap();
+    let e = line.into_event(42, "line");
+    assert_eq!(e.time, 1462430502652300);
+    assert_eq!(e.hostname, "default");
+    assert_eq!(e.facility, "gogs/gogs/hungry_jones/dee5ed93cbb6");
+    assert_eq!(e.facets.get("line").unwrap(),
+               "May  5 06:41:42 sshd[29]: Server listening on :: port 22.\r");
+    assert_eq!(e.facets.get("source").unwrap(), "stdout");
+    assert_eq!(e.facets.get("location").unwrap(), "home");
+
+    let line = json::decode::<SplunkLine>("{\"event\":{\"line\":\"l\"},\"time\":\"now\"}").unwrap();
+    let e = line.into_event(42, "stdout");
+    assert_eq!(e.time, 42);
+    assert_eq!(e.facets.get("stdout").unwrap(), "l");
+
+    let line = json::decode::<SplunkLine>("{\"event\":{\"line\":\"l\"},\"time\":1462430502}").unwrap();
+    assert_eq!(line.into_event(42, "stdout").time, 1462430502000000);
+  }
+
+  #[test]
+  fn test_dead_letters() {
+    let directory = env::temp_dir().join(format!("kiosque-dlq-{}", now_micros()));
+    let directory = directory.to_string_lossy().into_owned();
+    let (tx, _rx) = sync_channel(10);
+    let collector = Collector::new(tx, CollectorOptions {
+      dead_letters: Some(DeadLetterStore::new(directory.clone(), 2, 1 << 20)),
+      ..CollectorOptions::default()
+    });
+
+    for body in vec!["{\"event\":1}", "{\"time\":\"1\"}", "not json"] {
+      collector.post_events(&HecRequest {
+        channel: None,
+        authorization: Some("Splunk secret"),
+        remote_addr: Some("10.0.2.2:4242"),
+        body: body,
+      });
+    }
+    collector.post_events(&request(None, LINE));
+
+    let letters = dead_letter::list(&directory).unwrap();
+    assert_eq!(letters.len(), 2);
+    let (_, ref letter) = letters[0];
+    assert_eq!(letter.body, "{\"time\":\"1\"}");
+    assert_eq!(letter.token, Some("secret".to_owned()));
+    assert_eq!(letter.remote_addr, Some("10.0.2.2:4242".to_owned()));
+    assert_eq!(letters[1].1.body, "not json");
+
+    let _ = fs::remove_dir_all(&directory);
+  }
+
+  #[test]
+  fn test_dead_letters_size() {
+    let directory = env::temp_dir().join(format!("kiosque-dlq-size-{}", now_micros()));
+    let directory = directory.to_string_lossy().into_owned();
+    let store = DeadLetterStore::new(directory.clone(), 10, 200);
+
+    // The letter just stored is kept, whatever the older ones weigh
+    let first = store.store(&dead_letter::new_letter(None, None, "e".to_owned(), "first"))
+      .unwrap();
+    let body: String = iter::repeat('x').take(100).collect();
+    let second = store.store(&dead_letter::new_letter(None, None, "e".to_owned(), &body))
+      .unwrap();
+    assert!(fs::metadata(&first).is_err());
+    assert!(fs::metadata(&second).is_ok());
+
+    // Letters which cannot fit are refused
+    let body: String = iter::repeat('x').take(200).collect();
+    assert!(store.store(&dead_letter::new_letter(None, None, "e".to_owned(), &body)).is_err());
+    assert_eq!(dead_letter::list(&directory).unwrap().len(), 1);
+
+    let _ = fs::remove_dir_all(&directory);
+  }
+
+  fn limits() -> Limits {
+    Limits {
+      events_per_second: None,
+      bytes_per_second: None,
+      burst_seconds: 1.0,
+      daily_bytes: None,
+    }
+  }
+
+  #[test]
+  fn test_rate_limiter() {
+    let limiter = RateLimiter::new(Limits { events_per_second: Some(10.0), ..limits() });
+    assert_eq!(limiter.check("a", 6, 100), Ok(()));
+    assert_eq!(limiter.check("a", 4, 100), Ok(()));
+    assert_eq!(limiter.check("a", 5, 100), Err(1));
+    // Tokens have their own buckets
+    assert_eq!(limiter.check("b", 10, 100), Ok(()));
+    // A request larger than the bucket goes through when the bucket is full
+    assert_eq!(limiter.check("c", 50, 100), Ok(()));
+    assert!(limiter.check("c", 1, 100).is_err());
+    assert_eq!(limiter.throttled(), vec![("a".to_owned(), 5), ("c".to_owned(), 1)]);
+
+    let limiter = RateLimiter::new(Limits { daily_bytes: Some(1000), ..limits() });
+    assert_eq!(limiter.check("a", 1, 600), Ok(()));
+    assert!(limiter.check("a", 1, 600).unwrap_err() > 0);
+    assert_eq!(limiter.check("a", 1, 400), Ok(()));
+
+    // A refunded request does not count
+    let limiter = RateLimiter::new(Limits { daily_bytes: Some(1000), ..limits() });
+    assert_eq!(limiter.check("a", 1, 600), Ok(()));
+    limiter.refund("a", 1, 600);
+    assert_eq!(limiter.check("a", 1, 600), Ok(()));
+  }
+
+  #[test]
+  fn test_tokens_cap() {
+    let limiter = RateLimiter::new(Limits { daily_bytes: Some(1000), ..limits() });
+    for i in 0..MAX_TOKENS {
+      assert_eq!(limiter.check(&i.to_string(), 1, 1), Ok(()));
+    }
+    // New tokens share the limits of requests without a token
+    assert_eq!(limiter.check("a", 1, 600), Ok(()));
+    assert!(limiter.check("b", 1, 600).is_err());
+    assert!(limiter.check("", 1, 600).is_err());
+    assert_eq!(limiter.len(), MAX_TOKENS + 1);
+    assert_eq!(limiter.check("0", 1, 600), Ok(()));
+  }
+
+  #[test]
+  fn test_throttled_reply() {
+    let (tx, _rx) = sync_channel(10);
+    let collector = Collector::new(tx, CollectorOptions {
+      limits: Limits { bytes_per_second: Some(1.0), ..limits() },
+      ..CollectorOptions::default()
+    });
+    let mut req = request(None, LINE);
+    req.authorization = Some("Splunk 0123456789abcdef");
+    assert_eq!(collector.post_events(&req).body, "{\"text\":\"Success\",\"code\":0}");
+    let reply = collector.post_events(&req);
+    assert_eq!(reply.body, "{\"text\":\"Too many requests\",\"code\":9}");
+    assert!(reply.retry_after.unwrap() > 0);
+    assert_eq!(collector.throttled(&req).body, "{\"01234567...\":1}");
+
+    let (tx, _rx) = sync_channel(10);
+    let collector = Collector::new(tx, CollectorOptions {
+      tokens: vec!["0123456789abcdef".to_owned()],
+      limits: Limits { bytes_per_second: Some(1.0), ..limits() },
+      ..CollectorOptions::default()
+    });
+    assert_eq!(collector.throttled(&request(None, "")).body,
+               "{\"text\":\"Token is required\",\"code\":2}");
+    assert_eq!(collector.throttled(&req).body, "{}");
+  }
+
+  #[test]
+  fn test_idle_tokens_eviction() {
+    let limiter = RateLimiter::new(Limits { events_per_second: Some(10.0), ..limits() });
+    assert_eq!(limiter.check("a", 1, 100), Ok(()));
+    assert_eq!(limiter.check("b", 1, 100), Ok(()));
+    assert_eq!(limiter.len(), 2);
+    limiter.expire(Instant::now());
+    assert_eq!(limiter.len(), 2);
+    limiter.expire(Instant::now() + Duration::from_secs(IDLE_SECONDS));
+    assert_eq!(limiter.len(), 0);
+
+    // The daily quota of a token is kept until the day ends
+    let limiter = RateLimiter::new(Limits { daily_bytes: Some(1000), ..limits() });
+    assert_eq!(limiter.check("a", 1, 600), Ok(()));
+    limiter.expire(Instant::now() + Duration::from_secs(IDLE_SECONDS));
+    assert_eq!(limiter.len(), 1);
+    assert!(limiter.check("a", 1, 600).is_err());
+  }
+}
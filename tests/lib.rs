@@ -1,5 +1,32 @@
 
 mod capnp;
 mod config;
+mod cri;
+mod date;
+mod docker;
+mod docker_plugin;
+mod fake_apache;
+mod fields;
+mod fluent;
+mod gelf;
+mod grok;
+mod hec;
+mod lines;
+mod multiline;
+mod loki;
+mod otlp;
+mod replay;
+mod sanitize;
+mod severity;
+mod stdin;
+mod syslog;
+mod tail;
+mod transport;
 
-
+extern crate chrono;
+extern crate flate2;
+extern crate hyper;
+extern crate log_archive;
+extern crate rand;
+extern crate regex;
+extern crate rustc_serialize;
@@ -0,0 +1,71 @@
+#[cfg(test)]
+mod lines_test {
+  use std::io::{BufReader, Cursor};
+  use std::sync::mpsc::sync_channel;
+  use log_archive::lines::{self, Codec, LineOptions};
+
+  fn options(codec: Codec) -> LineOptions {
+    LineOptions {
+      codec: codec,
+      line_facet: "message".to_owned(),
+      tag: "tcp".to_owned(),
+      max_line: 8,
+    }
+  }
+
+  #[test]
+  fn test_read_line() {
+    // A tiny buffer makes lines span several reads
+    let mut reader = BufReader::with_capacity(3, Cursor::new(&b"short\r\nway too long line\n\nlast"[..]));
+    assert_eq!(lines::read_line(&mut reader, 8).unwrap(), Some(b"short".to_vec()));
+    assert_eq!(lines::read_line(&mut reader, 8).unwrap(), Some(b"way too ".to_vec()));
+    assert_eq!(lines::read_line(&mut reader, 8).unwrap(), Some(vec![]));
+    assert_eq!(lines::read_line(&mut reader, 8).unwrap(), Some(b"last".to_vec()));
+    assert_eq!(lines::read_line(&mut reader, 8).unwrap(), None);
+  }
+
+  #[test]
+  fn test_decode() {
+    let source = vec![("peer_address".to_owned(), "127.0.0.1:4242".to_owned())];
+    let e = lines::decode(b"{\"msg\":1}", 42, &source, &options(Codec::Plain)).unwrap();
+    assert_eq!(e.time, 42);
+    assert_eq!(e.facility, "tcp");
+    assert_eq!(e.facets["message"], "{\"msg\":1}");
+    assert_eq!(e.facets["peer_address"], "127.0.0.1:4242");
+
+    let line = b"{\"time\":\"2016-05-03T15:41:29.642521Z\",\"host\":\"web-1\",\"facility\":\"billing\",\"message\":\"paid\",\"amount\":12.5}";
+    let e = lines::decode(line, 42, &source, &options(Codec::Json)).unwrap();
+    assert_eq!(e.time, 1462290089642521);
+    assert_eq!(e.hostname, "web-1");
+    assert_eq!(e.facility, "billing");
+    assert_eq!(e.facets["message"], "paid");
+    assert_eq!(e.facets["amount"], "12.5");
+
+    let e = lines::decode(b"{\"peer_address\":\"10.0.0.1:1\"}", 42, &source, &options(Codec::Json))
+      .unwrap();
+    assert_eq!(e.facets["peer_address"], "127.0.0.1:4242");
+
+    let e = lines::decode(b"{\"timestamp\":1462290089.5,\"user\":{\"id\":7}}",
+                          42,
+                          &[],
+                          &options(Codec::Json))
+      .unwrap();
+    assert_eq!(e.time, 1462290089500000);
+    assert_eq!(e.facets["user"], "{\"id\":7}");
+
+    // Not an object
+    let e = lines::decode(b"[1, 2]", 42, &[], &options(Codec::Json)).unwrap();
+    assert_eq!(e.facets["message"], "[1, 2]");
+    assert!(lines::decode(b"  ", 42, &[], &options(Codec::Json)).is_none());
+  }
+
+  #[test]
+  fn test_forward() {
+    let (tx, rx) = sync_channel(10);
+    let mut reader = Cursor::new(&b"first\n\nsecond\n"[..]);
+    lines::forward(&mut reader, &[], &tx, &options(Codec::Plain)).unwrap();
+    assert_eq!(rx.try_recv().unwrap().facets["message"], "first");
+    assert_eq!(rx.try_recv().unwrap().facets["message"], "second");
+    assert!(rx.try_recv().is_err());
+  }
+}
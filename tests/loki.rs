@@ -0,0 +1,172 @@
+#[cfg(test)]
+mod loki_test {
+  use std::io::Write;
+  use std::sync::mpsc::sync_channel;
+  use std::time::Duration;
+  use flate2::Compression;
+  use flate2::write::GzEncoder;
+  use hyper::status::StatusCode;
+  use log_archive::loki::{LokiOptions, LokiReceiver, parse_labels};
+  use log_archive::protobuf::Writer;
+  use log_archive::snappy;
+
+  fn options() -> LokiOptions {
+    LokiOptions {
+      line_facet: "message".to_owned(),
+      tag: "loki".to_owned(),
+      max_length: 4096,
+      queue_size: 10,
+    }
+  }
+
+  /// Compresses `data` as a single literal, which is valid if poor snappy.
+  fn snappy_literal(data: &[u8]) -> Vec<u8> {
+    let mut block = vec![];
+    let mut length = data.len();
+    while length >= 0x80 {
+      block.push((length & 0x7f) as u8 | 0x80);
+      length >>= 7;
+    }
+    block.push(length as u8);
+    // A literal whose length - 1 follows on one byte
+    block.push(60 << 2);
+    block.push((data.len() - 1) as u8);
+    block.extend_from_slice(data);
+    block
+  }
+
+  fn entry(seconds: u64, nanos: u64, line: &str) -> Vec<u8> {
+    let mut timestamp = Writer::new();
+    timestamp.varint(1, seconds);
+    timestamp.varint(2, nanos);
+    let mut metadata = Writer::new();
+    metadata.bytes(1, b"trace_id");
+    metadata.bytes(2, b"0242ac120002");
+    let mut entry = Writer::new();
+    entry.bytes(1, &timestamp.buffer);
+    entry.bytes(2, line.as_bytes());
+    entry.bytes(3, &metadata.buffer);
+    entry.buffer
+  }
+
+  fn protobuf_request() -> Vec<u8> {
+    let mut stream = Writer::new();
+    stream.bytes(1, b"{job=\"varlogs\", host=\"web-1\", filename=\"/var/log/syslog\"}");
+    stream.bytes(2, &entry(1462290089, 642521000, "first line"));
+    stream.bytes(2, &entry(1462290090, 1000, "second line"));
+    let mut request = Writer::new();
+    request.bytes(1, &stream.buffer);
+    request.buffer
+  }
+
+  #[test]
+  fn test_snappy() {
+    assert_eq!(snappy::decompress(&[5, 0x10, b'h', b'e', b'l', b'l', b'o'], 16).unwrap(),
+               b"hello".to_vec());
+    // "abc" then an overlapping copy of 6 bytes at offset 3, with a 1 and a 2 byte offset
+    assert_eq!(snappy::decompress(&[9, 0x08, b'a', b'b', b'c', 0x09, 3], 16).unwrap(),
+               b"abcabcabc".to_vec());
+    assert_eq!(snappy::decompress(&[9, 0x08, b'a', b'b', b'c', 0x16, 3, 0], 16).unwrap(),
+               b"abcabcabc".to_vec());
+
+    assert!(snappy::decompress(&[9, 0x08, b'a', b'b', b'c', 0x09, 4], 16).is_err());
+    assert!(snappy::decompress(&[9, 0x08, b'a', b'b', b'c'], 16).is_err());
+    assert!(snappy::decompress(&[5, 0x10, b'h', b'e'], 16).is_err());
+    assert!(snappy::decompress(&[0x80, 0x01], 16).is_err());
+  }
+
+  #[test]
+  fn test_labels() {
+    assert_eq!(parse_labels("{job=\"varlogs\", path=\"C:\\\\logs\", msg=\"say \\\"hi\\\"\"}")
+                 .unwrap(),
+               vec![("job".to_owned(), "varlogs".to_owned()),
+                    ("path".to_owned(), "C:\\logs".to_owned()),
+                    ("msg".to_owned(), "say \"hi\"".to_owned())]);
+    assert!(parse_labels("{}").unwrap().is_empty());
+    assert!(parse_labels("job=\"varlogs\"").is_err());
+    assert!(parse_labels("{job=varlogs}").is_err());
+    assert!(parse_labels("{job=\"varlogs}").is_err());
+  }
+
+  #[test]
+  fn test_protobuf() {
+    let (tx, rx) = sync_channel(10);
+    let receiver = LokiReceiver::new(tx, options());
+    let (status, _) = receiver.push(Some("application/x-protobuf"),
+                                    None,
+                                    &snappy_literal(&protobuf_request()));
+    assert_eq!(status, StatusCode::NoContent);
+
+    let first = rx.recv().unwrap();
+    assert_eq!(first.time, 1462290089642521);
+    assert_eq!(first.facility, "varlogs");
+    assert_eq!(first.hostname, "web-1");
+    assert_eq!(first.facets["message"], "first line");
+    assert_eq!(first.facets["filename"], "/var/log/syslog");
+    assert_eq!(first.facets["trace_id"], "0242ac120002");
+    let second = rx.recv().unwrap();
+    assert_eq!(second.time, 1462290090000001);
+    assert_eq!(second.facets["message"], "second line");
+    assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+  }
+
+  #[test]
+  fn test_json() {
+    let body = "{\"streams\":[{\"stream\":{\"app\":\"shop\"},\"values\":[[\"1462290089642521000\",\"GET /cart\"],[\"1462290089700000000\",\"POST /pay\",{\"user\":\"42\"}]]}]}";
+    let (tx, rx) = sync_channel(10);
+    let receiver = LokiReceiver::new(tx, options());
+    let mut gz = GzEncoder::new(vec![], Compression::Default);
+    gz.write_all(body.as_bytes()).unwrap();
+    let (status, _) = receiver.push(Some("application/json"), Some("gzip"), &gz.finish().unwrap());
+    assert_eq!(status, StatusCode::NoContent);
+
+    let first = rx.recv().unwrap();
+    assert_eq!(first.time, 1462290089642521);
+    assert_eq!(first.facility, "loki");
+    assert_eq!(first.facets["app"], "shop");
+    assert_eq!(first.facets["message"], "GET /cart");
+    let second = rx.recv().unwrap();
+    assert_eq!(second.facets["user"], "42");
+  }
+
+  #[test]
+  fn test_errors() {
+    let (tx, _rx) = sync_channel(1);
+    let receiver = LokiReceiver::new(tx, options());
+    assert_eq!(receiver.push(Some("text/plain"), None, b"").0,
+               StatusCode::UnsupportedMediaType);
+    assert_eq!(receiver.push(Some("application/json"), Some("br"), b"{}").0,
+               StatusCode::UnsupportedMediaType);
+    assert_eq!(receiver.push(Some("application/json"), None, b"{\"streams\":[{}]}").0,
+               StatusCode::BadRequest);
+    assert_eq!(receiver.push(None, None, &protobuf_request()).0,
+               StatusCode::BadRequest);
+
+    // Without a content type, the body is snappy compressed protobuf
+    let request = snappy_literal(&protobuf_request());
+    assert_eq!(receiver.push(None, None, &request).0, StatusCode::NoContent);
+
+    // A timestamp beyond the microseconds range is refused
+    let mut stream = Writer::new();
+    stream.bytes(1, b"{job=\"varlogs\"}");
+    stream.bytes(2, &entry(u64::max_value(), 0, "overflow"));
+    let mut request = Writer::new();
+    request.bytes(1, &stream.buffer);
+    assert_eq!(receiver.push(None, None, &snappy_literal(&request.buffer)).0,
+               StatusCode::BadRequest);
+  }
+
+  #[test]
+  fn test_queue_full() {
+    let (tx, rx) = sync_channel(0);
+    let receiver = LokiReceiver::new(tx, LokiOptions { queue_size: 3, ..options() });
+    let request = snappy_literal(&protobuf_request());
+    assert_eq!(receiver.push(None, None, &request).0, StatusCode::NoContent);
+    // Two entries are pending, the next two do not fit
+    assert_eq!(receiver.push(None, None, &request).0, StatusCode::ServiceUnavailable);
+
+    assert_eq!(rx.recv().unwrap().facets["message"], "first line");
+    assert_eq!(rx.recv().unwrap().facets["message"], "second line");
+    assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+  }
+}
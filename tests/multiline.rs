@@ -0,0 +1,117 @@
+#[cfg(test)]
+mod multiline_test {
+  use std::sync::{Arc, Mutex};
+  use std::thread;
+  use std::time::{Duration, Instant};
+  use regex::Regex;
+  use log_archive::event::{Delivery, Event};
+  use log_archive::multiline::{Multiline, MultilineOptions};
+
+  fn line(facility: &str, text: &str) -> Event {
+    let mut e = Event::new(1462290089642521);
+    e.facility = facility.to_owned();
+    e.facets.insert("source".to_owned(), "stdout".to_owned());
+    e.facets.insert("stdout".to_owned(), text.to_owned());
+    e
+  }
+
+  fn java() -> Multiline {
+    Multiline::new(MultilineOptions {
+      start: None,
+      continuation: Some(Regex::new(r"^(\s+at |Caused by:)").unwrap()),
+      line_facet: "stdout".to_owned(),
+      key_facets: vec!["source".to_owned()],
+      max_lines: 3,
+      max_streams: 2,
+      timeout: Duration::from_millis(500),
+    })
+  }
+
+  #[test]
+  fn test_continuation() {
+    let mut multiline = java();
+    assert!(multiline.push(line("app", "java.lang.NullPointerException")).is_empty());
+    assert!(multiline.push(line("app", "    at Foo.bar(Foo.java:12)")).is_empty());
+    // Another stream does not interfere
+    assert!(multiline.push(line("other", "started")).is_empty());
+
+    let complete = multiline.push(line("app", "GET /health"));
+    assert_eq!(complete.len(), 1);
+    assert_eq!(complete[0].facets["stdout"],
+               "java.lang.NullPointerException\n    at Foo.bar(Foo.java:12)");
+    assert_eq!(multiline.pending(), 2);
+
+    let flushed = multiline.flush();
+    assert_eq!(flushed.len(), 2);
+    assert_eq!(multiline.pending(), 0);
+
+    // A continuation without a pending event, or an event without the line facet
+    assert_eq!(multiline.push(line("app", "    at Foo.bar(Foo.java:12)")).len(), 1);
+    assert_eq!(multiline.push(Event::new(0)).len(), 1);
+  }
+
+  #[test]
+  fn test_max_lines_and_timeout() {
+    let mut multiline = java();
+    multiline.push(line("app", "Exception"));
+    multiline.push(line("app", "  at a"));
+    let complete = multiline.push(line("app", "  at b"));
+    assert_eq!(complete.len(), 1);
+    assert_eq!(complete[0].facets["stdout"], "Exception\n  at a\n  at b");
+
+    multiline.push(line("app", "Exception"));
+    assert!(multiline.expire(Instant::now()).is_empty());
+    let expired = multiline.expire(Instant::now() + Duration::from_millis(600));
+    assert_eq!(expired.len(), 1);
+    assert_eq!(expired[0].facets["stdout"], "Exception");
+  }
+
+  #[test]
+  fn test_max_streams() {
+    let mut multiline = java();
+    assert!(multiline.push(line("a", "Exception in a")).is_empty());
+    thread::sleep(Duration::from_millis(1));
+    assert!(multiline.push(line("b", "Exception in b")).is_empty());
+    // A third stream sends the event idle for the longest
+    let complete = multiline.push(line("c", "Exception in c"));
+    assert_eq!(complete.len(), 1);
+    assert_eq!(complete[0].facets["stdout"], "Exception in a");
+    assert_eq!(multiline.pending(), 2);
+  }
+
+  #[test]
+  fn test_start_pattern_and_acks() {
+    let mut multiline = Multiline::new(MultilineOptions {
+      start: Some(Regex::new(r"^\d{4}-\d{2}-\d{2}").unwrap()),
+      continuation: None,
+      line_facet: "stdout".to_owned(),
+      key_facets: vec![],
+      max_lines: 100,
+      max_streams: 100,
+      timeout: Duration::from_millis(500),
+    });
+
+    let confirmed = Arc::new(Mutex::new(false));
+    let flag = confirmed.clone();
+    let delivery = Delivery::new(3, Box::new(move || *flag.lock().unwrap() = true));
+    let mut events = vec![line("app", "2016-05-03 Traceback (most recent call last):"),
+                          line("app", "  File \"app.py\", line 3"),
+                          line("app", "ValueError: boom")];
+    for e in events.iter_mut() {
+      e.acks.push(delivery.clone());
+    }
+    for e in events {
+      assert!(multiline.push(e).is_empty());
+    }
+
+    let complete = multiline.push(line("app", "2016-05-03 done"));
+    assert_eq!(complete.len(), 1);
+    assert_eq!(complete[0].facets["stdout"],
+               "2016-05-03 Traceback (most recent call last):\n  File \"app.py\", line 3\n\
+                ValueError: boom");
+    // The merged event carries the acknowledgements of its lines
+    assert_eq!(complete[0].acks.len(), 3);
+    complete[0].confirm();
+    assert!(*confirmed.lock().unwrap());
+  }
+}
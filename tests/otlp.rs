@@ -0,0 +1,139 @@
+#[cfg(test)]
+mod otlp_test {
+  use std::io::Write;
+  use std::sync::mpsc::sync_channel;
+  use std::time::Duration;
+  use flate2::Compression;
+  use flate2::write::GzEncoder;
+  use hyper::status::StatusCode;
+  use log_archive::otlp::{OtlpOptions, OtlpReceiver};
+  use log_archive::protobuf::{Reader, Writer, Field};
+
+  fn options() -> OtlpOptions {
+    OtlpOptions {
+      line_facet: "message".to_owned(),
+      tag: "otlp".to_owned(),
+      max_line: 16,
+      max_length: 4096,
+      queue_size: 10,
+    }
+  }
+
+  fn string_attribute(key: &str, value: &str) -> Vec<u8> {
+    let mut any_value = Writer::new();
+    any_value.bytes(1, value.as_bytes());
+    let mut kv = Writer::new();
+    kv.bytes(1, key.as_bytes());
+    kv.bytes(2, &any_value.buffer);
+    kv.buffer
+  }
+
+  fn log_record(body: &str) -> Vec<u8> {
+    let mut record = Writer::new();
+    // time_unix_nano is a fixed64
+    record.buffer.push((1 << 3) | 1);
+    record.buffer.extend_from_slice(&[0xa8, 0x71, 0xb7, 0xc7, 0x16, 0x19, 0x4b, 0x14]);
+    record.varint(2, 17);
+    let mut any_value = Writer::new();
+    any_value.bytes(1, body.as_bytes());
+    record.bytes(5, &any_value.buffer);
+    record.bytes(6, &string_attribute("http.method", "GET"));
+    record.bytes(9, &[0x5b, 0x8e, 0xff, 0xf7, 0x98, 0x03, 0x81, 0x03, 0xd2, 0x69, 0xb6, 0x33,
+                      0x81, 0x3f, 0xc6, 0x0c]);
+    record.buffer
+  }
+
+  fn protobuf_request() -> Vec<u8> {
+    let mut resource = Writer::new();
+    resource.bytes(1, &string_attribute("service.name", "checkout"));
+    resource.bytes(1, &string_attribute("host.name", "web-1"));
+    let mut scope = Writer::new();
+    scope.bytes(1, b"io.kiosque");
+    let mut scope_logs = Writer::new();
+    scope_logs.bytes(1, &scope.buffer);
+    scope_logs.bytes(2, &log_record("payment failed"));
+    scope_logs.bytes(2, &log_record("a body that is way too long"));
+    let mut resource_logs = Writer::new();
+    resource_logs.bytes(1, &resource.buffer);
+    resource_logs.bytes(2, &scope_logs.buffer);
+    let mut request = Writer::new();
+    request.bytes(1, &resource_logs.buffer);
+    request.buffer
+  }
+
+  #[test]
+  fn test_protobuf() {
+    let (tx, rx) = sync_channel(10);
+    let receiver = OtlpReceiver::new(tx, options());
+    let reply = receiver.export(Some("application/x-protobuf"), None, &protobuf_request());
+    assert_eq!(reply.status, StatusCode::Ok);
+
+    // partial_success { rejected_log_records: 1, error_message: ... }
+    let fields: Vec<_> = Reader::new(&reply.body).map(|f| f.unwrap()).collect();
+    assert_eq!(fields.len(), 1);
+    let partial: Vec<_> = Reader::new(fields[0].1.as_bytes().unwrap()).map(|f| f.unwrap()).collect();
+    assert_eq!(partial[0], (1, Field::Varint(1)));
+
+    let e = rx.recv().unwrap();
+    assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+    assert_eq!(e.time, 1462290089642521);
+    assert_eq!(e.facility, "checkout");
+    assert_eq!(e.hostname, "web-1");
+    assert_eq!(e.facets["message"], "payment failed");
+    assert_eq!(e.facets["severity"], "ERROR");
+    assert_eq!(e.facets["severity_number"], "17");
+    assert_eq!(e.facets["trace_id"], "5b8efff798038103d269b633813fc60c");
+    assert_eq!(e.facets["scope.name"], "io.kiosque");
+    assert_eq!(e.facets["resource.service.name"], "checkout");
+    assert_eq!(e.facets["attributes.http.method"], "GET");
+  }
+
+  #[test]
+  fn test_json() {
+    let body = "{\"resourceLogs\":[{\"resource\":{\"attributes\":[{\"key\":\"service.name\",\"value\":{\"stringValue\":\"checkout\"}}]},\"scopeLogs\":[{\"scope\":{\"name\":\"io.kiosque\",\"version\":\"1.0\"},\"logRecords\":[{\"timeUnixNano\":\"1462290089642521000\",\"severityText\":\"Information\",\"body\":{\"kvlistValue\":{\"values\":[{\"key\":\"order\",\"value\":{\"intValue\":\"42\"}}]}},\"attributes\":[{\"key\":\"retry\",\"value\":{\"boolValue\":true}}],\"spanId\":\"EEE19B7EC3C1B174\"},42]}]}]}";
+    let (tx, rx) = sync_channel(10);
+    let receiver = OtlpReceiver::new(tx, options());
+    let reply = receiver.export(Some("application/json; charset=utf-8"), None, body.as_bytes());
+    assert_eq!(reply.status, StatusCode::Ok);
+    assert_eq!(String::from_utf8(reply.body).unwrap(),
+               "{\"partialSuccess\":{\"rejectedLogRecords\":\"1\",\"errorMessage\":\"Log record is not an object\"}}");
+
+    let e = rx.recv().unwrap();
+    assert_eq!(e.time, 1462290089642521);
+    assert_eq!(e.facility, "checkout");
+    assert_eq!(e.facets["message"], "{\"order\":42}");
+    assert_eq!(e.facets["severity"], "Information");
+    assert_eq!(e.facets["span_id"], "eee19b7ec3c1b174");
+    assert_eq!(e.facets["scope.version"], "1.0");
+    assert_eq!(e.facets["attributes.retry"], "true");
+  }
+
+  #[test]
+  fn test_errors() {
+    let (tx, rx) = sync_channel(0);
+    let receiver = OtlpReceiver::new(tx, OtlpOptions { queue_size: 1, ..options() });
+    assert_eq!(receiver.export(Some("text/plain"), None, b"").status,
+               StatusCode::UnsupportedMediaType);
+    assert_eq!(receiver.export(Some("application/json"), None, b"[").status,
+               StatusCode::BadRequest);
+    assert_eq!(receiver.export(Some("application/json"), Some("br"), b"{}").status,
+               StatusCode::UnsupportedMediaType);
+    assert_eq!(receiver.export(Some("application/x-protobuf"), None, &[0x0a, 0x05]).status,
+               StatusCode::BadRequest);
+    assert_eq!(receiver.too_large(Some("application/json")).status,
+               StatusCode::PayloadTooLarge);
+
+    let mut gz = GzEncoder::new(vec![], Compression::Default);
+    gz.write_all(&protobuf_request()).unwrap();
+    let reply = receiver.export(Some("application/x-protobuf"),
+                                Some("gzip"),
+                                &gz.finish().unwrap());
+    assert_eq!(reply.status, StatusCode::Ok);
+
+    // The record of the previous request is pending, nothing of this one is queued
+    let reply = receiver.export(Some("application/x-protobuf"), None, &protobuf_request());
+    assert_eq!(reply.status, StatusCode::ServiceUnavailable);
+    assert!(rx.recv().is_ok());
+    assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+  }
+}
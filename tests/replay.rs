@@ -0,0 +1,98 @@
+#[cfg(test)]
+mod replay_test {
+  use std::env;
+  use std::fs::{self, File};
+  use regex::Regex;
+  use log_archive::configuration_items::file_output::write_log_block;
+  use log_archive::event::{self, Event};
+  use log_archive::logmanager;
+  use log_archive::replay::{Needle, Replay, ReplayQuery};
+
+  fn line(time: u64, text: &str) -> Event {
+    let mut e = Event::new(time);
+    e.facility = "apache".to_owned();
+    e.facets.insert("stdout".to_owned(), text.to_owned());
+    e
+  }
+
+  /// Writes overlapping blocks, each in its own directory so file names never collide.
+  fn archive() -> String {
+    let dir = env::temp_dir().join(format!("kiosque-replay-{}", event::now_micros()));
+    let dir = dir.to_string_lossy().into_owned();
+    write_log_block(&format!("{}/a", dir),
+                    &[line(10, "GET /a"), line(30, "GET /c"), line(50, "POST /e")])
+      .unwrap();
+    write_log_block(&format!("{}/b", dir), &[line(20, "GET /b"), line(40, "POST /d")]).unwrap();
+    write_log_block(&format!("{}/c", dir), &[line(1000, "GET /f"), line(2000, "GET /g")])
+      .unwrap();
+    dir
+  }
+
+  fn query() -> ReplayQuery {
+    ReplayQuery {
+      since: None,
+      until: None,
+      field: None,
+      needle: None,
+    }
+  }
+
+  fn times(replay: Replay) -> Vec<u64> {
+    replay.map(|e| e.time).collect()
+  }
+
+  #[test]
+  fn test_replay() {
+    let dir = archive();
+    let files = logmanager::archive_files(&dir).unwrap();
+    assert_eq!(files.len(), 3);
+
+    let replay = Replay::new(files.clone(), query());
+    assert_eq!(replay.remaining_blocks(), 3);
+    assert_eq!(times(replay), vec![10, 20, 30, 40, 50, 1000, 2000]);
+
+    let mut range = query();
+    range.since = Some(25);
+    range.until = Some(45);
+    let replay = Replay::new(files.clone(), range);
+    // The last block is out of the range
+    assert_eq!(replay.remaining_blocks(), 2);
+    assert_eq!(times(replay), vec![30, 40]);
+
+    let mut search = query();
+    search.field = Some("stdout".to_owned());
+    search.needle = Some(Needle::Substring("GET".to_owned()));
+    assert_eq!(times(Replay::new(files.clone(), search)), vec![10, 20, 30, 1000, 2000]);
+
+    let mut search = query();
+    search.needle = Some(Needle::Regex(Regex::new("^POST /[a-d]$").unwrap()));
+    let events: Vec<Event> = Replay::new(files.clone(), search).collect();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].facets["stdout"], "POST /d");
+    assert_eq!(events[0].facility, "apache");
+
+    let mut search = query();
+    search.field = Some("missing".to_owned());
+    search.needle = Some(Needle::Substring("GET".to_owned()));
+    assert!(times(Replay::new(files, search)).is_empty());
+
+    let _ = fs::remove_dir_all(&dir);
+  }
+
+  #[test]
+  fn test_unreadable_blocks() {
+    let dir = archive();
+    let mut files = logmanager::archive_files(&dir).unwrap();
+    let empty = format!("{}/empty.log", dir);
+    File::create(&empty).unwrap();
+    files.push(empty);
+    files.push(format!("{}/missing.log", dir));
+
+    // Empty and missing files are skipped
+    let replay = Replay::new(files, query());
+    assert_eq!(replay.remaining_blocks(), 3);
+    assert_eq!(times(replay), vec![10, 20, 30, 40, 50, 1000, 2000]);
+
+    let _ = fs::remove_dir_all(&dir);
+  }
+}
@@ -0,0 +1,59 @@
+#[cfg(test)]
+mod sanitize_test {
+  use log_archive::event::Event;
+  use log_archive::sanitize::{sanitize, sanitize_event, SanitizeOptions};
+
+  fn options() -> SanitizeOptions {
+    SanitizeOptions {
+      facets: vec!["stdout".to_owned()],
+      keep_whitespace: true,
+      collapse_replacements: false,
+      replacement: "?".to_owned(),
+    }
+  }
+
+  #[test]
+  fn test_gogs_line() {
+    let line = "2016/05/03 15:41:29 \u{1b}[1;33m[W] Custom config '/data/gogs/conf/app.ini' not \
+                found\u{1b}[0m\r";
+    assert_eq!(sanitize(line, &options()),
+               "2016/05/03 15:41:29 [W] Custom config '/data/gogs/conf/app.ini' not found");
+  }
+
+  #[test]
+  fn test_sequences() {
+    let o = options();
+    assert_eq!(sanitize("\u{1b}]0;title\u{7}ok\u{1b}]2;t\u{1b}\\!", &o), "ok!");
+    assert_eq!(sanitize("\u{1b}[2K\u{1b}[1Gdone\u{1b}7\u{1b}(B", &o), "done");
+    assert_eq!(sanitize("a\u{0}b\u{7f}c\u{9b}31md", &o), "abcd");
+    assert_eq!(sanitize("10%\r20%\r", &o), "10%20%");
+    assert_eq!(sanitize("at a\n\tat b", &o), "at a\n\tat b");
+    assert_eq!(sanitize("unterminated \u{1b}[1;3", &o), "unterminated ");
+
+    let mut flat = options();
+    flat.keep_whitespace = false;
+    assert_eq!(sanitize("at a\n\tat b", &flat), "at a at b");
+    assert_eq!(sanitize("a\tb\r\nc", &flat), "a b c");
+
+    assert_eq!(sanitize("caf\u{fffd}\u{fffd} ok", &o), "caf\u{fffd}\u{fffd} ok");
+    let mut collapse = options();
+    collapse.collapse_replacements = true;
+    assert_eq!(sanitize("caf\u{fffd}\u{fffd} ok", &collapse), "caf? ok");
+  }
+
+  #[test]
+  fn test_event() {
+    let mut e = Event::new(0);
+    e.facets.insert("stdout".to_owned(), "\u{1b}[1;36m[T] Custom path\u{1b}[0m\r".to_owned());
+    e.facets.insert("source".to_owned(), "std\rout".to_owned());
+    assert!(sanitize_event(&mut e, &options()));
+    assert_eq!(e.facets["stdout"], "[T] Custom path");
+    assert_eq!(e.facets["source"], "std\rout");
+    assert!(!sanitize_event(&mut e, &options()));
+
+    let mut all = options();
+    all.facets = vec![];
+    assert!(sanitize_event(&mut e, &all));
+    assert_eq!(e.facets["source"], "stdout");
+  }
+}
@@ -0,0 +1,70 @@
+#[cfg(test)]
+mod severity_test {
+  use log_archive::event::Event;
+  use log_archive::severity::{Level, Rule, SeverityDetector, SeverityOptions};
+
+  fn detector(rules: Vec<Rule>, default: Option<Level>) -> SeverityDetector {
+    SeverityDetector::new(SeverityOptions {
+      text_facets: vec!["stdout".to_owned()],
+      level_fields: vec!["level".to_owned(), "severity".to_owned()],
+      rules: rules,
+      level_facet: "level".to_owned(),
+      default: default,
+    })
+  }
+
+  #[test]
+  fn test_markers() {
+    let d = detector(vec![], None);
+    assert_eq!(d.detect("2016/05/03 15:41:29 [W] Custom config not found"),
+               Some(Level::Warn));
+    assert_eq!(d.detect("2016/05/03 15:41:29 [T] Custom path: /data/gogs"),
+               Some(Level::Trace));
+    assert_eq!(d.detect("E0503 15:41:29.642521    1 main.go:42] boom"), Some(Level::Error));
+    assert_eq!(d.detect("<11>May  3 15:41:29 host app: failed"), Some(Level::Error));
+    assert_eq!(d.detect(r#"{"level":"warning","msg":"disk"}"#), Some(Level::Warn));
+    assert_eq!(d.detect(r#"{"level":50,"msg":"bunyan"}"#), Some(Level::Error));
+    assert_eq!(d.detect("2016-05-03 15:41:29,642 WARN [main] slow"), Some(Level::Warn));
+    assert_eq!(d.detect("[error] client denied"), Some(Level::Error));
+    assert_eq!(d.detect("time=now level=debug msg=hi"), Some(Level::Debug));
+    assert_eq!(d.detect("FATAL: out of memory"), Some(Level::Fatal));
+    assert_eq!(d.detect("GET /health 200"), None);
+    assert_eq!(d.detect("no error here"), None);
+  }
+
+  #[test]
+  fn test_names() {
+    assert_eq!(Level::from_name("WARNING"), Some(Level::Warn));
+    assert_eq!(Level::from_name("crit"), Some(Level::Fatal));
+    assert_eq!(Level::from_name("notice"), Some(Level::Info));
+    assert_eq!(Level::from_name("3"), Some(Level::Error));
+    assert_eq!(Level::from_name("loud"), None);
+    assert_eq!(Level::Warn.name(), "warn");
+  }
+
+  #[test]
+  fn test_rules_and_events() {
+    let rules = vec![Rule::parse("error:^Traceback").unwrap(),
+                     Rule::parse("debug:healthcheck").unwrap()];
+    assert!(Rule::parse("loud:x").is_err());
+    assert!(Rule::parse("error").is_err());
+    let d = detector(rules, Some(Level::Info));
+
+    let mut e = Event::new(0);
+    e.facets.insert("stdout".to_owned(), "Traceback (most recent call last):".to_owned());
+    assert!(d.apply(&mut e));
+    assert_eq!(e.facets["level"], "error");
+
+    // Level facets, e.g. from the syslog input, win over the text
+    let mut e = Event::new(0);
+    e.facets.insert("stdout".to_owned(), "[W] healthcheck".to_owned());
+    e.facets.insert("severity".to_owned(), "err".to_owned());
+    assert!(d.apply(&mut e));
+    assert_eq!(e.facets["level"], "error");
+
+    let mut e = Event::new(0);
+    e.facets.insert("stdout".to_owned(), "GET /health 200".to_owned());
+    assert!(!d.apply(&mut e));
+    assert_eq!(e.facets["level"], "info");
+  }
+}
@@ -0,0 +1,47 @@
+#[cfg(test)]
+mod stdin_test {
+  use std::io::{BufReader, Cursor};
+  use std::sync::mpsc::sync_channel;
+  use log_archive::configuration_items::stdin_input::{delimiter, forward};
+
+  fn records(input: &[u8], delimiter: u8, max_line: usize) -> Vec<String> {
+    let (tx, rx) = sync_channel(10);
+    // A tiny buffer makes records span several reads
+    let mut reader = BufReader::with_capacity(3, Cursor::new(input));
+    forward(&mut reader, delimiter, "stdin", "message", max_line, &tx).unwrap();
+    drop(tx);
+    rx.iter()
+      .map(|e| {
+        assert_eq!(e.facility, "stdin");
+        e.facets["message"].clone()
+      })
+      .collect()
+  }
+
+  #[test]
+  fn test_newline_delimiter() {
+    assert_eq!(records(b"one\r\ntwo\n\nthree", delimiter("newline"), 1024),
+               vec!["one", "two", "three"]);
+  }
+
+  #[test]
+  fn test_nul_delimiter() {
+    // Carriage returns and newlines belong to the records
+    assert_eq!(records(b"one\r\n\0two\0\0", delimiter("nul"), 1024),
+               vec!["one\r\n", "two"]);
+  }
+
+  #[test]
+  fn test_max_line() {
+    let long: Vec<u8> = (0..100000).map(|_| b'x').chain(b"\nshort\n".iter().cloned()).collect();
+    assert_eq!(records(&long, b'\n', 4), vec!["xxxx", "shor"]);
+  }
+
+  #[test]
+  fn test_returns_when_the_pipeline_stops() {
+    let (tx, rx) = sync_channel(1);
+    drop(rx);
+    let mut reader = Cursor::new(&b"one\ntwo\n"[..]);
+    assert!(forward(&mut reader, b'\n', "stdin", "message", 1024, &tx).is_ok());
+  }
+}
@@ -0,0 +1,93 @@
+#[cfg(test)]
+mod syslog_test {
+  use std::io::Cursor;
+  use chrono::{TimeZone, UTC};
+  use log_archive::syslog::{parse_at, read_frame, StructuredElement};
+
+  #[test]
+  fn test_rfc5424() {
+    let now = UTC.timestamp(1065916800, 0);
+    let m = parse_at("<34>1 2003-10-11T22:14:15.003Z mymachine.example.com su - ID47 - \u{feff}'su root' failed for lonvick on /dev/pts/8", now);
+    assert_eq!(m.facility, 4);
+    assert_eq!(m.severity, 2);
+    assert_eq!(m.timestamp, Some(1065910455003000));
+    assert_eq!(m.hostname, Some("mymachine.example.com".to_owned()));
+    assert_eq!(m.app_name, Some("su".to_owned()));
+    assert_eq!(m.procid, None);
+    assert_eq!(m.msgid, Some("ID47".to_owned()));
+    assert_eq!(m.message, "'su root' failed for lonvick on /dev/pts/8");
+
+    let m = parse_at("<165>1 2003-08-24T05:14:15.000003-07:00 192.0.2.1 myproc 8710 - - %% It's time to make the do-nuts.", now);
+    assert_eq!(m.timestamp, Some(1061727255000003));
+    assert_eq!(m.procid, Some("8710".to_owned()));
+    assert_eq!(m.structured_data, vec![]);
+    assert_eq!(m.message, "%% It's time to make the do-nuts.");
+
+    let m = parse_at("<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 [exampleSDID@32473 iut=\"3\" eventSource=\"Appli\\\"cation\"][examplePriority@32473 class=\"high\"] An application event", now);
+    assert_eq!(m.structured_data,
+               vec![StructuredElement {
+                      id: "exampleSDID@32473".to_owned(),
+                      params: vec![("iut".to_owned(), "3".to_owned()),
+                                   ("eventSource".to_owned(), "Appli\"cation".to_owned())],
+                    },
+                    StructuredElement {
+                      id: "examplePriority@32473".to_owned(),
+                      params: vec![("class".to_owned(), "high".to_owned())],
+                    }]);
+    assert_eq!(m.message, "An application event");
+
+    let e = m.into_event(42, "message");
+    assert_eq!(e.facility, "local4");
+    assert_eq!(e.facets.get("severity").unwrap(), "notice");
+    assert_eq!(e.facets.get("sd.exampleSDID@32473.iut").unwrap(), "3");
+    assert_eq!(e.facets.get("msgid").unwrap(), "ID47");
+  }
+
+  #[test]
+  fn test_rfc3164() {
+    let now = UTC.timestamp(1065916800, 0);
+    let m = parse_at("<34>Oct 11 22:14:15 mymachine su: 'su root' failed for lonvick on /dev/pts/8\n", now);
+    assert_eq!(m.facility, 4);
+    assert_eq!(m.timestamp, Some(1065910455000000));
+    assert_eq!(m.hostname, Some("mymachine".to_owned()));
+    assert_eq!(m.app_name, Some("su".to_owned()));
+    assert_eq!(m.message, "'su root' failed for lonvick on /dev/pts/8");
+
+    let now = UTC.timestamp(1462430502, 0);
+    let m = parse_at("<13>May  5 06:41:42 sshd[29]: Server listening on :: port 22.", now);
+    assert_eq!(m.timestamp, Some(1462430502000000));
+    assert_eq!(m.hostname, None);
+    assert_eq!(m.app_name, Some("sshd".to_owned()));
+    assert_eq!(m.procid, Some("29".to_owned()));
+    assert_eq!(m.message, "Server listening on :: port 22.");
+
+    // Messages from the end of last year
+    let m = parse_at("<13>Dec 31 23:59:00 host app: bye", UTC.timestamp(1483315200, 0));
+    assert_eq!(m.timestamp, Some(1483228740000000));
+
+    let m = parse_at("hello world", now);
+    assert_eq!((m.facility, m.severity), (1, 5));
+    assert_eq!(m.timestamp, None);
+    assert_eq!(m.message, "hello world");
+  }
+
+  #[test]
+  fn test_read_frame() {
+    let mut stream = Cursor::new(b"9 <13>hello<14>world\r\n10 <13>a\nb\nc\n".to_vec());
+    assert_eq!(read_frame(&mut stream, 1024).unwrap(), Some("<13>hello".to_owned()));
+    assert_eq!(read_frame(&mut stream, 1024).unwrap(), Some("<14>world".to_owned()));
+    assert_eq!(read_frame(&mut stream, 1024).unwrap(), Some("<13>a\nb\nc\n".to_owned()));
+    assert_eq!(read_frame(&mut stream, 1024).unwrap(), None);
+
+    let mut stream = Cursor::new(b"99999 <13>hello".to_vec());
+    assert!(read_frame(&mut stream, 1024).is_err());
+
+    // Neither the octet count nor a line is read past its limit
+    let mut stream = Cursor::new(vec![b'1'; 100000]);
+    assert!(read_frame(&mut stream, 1024).is_err());
+    assert!(stream.position() <= 20);
+    let mut stream = Cursor::new(b"<13>hello world\n<14>next".to_vec());
+    assert_eq!(read_frame(&mut stream, 9).unwrap(), Some("<13>hello".to_owned()));
+    assert_eq!(read_frame(&mut stream, 9).unwrap(), Some("<14>next".to_owned()));
+  }
+}
@@ -0,0 +1,134 @@
+#[cfg(test)]
+mod tail_test {
+  use std::env;
+  use std::fs::{self, File, OpenOptions};
+  use std::io::Write;
+  use log_archive::event;
+  use log_archive::tail::{Tailer, load_checkpoints};
+
+  fn temp_dir(name: &str) -> String {
+    let dir = env::temp_dir().join(format!("kiosque-{}-{}", name, event::now_micros()));
+    fs::create_dir_all(&dir).unwrap();
+    dir.to_string_lossy().into_owned()
+  }
+
+  fn append(path: &str, content: &str) {
+    let mut f = OpenOptions::new().create(true).append(true).open(path).unwrap();
+    f.write_all(content.as_bytes()).unwrap();
+  }
+
+  fn poll(tailer: &mut Tailer) -> Vec<String> {
+    let mut lines = vec![];
+    tailer.poll(|_, line, delivery| {
+        lines.push(String::from_utf8(line).unwrap());
+        delivery.confirm();
+        true
+      })
+      .unwrap();
+    lines
+  }
+
+  #[test]
+  fn test_partial_lines_and_rotation() {
+    let dir = temp_dir("rotation");
+    let log = format!("{}/app.log", dir);
+    append(&log, "old\n");
+
+    let mut tailer = Tailer::new(vec![format!("{}/*.log", dir)], None, false, 1024);
+    assert_eq!(poll(&mut tailer), Vec::<String>::new());
+
+    append(&log, "one\ntw");
+    assert_eq!(poll(&mut tailer), vec!["one"]);
+    append(&log, "o\n");
+    assert_eq!(poll(&mut tailer), vec!["two"]);
+
+    // Renamed away, then recreated: the end of the old file comes first
+    append(&log, "three\n");
+    fs::rename(&log, format!("{}/app.log.1", dir)).unwrap();
+    append(&log, "four\n");
+    assert_eq!(poll(&mut tailer), vec!["three", "four"]);
+
+    // Copied then truncated in place
+    File::create(&log).unwrap();
+    append(&log, "5\n");
+    assert_eq!(poll(&mut tailer), vec!["5"]);
+  }
+
+  #[test]
+  fn test_checkpoints() {
+    let dir = temp_dir("checkpoints");
+    let log = format!("{}/app.log", dir);
+    let state = format!("{}/state.json", dir);
+    append(&log, "one\n");
+
+    {
+      let mut tailer = Tailer::new(vec![log.clone()], Some(state.clone()), true, 3);
+      assert_eq!(poll(&mut tailer), vec!["one"]);
+      append(&log, "two-truncated\n");
+      assert_eq!(poll(&mut tailer), vec!["two"]);
+    }
+    assert_eq!(load_checkpoints(&state).unwrap()[&log].offset, 18);
+
+    append(&log, "six\n");
+    let mut tailer = Tailer::new(vec![log.clone()], Some(state.clone()), true, 1024);
+    assert_eq!(poll(&mut tailer), vec!["six"]);
+  }
+
+  #[test]
+  fn test_checkpoints_wait_for_confirmations() {
+    let dir = temp_dir("confirmations");
+    let log = format!("{}/app.log", dir);
+    let state = format!("{}/state.json", dir);
+    append(&log, "one\ntwo\nthree\n");
+
+    let mut tailer = Tailer::new(vec![log.clone()], Some(state.clone()), true, 1024);
+    let mut deliveries = vec![];
+    tailer.poll(|_, _, delivery| {
+        deliveries.push(delivery);
+        true
+      })
+      .unwrap();
+    assert_eq!(load_checkpoints(&state).unwrap()[&log].offset, 0);
+    // Out of order: "two" is not saved before "one"
+    deliveries[1].confirm();
+    assert_eq!(poll(&mut tailer), Vec::<String>::new());
+    assert_eq!(load_checkpoints(&state).unwrap()[&log].offset, 0);
+    deliveries[0].confirm();
+    assert_eq!(poll(&mut tailer), Vec::<String>::new());
+    assert_eq!(load_checkpoints(&state).unwrap()[&log].offset, 8);
+    deliveries[2].confirm();
+    assert_eq!(poll(&mut tailer), Vec::<String>::new());
+    assert_eq!(load_checkpoints(&state).unwrap()[&log].offset, 14);
+  }
+
+  #[test]
+  fn test_refused_lines_are_read_again() {
+    let dir = temp_dir("refused");
+    let log = format!("{}/app.log", dir);
+    append(&log, "one\ntwo\n");
+
+    let mut tailer = Tailer::new(vec![log.clone()], None, true, 1024);
+    let mut lines = vec![];
+    tailer.poll(|_, line, delivery| {
+        delivery.confirm();
+        lines.push(String::from_utf8(line).unwrap());
+        lines.len() < 2
+      })
+      .unwrap();
+    assert_eq!(lines, vec!["one", "two"]);
+    assert_eq!(poll(&mut tailer), vec!["two"]);
+  }
+
+  #[test]
+  fn test_long_lines() {
+    let dir = temp_dir("long");
+    let log = format!("{}/app.log", dir);
+    let long: String = (0..200000).map(|_| 'x').collect();
+    append(&log, &format!("{}\nshort\n{}", long, long));
+
+    let mut tailer = Tailer::new(vec![log.clone()], None, true, 10);
+    assert_eq!(poll(&mut tailer), vec!["xxxxxxxxxx", "short"]);
+    append(&log, "end\n");
+    assert_eq!(poll(&mut tailer), vec!["xxxxxxxxxx"]);
+  }
+}
@@ -0,0 +1,119 @@
+#[cfg(test)]
+mod transport_test {
+  use std::sync::Arc;
+  use std::sync::atomic::{AtomicUsize, Ordering};
+  use log_archive::event::{Delivery, Event};
+  use log_archive::transport::{self, AckReader, BatchReader, Outbox};
+
+  fn line(time: u64, text: &str) -> Event {
+    let mut e = Event::new(time);
+    e.facility = "gogs".to_owned();
+    e.hostname = "default".to_owned();
+    e.facets.insert("stdout".to_owned(), text.to_owned());
+    e
+  }
+
+  #[test]
+  fn test_batches() {
+    let mut stream = transport::encode_batch(7, &[line(1462290089642521, "GET /"),
+                                                  line(1462290089643815, "GET /favicon.ico")]);
+    stream.extend(transport::encode_batch(8, &[]));
+
+    let mut reader = BatchReader::new(1 << 20);
+    let mut batches = vec![];
+    // Frames arrive in arbitrary pieces
+    for chunk in stream.chunks(5) {
+      reader.push(chunk);
+      while let Some(batch) = reader.next_batch().unwrap() {
+        batches.push(batch);
+      }
+    }
+    assert_eq!(batches.len(), 2);
+    let (id, ref events) = batches[0];
+    assert_eq!(id, 7);
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].time, 1462290089642521);
+    assert_eq!(events[0].facility, "gogs");
+    assert_eq!(events[0].hostname, "default");
+    assert_eq!(events[1].facets["stdout"], "GET /favicon.ico");
+    assert_eq!(batches[1].0, 8);
+    assert!(batches[1].1.is_empty());
+
+    let mut reader = BatchReader::new(16);
+    reader.push(&transport::encode_batch(9, &[line(0, "too large")]));
+    assert!(reader.next_batch().is_err());
+  }
+
+  #[test]
+  fn test_token() {
+    let mut stream = transport::encode_token("s3cret");
+    stream.extend(transport::encode_batch(7, &[line(1462290089642521, "GET /")]));
+    let mut reader = BatchReader::new(1 << 20);
+    reader.push(&stream[..5]);
+    assert_eq!(reader.next_token(), Ok(None));
+    reader.push(&stream[5..]);
+    assert_eq!(reader.next_token(), Ok(Some(b"s3cret".to_vec())));
+    assert_eq!(reader.next_batch().unwrap().unwrap().0, 7);
+
+    assert!(transport::same_token(b"s3cret", b"s3cret"));
+    assert!(!transport::same_token(b"s3cret", b"s3creT"));
+    assert!(!transport::same_token(b"s3cret", b"s3cre"));
+
+    // A sender which does not know about tokens
+    let mut reader = BatchReader::new(1 << 20);
+    reader.push(&transport::encode_batch(!0, &[]));
+    assert!(reader.next_token().is_err());
+  }
+
+  #[test]
+  fn test_acks() {
+    let mut reader = AckReader::new();
+    let mut acks = transport::encode_ack(1462290089642521);
+    acks.extend(transport::encode_ack(2));
+    reader.push(&acks[..5]);
+    assert_eq!(reader.next_ack(), None);
+    reader.push(&acks[5..]);
+    assert_eq!(reader.next_ack(), Some(1462290089642521));
+    assert_eq!(reader.next_ack(), Some(2));
+    assert_eq!(reader.next_ack(), None);
+  }
+
+  #[test]
+  fn test_outbox() {
+    let confirmed = Arc::new(AtomicUsize::new(0));
+    let counter = confirmed.clone();
+    let delivery = Delivery::new(3,
+                                 Box::new(move || {
+                                   counter.fetch_add(1, Ordering::SeqCst);
+                                 }));
+    let tracked = |text: &str| {
+      let mut e = line(0, text);
+      e.acks.push(delivery.clone());
+      e
+    };
+
+    let mut outbox = Outbox::new(100, 2);
+    let first = outbox.add(vec![tracked("a"), tracked("b")]).to_vec();
+    outbox.add(vec![tracked("c")]);
+    assert!(outbox.is_full());
+
+    // Both batches are sent again on a new connection, in order
+    {
+      let frames = outbox.frames();
+      assert_eq!(frames.len(), 2);
+      assert_eq!(frames[0], &first[..]);
+      let mut reader = BatchReader::new(1 << 20);
+      reader.push(frames[1]);
+      assert_eq!(reader.next_batch().unwrap().unwrap().0, 101);
+    }
+
+    assert_eq!(outbox.acknowledge(101), 1);
+    assert_eq!(outbox.acknowledge(101), 0);
+    assert!(!outbox.is_full());
+    assert_eq!(confirmed.load(Ordering::SeqCst), 0);
+
+    assert_eq!(outbox.acknowledge(100), 2);
+    assert!(outbox.is_empty());
+    assert_eq!(confirmed.load(Ordering::SeqCst), 1);
+  }
+}